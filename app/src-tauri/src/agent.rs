@@ -1,12 +1,69 @@
-use crate::config::load_settings;
-use reqwest::{Client, Response};
+mod context;
+pub mod conversation;
+pub mod handshake;
+pub mod models;
+mod openai;
+pub mod policy;
+pub mod recording;
+mod resume;
+pub mod rollback;
+mod selection;
+mod sse;
+pub mod staging;
+pub mod usage;
+pub mod validate;
+
+use crate::config::{load_settings, AppSettings};
+use futures_util::future::{AbortHandle, AbortRegistration, Abortable};
+use reqwest::header::HeaderName;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use resume::{ResumeState, MAX_RECONNECTS};
+use selection::{Selection, SelectionScope};
 use serde::{Deserialize, Serialize};
-use std::path::{Path, PathBuf};
-use tauri::{AppHandle, Emitter};
+use sse::{SseDecoder, SseItem};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
 use tokio::fs;
 
 const MAX_FRAME_SIZE: usize = 10 * 1024 * 1024; // 10MB
 
+/// How a single connection's stream ended.
+enum StreamEnd {
+    Finished,
+    Interrupted(String),
+}
+
+/// Where `process_stream` reads the body from.
+enum StreamBody {
+    Live(Response),
+    // frames from a run log, re-encoded
+    Replay(VecDeque<Vec<u8>>),
+}
+
+impl StreamBody {
+    async fn chunk(&mut self) -> Result<Option<Vec<u8>>, String> {
+        match self {
+            StreamBody::Live(response) => response
+                .chunk()
+                .await
+                .map(|chunk| chunk.map(|c| c.to_vec()))
+                .map_err(|e| e.to_string()),
+            StreamBody::Replay(chunks) => Ok(chunks.pop_front()),
+        }
+    }
+}
+
+/// Why a request to the backend produced no stream.
+enum SendError {
+    // 401/403; retrying with the same credentials cannot help
+    Unauthorized(StatusCode),
+    Failed(String),
+}
+
 #[derive(Debug, Serialize)]
 struct ScaffoldRequest {
     project_path: String,
@@ -14,6 +71,9 @@ struct ScaffoldRequest {
     app_name: String,
     brand_color: String,
     image_urls: Vec<String>,
+    history: Vec<conversation::HistoryTurn>,
+    #[serde(flatten)]
+    generation: models::Generation,
 }
 
 #[derive(Debug, Serialize)]
@@ -22,30 +82,492 @@ struct EditRequest {
     relative_path: String,
     content: String,
     user_prompt: String,
+    history: Vec<conversation::HistoryTurn>,
+    context: context::ContextBundle,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    selection: Option<Selection>,
+    // type errors a repair round asks the agent to fix
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    diagnostics: Vec<validate::CheckDiagnostic>,
+    #[serde(flatten)]
+    generation: models::Generation,
+}
+
+/// A request for the agent, whichever provider serves it.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum AgentRequest {
+    Scaffold(ScaffoldRequest),
+    Edit(EditRequest),
+}
+
+impl AgentRequest {
+    fn endpoint(&self) -> &'static str {
+        match self {
+            AgentRequest::Scaffold(_) => "generate",
+            AgentRequest::Edit(_) => "edit",
+        }
+    }
+
+    fn generation(&self) -> &models::Generation {
+        match self {
+            AgentRequest::Scaffold(r) => &r.generation,
+            AgentRequest::Edit(r) => &r.generation,
+        }
+    }
+}
+
+/// Per-call options shared by the agent commands.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RunOptions {
+    pub run_id: Option<String>,
+    // hold writes for review instead of touching the project
+    pub staged: bool,
+    // conversation to continue; defaults to the project's most recent thread
+    pub thread_id: Option<String>,
+    // files the user wants the edit agent to see regardless of imports
+    pub pinned_files: Vec<String>,
+    // byte budget for file contents in the edit context
+    pub context_budget: Option<usize>,
+    // type-check the project after the run and let the agent fix what it finds
+    pub validate: bool,
+    // replaces the project's `tsc --noEmit`
+    pub check_command: Option<String>,
+    pub max_repair_rounds: Option<u32>,
+    // override the model settings for this call
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    // start even though the project is past its soft budget
+    pub over_soft_budget: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum AgentEvent {
-    FileWrite { path: String, content: String },
-    Status { message: String },
-    Done { summary: String, files: Vec<String> },
-    Error { message: String },
+    FileWrite {
+        path: String,
+        content: String,
+    },
+    FileStaged {
+        path: String,
+    },
+    FileDelete {
+        path: String,
+    },
+    FileRename {
+        from: String,
+        to: String,
+    },
+    FilePatch {
+        path: String,
+        diff: String,
+    },
+    // replacement text for the selected region of a selection-scoped edit
+    SelectionEdit {
+        path: String,
+        content: String,
+    },
+    Conflict {
+        path: String,
+        message: String,
+    },
+    // a file operation the project's write policy blocked
+    PolicyViolation {
+        path: String,
+        rule: String,
+        message: String,
+    },
+    Status {
+        message: String,
+    },
+    TextDelta {
+        delta: String,
+    },
+    ToolCallStart {
+        call_id: String,
+        tool: String,
+        #[serde(default)]
+        path: Option<String>,
+    },
+    ToolCallFinish {
+        call_id: String,
+        tool: String,
+        #[serde(default)]
+        path: Option<String>,
+        #[serde(default)]
+        error: Option<String>,
+    },
+    Progress {
+        current: u32,
+        total: u32,
+        #[serde(default)]
+        label: Option<String>,
+    },
+    Done {
+        summary: String,
+        files: Vec<String>,
+        // totals for the run so far once emitted; per request from the backend
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        usage: Option<usage::Usage>,
+    },
+    Error {
+        message: String,
+    },
+    Diagnostic {
+        message: String,
+    },
+    // the backend rejected our credentials
+    Unauthorized {
+        status: u16,
+        message: String,
+    },
+    CheckStarted {
+        round: u32,
+        command: String,
+    },
+    CheckFinished {
+        round: u32,
+        diagnostics: Vec<validate::CheckDiagnostic>,
+    },
+    RepairStarted {
+        round: u32,
+        max_rounds: u32,
+        files: Vec<String>,
+    },
+    // a repair request's `done`; only the run's own request emits `Done`
+    RepairFinished {
+        summary: String,
+        files: Vec<String>,
+    },
+    ValidationFinished {
+        passed: bool,
+        rounds: u32,
+        // diagnostics left when the loop stopped
+        remaining: usize,
+        // why validation was skipped or stopped early
+        reason: Option<String>,
+    },
+    ResumableFailure {
+        message: String,
+        last_event_id: Option<String>,
+        attempts: u32,
+        applied_files: Vec<String>,
+    },
+    Cancelled,
+}
+
+impl AgentEvent {
+    /// How a run that ended with this event is labelled in records; `None`
+    /// for events that do not end a run.
+    fn outcome_label(&self) -> Option<&'static str> {
+        match self {
+            AgentEvent::Done { .. } => Some("done"),
+            AgentEvent::Error { .. } => Some("error"),
+            AgentEvent::ResumableFailure { .. } => Some("interrupted"),
+            AgentEvent::Cancelled => Some("cancelled"),
+            _ => None,
+        }
+    }
+
+    /// Project paths a file operation changes.
+    fn touched_paths(&self) -> Vec<&str> {
+        match self {
+            AgentEvent::FileWrite { path, .. }
+            | AgentEvent::FileDelete { path }
+            | AgentEvent::FilePatch { path, .. }
+            | AgentEvent::SelectionEdit { path, .. } => vec![path],
+            AgentEvent::FileRename { from, to } => vec![from, to],
+            _ => Vec::new(),
+        }
+    }
 }
 
-fn find_double_newline(buf: &[u8]) -> Option<usize> {
-    buf.windows(2).position(|w| w == b"\n\n")
+/// Envelope that tags every emitted event with the run it belongs to.
+#[derive(Debug, Clone, Serialize)]
+struct RunEvent<'a> {
+    run_id: &'a str,
+    project_path: &'a str,
+    #[serde(flatten)]
+    event: &'a AgentEvent,
 }
 
-async fn secure_write_file(base_dir: &Path, rel_path: &str, content: &str) -> Result<(), String> {
-    let target_path = base_dir.join(rel_path);
+/// A single agent run against one project.
+struct AgentRun<'a, R: Runtime> {
+    app: &'a AppHandle<R>,
+    run_id: &'a str,
+    project_path: &'a str,
+    base_path: PathBuf,
+    // shadow directory that receives writes while the run is under review
+    staging: Option<staging::StagingArea>,
+    // originals of every file the run touches, for undo
+    snapshots: Option<rollback::Snapshots>,
+    policy: policy::PolicyGuard,
+    // region a selection-scoped edit is confined to; cleared once the edit's
+    // own request is done, so repairs may touch the whole file
+    selection: Mutex<Option<Arc<SelectionScope>>>,
+    // last terminal event emitted
+    outcome: Mutex<Option<AgentEvent>>,
+    // set while validation sends repair requests
+    repairing: AtomicBool,
+    usage: usage::RunUsage,
+    // the run's log on disk; `None` for replays
+    recorder: Option<recording::Recorder>,
+}
+
+impl<'a, R: Runtime> AgentRun<'a, R> {
+    fn begin(
+        app: &'a AppHandle<R>,
+        run_id: &'a str,
+        project_path: &'a str,
+        staged: bool,
+    ) -> Result<Self, String> {
+        let base_path = PathBuf::from(project_path)
+            .canonicalize()
+            .map_err(|e| e.to_string())?;
+        let policy = policy::PolicyGuard::new(&policy::load(app, project_path)?)?;
+        recording::check_unused(app, project_path, run_id)?;
+
+        // last, so a run that fails to begin leaves nothing on disk
+        let (staging, snapshots) = if staged {
+            (
+                Some(staging::StagingArea::begin(app, run_id, &base_path)?),
+                None,
+            )
+        } else {
+            (
+                None,
+                Some(rollback::Snapshots::begin(app, run_id, &base_path)?),
+            )
+        };
+
+        Ok(Self {
+            app,
+            run_id,
+            project_path,
+            base_path,
+            staging,
+            snapshots,
+            policy,
+            selection: Mutex::new(None),
+            outcome: Mutex::new(None),
+            repairing: AtomicBool::new(false),
+            usage: usage::RunUsage::new(),
+            recorder: None,
+        })
+    }
+
+    /// A run that applies a recorded stream to a scratch directory: no undo
+    /// snapshots and no log of its own.
+    fn replay(
+        app: &'a AppHandle<R>,
+        run_id: &'a str,
+        scratch_path: &'a str,
+        policy: &policy::WritePolicy,
+    ) -> Result<Self, String> {
+        let base_path = PathBuf::from(scratch_path)
+            .canonicalize()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            app,
+            run_id,
+            project_path: scratch_path,
+            base_path,
+            staging: None,
+            snapshots: None,
+            policy: policy::PolicyGuard::new(policy)?,
+            selection: Mutex::new(None),
+            outcome: Mutex::new(None),
+            repairing: AtomicBool::new(false),
+            usage: usage::RunUsage::new(),
+            recorder: None,
+        })
+    }
+
+    /// Starts the run's log; a run that cannot be recorded still runs.
+    fn record(&mut self, action: &str, provider: Provider) {
+        match recording::Recorder::begin(self.app, self, action, provider.name()) {
+            Ok(recorder) => self.recorder = Some(recorder),
+            Err(e) => self.emit(&diagnostic(format!("Run will not be recorded: {}", e))),
+        }
+    }
+
+    fn outcome(&self) -> Option<AgentEvent> {
+        self.outcome.lock().ok().and_then(|o| o.clone())
+    }
+
+    fn set_outcome(&self, event: Option<AgentEvent>) {
+        if let Ok(mut outcome) = self.outcome.lock() {
+            *outcome = event;
+        }
+    }
+
+    fn selection(&self) -> Option<Arc<SelectionScope>> {
+        self.selection.lock().ok().and_then(|s| s.clone())
+    }
+
+    fn end_selection(&self) {
+        if let Ok(mut selection) = self.selection.lock() {
+            *selection = None;
+        }
+    }
+
+    fn emit(&self, event: &AgentEvent) {
+        if self.repairing.load(Ordering::Relaxed) {
+            if let AgentEvent::Done { summary, files, .. } = event {
+                self.set_outcome(Some(event.clone()));
+                return self.emit(&AgentEvent::RepairFinished {
+                    summary: summary.clone(),
+                    files: files.clone(),
+                });
+            }
+        }
+        if let Some(recorder) = &self.recorder {
+            recorder.record(&recording::Record::Event {
+                event: event.clone(),
+            });
+        }
+        if matches!(
+            event,
+            AgentEvent::Done { .. }
+                | AgentEvent::Error { .. }
+                | AgentEvent::ResumableFailure { .. }
+                | AgentEvent::Cancelled
+        ) {
+            self.set_outcome(Some(event.clone()));
+        }
+
+        self.app
+            .emit(
+                "agent_event",
+                RunEvent {
+                    run_id: self.run_id,
+                    project_path: self.project_path,
+                    event,
+                },
+            )
+            .ok();
+    }
+}
+
+/// Abort handles for in-flight agent runs, keyed by run ID.
+#[derive(Default)]
+pub struct AgentRuns {
+    handles: Mutex<HashMap<String, AbortHandle>>,
+}
+
+/// A run id claimed in `AgentRuns`; released when dropped.
+struct ActiveRun<'a> {
+    runs: &'a AgentRuns,
+    run_id: String,
+    registration: Option<AbortRegistration>,
+}
+
+impl Drop for ActiveRun<'_> {
+    fn drop(&mut self) {
+        self.runs.remove(&self.run_id);
+    }
+}
+
+impl AgentRuns {
+    /// Claims `run_id` before the run creates any state on disk, so a
+    /// duplicate id cannot touch the state of the run that owns it.
+    fn register(&self, run_id: &str) -> Result<ActiveRun<'_>, String> {
+        let mut handles = self.handles.lock().map_err(|e| e.to_string())?;
+        if handles.contains_key(run_id) {
+            return Err(format!("Agent run already active: {}", run_id));
+        }
+        let (handle, registration) = AbortHandle::new_pair();
+        handles.insert(run_id.to_string(), handle);
+        Ok(ActiveRun {
+            runs: self,
+            run_id: run_id.to_string(),
+            registration: Some(registration),
+        })
+    }
+
+    fn cancel(&self, run_id: &str) -> Result<(), String> {
+        let handles = self.handles.lock().map_err(|e| e.to_string())?;
+        let handle = handles
+            .get(run_id)
+            .ok_or_else(|| format!("No active agent run: {}", run_id))?;
+        handle.abort();
+        Ok(())
+    }
+
+    fn is_active(&self, run_id: &str) -> bool {
+        self.handles
+            .lock()
+            .map(|handles| handles.contains_key(run_id))
+            .unwrap_or(false)
+    }
+
+    fn remove(&self, run_id: &str) {
+        if let Ok(mut handles) = self.handles.lock() {
+            handles.remove(run_id);
+        }
+    }
+}
+
+/// Per-run directory under app data, e.g. `<app data>/agent-staging/<run id>`.
+fn run_data_dir<R: Runtime>(
+    app: &AppHandle<R>,
+    kind: &str,
+    run_id: &str,
+) -> Result<PathBuf, String> {
+    if run_id.is_empty()
+        || !run_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!("Invalid run id: {}", run_id));
+    }
+
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {}", e))?;
+
+    Ok(data_dir.join(kind).join(run_id))
+}
+
+/// Normalizes an agent-supplied relative path, rejecting absolute paths and `..`.
+fn normalize_rel(rel_path: &str) -> Result<String, String> {
+    let mut parts = Vec::new();
+    for component in Path::new(rel_path).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::CurDir => {}
+            _ => return Err("Security violation: path traversal detected".into()),
+        }
+    }
+    if parts.is_empty() {
+        return Err("Invalid file path".into());
+    }
+    Ok(parts.join("/"))
+}
+
+/// Resolves `rel_path` inside `base_dir`, refusing anything that escapes it.
+async fn secure_path(
+    base_dir: &Path,
+    rel_path: &str,
+    create_parent: bool,
+) -> Result<PathBuf, String> {
+    let target_path = base_dir.join(normalize_rel(rel_path)?);
 
     let parent = target_path.parent().ok_or("Invalid file path")?;
-    fs::create_dir_all(parent)
-        .await
-        .map_err(|e| e.to_string())?;
+    let file_name = target_path.file_name().ok_or("Invalid file path")?;
+    if create_parent {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
 
-    let canonical_parent = fs::canonicalize(parent).await.map_err(|e| e.to_string())?;
+    let canonical_parent = fs::canonicalize(parent)
+        .await
+        .map_err(|e| format!("Failed to resolve {}: {}", rel_path, e))?;
     let canonical_base = fs::canonicalize(base_dir)
         .await
         .map_err(|e| e.to_string())?;
@@ -54,143 +576,817 @@ async fn secure_write_file(base_dir: &Path, rel_path: &str, content: &str) -> Re
         return Err("Security violation: path traversal detected".into());
     }
 
+    Ok(canonical_parent.join(file_name))
+}
+
+async fn secure_write_file(base_dir: &Path, rel_path: &str, content: &str) -> Result<(), String> {
+    let target_path = secure_path(base_dir, rel_path, true).await?;
+
     fs::write(&target_path, content)
         .await
         .map_err(|e| format!("Failed to write {}: {}", rel_path, e))
 }
 
-async fn process_stream(
-    app: &AppHandle,
-    base_path: &Path,
-    mut response: Response,
+async fn secure_delete_file(base_dir: &Path, rel_path: &str) -> Result<(), String> {
+    let target_path = secure_path(base_dir, rel_path, false).await?;
+
+    if !fs::metadata(&target_path)
+        .await
+        .map(|m| m.is_file())
+        .unwrap_or(false)
+    {
+        return Err(format!("Cannot delete {}: not a file", rel_path));
+    }
+
+    fs::remove_file(&target_path)
+        .await
+        .map_err(|e| format!("Failed to delete {}: {}", rel_path, e))
+}
+
+async fn secure_rename_file(base_dir: &Path, from: &str, to: &str) -> Result<(), String> {
+    let from_path = secure_path(base_dir, from, false).await?;
+    let to_path = secure_path(base_dir, to, true).await?;
+
+    if fs::metadata(&to_path).await.is_ok() {
+        return Err(format!(
+            "Cannot rename {} to {}: destination exists",
+            from, to
+        ));
+    }
+
+    fs::rename(&from_path, &to_path)
+        .await
+        .map_err(|e| format!("Failed to rename {} to {}: {}", from, to, e))
+}
+
+/// Applies a unified diff to `original`. A failure means the patch does not
+/// match the current content and is reported as a conflict.
+fn apply_patch(original: &str, diff: &str) -> Result<String, String> {
+    let patch = diffy::Patch::from_str(diff).map_err(|e| format!("Invalid patch: {}", e))?;
+    diffy::apply(original, &patch).map_err(|e| format!("Patch does not apply cleanly: {}", e))
+}
+
+/// Current content of `rel_path` as this run sees it.
+async fn read_for_run<R: Runtime>(
+    run: &AgentRun<'_, R>,
+    rel_path: &str,
+) -> Result<Option<String>, String> {
+    if let Some(staging) = &run.staging {
+        return staging.read(&run.base_path, &normalize_rel(rel_path)?);
+    }
+
+    let target_path = secure_path(&run.base_path, rel_path, false).await?;
+    match fs::read_to_string(&target_path).await {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {}: {}", rel_path, e)),
+    }
+}
+
+async fn write_for_run<R: Runtime>(
+    run: &AgentRun<'_, R>,
+    rel_path: &str,
+    content: &str,
 ) -> Result<(), String> {
-    let mut raw_buf = Vec::new();
+    match &run.staging {
+        Some(staging) => {
+            secure_write_file(&staging.files_dir(), rel_path, content).await?;
+            staging.staged_write(&normalize_rel(rel_path)?)
+        }
+        None => secure_write_file(&run.base_path, rel_path, content).await,
+    }
+}
+
+async fn delete_for_run<R: Runtime>(run: &AgentRun<'_, R>, rel_path: &str) -> Result<(), String> {
+    match &run.staging {
+        Some(staging) => {
+            if read_for_run(run, rel_path).await?.is_none() {
+                return Err(format!("Cannot delete {}: not a file", rel_path));
+            }
+            staging.stage_delete(&normalize_rel(rel_path)?)
+        }
+        None => secure_delete_file(&run.base_path, rel_path).await,
+    }
+}
 
-    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
-        raw_buf.extend_from_slice(&chunk);
+/// Confines a file operation on the target of a selection-scoped edit to the
+/// selected region and merges it into the current content.
+async fn apply_selection_edit<R: Runtime>(
+    run: &AgentRun<'_, R>,
+    scope: &SelectionScope,
+    event: &AgentEvent,
+) -> Result<AgentEvent, AgentEvent> {
+    let error = |message: String| AgentEvent::Error { message };
+    let path = event.touched_paths()[0].to_string();
+    let conflict = |message: String| AgentEvent::Conflict {
+        path: path.clone(),
+        message,
+    };
 
-        if raw_buf.len() > MAX_FRAME_SIZE {
-            return Err("Response exceeded safety limit".into());
+    let replacement = match event {
+        AgentEvent::SelectionEdit { content, .. } => content.clone(),
+        AgentEvent::FileWrite { content, .. } => scope.extract(content).map_err(conflict)?,
+        AgentEvent::FilePatch { diff, .. } => {
+            let patched = apply_patch(&scope.original(), diff).map_err(conflict)?;
+            scope.extract(&patched).map_err(conflict)?
         }
+        _ => return Err(conflict("Agent changed code outside the selection".into())),
+    };
+
+    let current = read_for_run(run, &path)
+        .await
+        .map_err(error)?
+        .ok_or_else(|| conflict("File no longer exists".into()))?;
+    let merged = scope.merge(&current, &replacement).map_err(conflict)?;
+
+    run.policy.check_size(&path, merged.len())?;
+    write_for_run(run, &path, &merged).await.map_err(error)?;
+    Ok(AgentEvent::FileWrite {
+        path,
+        content: merged,
+    })
+}
+
+/// Applies a file operation, returning the event that describes what landed.
+async fn apply_file_op<R: Runtime>(
+    run: &AgentRun<'_, R>,
+    event: &AgentEvent,
+) -> Result<AgentEvent, AgentEvent> {
+    let error = |message: String| AgentEvent::Error { message };
+
+    if let Some(snapshots) = &run.snapshots {
+        for path in event.touched_paths() {
+            // a path undo could not resolve would make the whole run impossible to undo
+            let path = normalize_rel(path).map_err(error)?;
+            snapshots.record(&path).await.map_err(error)?;
+        }
+    }
 
-        while let Some(pos) = find_double_newline(&raw_buf) {
-            let frame_bytes: Vec<u8> = raw_buf.drain(..pos + 2).collect();
+    if let Some(scope) = run.selection() {
+        if event.touched_paths().iter().any(|p| scope.covers(p)) {
+            return apply_selection_edit(run, &scope, event).await;
+        }
+    }
 
-            let frame = match std::str::from_utf8(&frame_bytes) {
-                Ok(s) => s.trim(),
-                Err(_) => continue,
+    match event {
+        AgentEvent::FileWrite { path, content } => {
+            write_for_run(run, path, content).await.map_err(error)?
+        }
+        AgentEvent::FileDelete { path } => delete_for_run(run, path).await.map_err(error)?,
+        AgentEvent::FileRename { from, to } => match &run.staging {
+            Some(_) => {
+                let content = read_for_run(run, from)
+                    .await
+                    .map_err(error)?
+                    .ok_or_else(|| error(format!("Cannot rename {}: not a file", from)))?;
+                if read_for_run(run, to).await.map_err(error)?.is_some() {
+                    return Err(error(format!(
+                        "Cannot rename {} to {}: destination exists",
+                        from, to
+                    )));
+                }
+                write_for_run(run, to, &content).await.map_err(error)?;
+                delete_for_run(run, from).await.map_err(error)?
+            }
+            None => secure_rename_file(&run.base_path, from, to)
+                .await
+                .map_err(error)?,
+        },
+        AgentEvent::FilePatch { path, diff } => {
+            let conflict = |message: String| AgentEvent::Conflict {
+                path: path.clone(),
+                message,
             };
+            let original = read_for_run(run, path)
+                .await
+                .map_err(error)?
+                .ok_or_else(|| conflict("File does not exist".into()))?;
+            let patched = apply_patch(&original, diff).map_err(conflict)?;
+            run.policy.check_size(path, patched.len())?;
+            write_for_run(run, path, &patched).await.map_err(error)?
+        }
+        AgentEvent::SelectionEdit { path, .. } => {
+            return Err(AgentEvent::Conflict {
+                path: path.clone(),
+                message: "Selection edit received for a run without a selection".into(),
+            })
+        }
+        _ => {}
+    }
 
-            if !frame.starts_with("data:") {
-                continue;
+    Ok(event.clone())
+}
+
+fn failure_message(failure: &AgentEvent) -> String {
+    match failure {
+        AgentEvent::Conflict { message, .. }
+        | AgentEvent::PolicyViolation { message, .. }
+        | AgentEvent::Error { message } => message.clone(),
+        _ => "File operation failed".to_string(),
+    }
+}
+
+/// Applies a file operation and emits what landed, or why it did not.
+async fn perform_file_op<R: Runtime>(
+    run: &AgentRun<'_, R>,
+    event: &AgentEvent,
+) -> Result<(), String> {
+    match apply_file_op(run, event).await {
+        Err(failure) => {
+            let message = failure_message(&failure);
+            run.emit(&failure);
+            Err(message)
+        }
+        Ok(applied) => {
+            run.policy.record(event);
+            match (&run.staging, applied.touched_paths().last()) {
+                (Some(_), Some(path)) => run.emit(&AgentEvent::FileStaged {
+                    path: path.to_string(),
+                }),
+                _ => run.emit(&applied),
             }
+            Ok(())
+        }
+    }
+}
 
-            let event: AgentEvent = match serde_json::from_str(&frame[5..]) {
+/// Checks a file operation against the write policy, then performs it. For
+/// providers that produce file operations locally rather than over a stream.
+async fn execute_file_op<R: Runtime>(
+    run: &AgentRun<'_, R>,
+    event: &AgentEvent,
+) -> Result<(), String> {
+    if let Err(violation) = run.policy.check(event) {
+        let failure: AgentEvent = violation.into();
+        let message = failure_message(&failure);
+        run.emit(&failure);
+        return Err(message);
+    }
+    perform_file_op(run, event).await
+}
+
+/// Applies one event from the stream. Returns `true` once the run is finished.
+async fn handle_event<R: Runtime>(run: &AgentRun<'_, R>, event: AgentEvent) -> bool {
+    match &event {
+        AgentEvent::FileWrite { .. }
+        | AgentEvent::FileDelete { .. }
+        | AgentEvent::FilePatch { .. }
+        | AgentEvent::SelectionEdit { .. }
+        | AgentEvent::FileRename { .. } => {
+            // failures are reported as events; the stream carries on
+            let _ = perform_file_op(run, &event).await;
+            false
+        }
+        AgentEvent::Done {
+            summary,
+            files,
+            usage,
+        } => {
+            if let Some(usage) = usage {
+                run.usage.add(usage);
+            }
+            run.emit(&AgentEvent::Done {
+                summary: summary.clone(),
+                files: files.clone(),
+                usage: Some(run.usage.total()),
+            });
+            true
+        }
+        AgentEvent::Error { .. } => {
+            run.emit(&event);
+            true
+        }
+        // status, text deltas, tool calls and progress are informational
+        _ => {
+            run.emit(&event);
+            false
+        }
+    }
+}
+
+fn diagnostic(message: String) -> AgentEvent {
+    AgentEvent::Diagnostic { message }
+}
+
+async fn process_stream<R: Runtime>(
+    run: &AgentRun<'_, R>,
+    mut body: StreamBody,
+    state: &mut ResumeState,
+) -> Result<StreamEnd, String> {
+    let mut decoder = SseDecoder::new(MAX_FRAME_SIZE);
+    state.begin_stream();
+    if let Some(recorder) = &run.recorder {
+        recorder.stream();
+    }
+
+    loop {
+        let chunk = match body.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) => return Ok(StreamEnd::Interrupted(e)),
+        };
+        if let Some(recorder) = &run.recorder {
+            recorder.chunk(&chunk);
+        }
+
+        let items = decoder.feed(&chunk)?;
+        if let Some(id) = decoder.last_event_id() {
+            state.last_event_id = Some(id.to_string());
+        }
+        state.retry_ms = decoder.retry().or(state.retry_ms);
+
+        for item in items {
+            let frame = match item {
+                SseItem::Event(frame) => {
+                    if let Some(recorder) = &run.recorder {
+                        recorder.frame(&frame);
+                    }
+                    frame
+                }
+                SseItem::Comment(_) => continue,
+                SseItem::Diagnostic(message) => {
+                    run.emit(&diagnostic(message));
+                    continue;
+                }
+            };
+
+            let event: AgentEvent = match serde_json::from_str(&frame.data) {
                 Ok(e) => e,
-                Err(_) => continue,
+                Err(e) => {
+                    run.emit(&diagnostic(format!(
+                        "Malformed `{}` frame ({}): {}",
+                        frame.event,
+                        e,
+                        frame.data.chars().take(200).collect::<String>()
+                    )));
+                    continue;
+                }
             };
 
-            match &event {
-                AgentEvent::FileWrite { path, content } => {
-                    if let Err(e) = secure_write_file(base_path, path, content).await {
-                        app.emit("agent_event", AgentEvent::Error { message: e })
-                            .ok();
-                        continue;
-                    }
-                    app.emit("agent_event", event).ok();
+            if !event.touched_paths().is_empty() {
+                if let Err(violation) = run.policy.check(&event) {
+                    run.emit(&violation.into());
+                    continue;
                 }
-                AgentEvent::Done { .. } | AgentEvent::Error { .. } => {
-                    app.emit("agent_event", event).ok();
-                    return Ok(());
+                // frames replayed after a reconnect
+                if !state.mark_applied(&event, frame.id.as_deref()) {
+                    continue;
                 }
-                _ => {
-                    app.emit("agent_event", event).ok();
+            }
+
+            if handle_event(run, event).await {
+                return Ok(StreamEnd::Finished);
+            }
+        }
+    }
+
+    for item in decoder.finish() {
+        if let SseItem::Diagnostic(message) = item {
+            run.emit(&diagnostic(message));
+        }
+    }
+
+    Ok(StreamEnd::Interrupted("Stream closed unexpectedly".into()))
+}
+
+/// Which service runs the agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Provider {
+    // the FastAPI backend at `api_url`
+    Backend,
+    // an OpenAI-compatible chat-completions endpoint, driven from here
+    OpenAi,
+}
+
+impl Provider {
+    fn name(self) -> &'static str {
+        match self {
+            Provider::Backend => "backend",
+            Provider::OpenAi => "openai",
+        }
+    }
+}
+
+/// The agent backend as configured in settings.
+struct Backend<'a> {
+    client: &'a Client,
+    settings: AppSettings,
+    provider: Provider,
+    // `None` until negotiated, or when the handshake failed
+    capabilities: Option<handshake::Capabilities>,
+}
+
+impl<'a> Backend<'a> {
+    fn new(client: &'a Client, settings: AppSettings) -> Result<Self, String> {
+        let header = settings.api_auth_header.trim();
+        if !header.is_empty() && HeaderName::from_bytes(header.as_bytes()).is_err() {
+            return Err(format!("Invalid API auth header name: {}", header));
+        }
+
+        let provider = match settings.agent_provider.as_str() {
+            "" | "backend" => Provider::Backend,
+            "openai" => Provider::OpenAi,
+            other => return Err(format!("Unknown agent provider: {}", other)),
+        };
+
+        Ok(Self {
+            client,
+            settings,
+            provider,
+            capabilities: None,
+        })
+    }
+
+    async fn load<R: Runtime>(app: &AppHandle<R>, client: &'a Client) -> Result<Self, String> {
+        Self::new(client, load_settings(app.clone()).await?)
+    }
+
+    /// Loads the saved backend and negotiates capabilities with it.
+    async fn connect<R: Runtime>(
+        app: &AppHandle<R>,
+        client: &'a Client,
+        cache: &handshake::BackendCache,
+    ) -> Result<Self, String> {
+        let mut backend = Self::load(app, client).await?;
+        if backend.provider == Provider::Backend {
+            backend.capabilities = handshake::negotiate(&backend, cache).await?;
+        }
+        Ok(backend)
+    }
+
+    fn url(&self, endpoint: &str) -> String {
+        format!(
+            "{}/{}",
+            self.settings.api_url.trim_end_matches('/'),
+            endpoint
+        )
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        let token = self.settings.api_token.trim();
+        if token.is_empty() {
+            return request;
+        }
+        match self.settings.api_auth_header.trim() {
+            "" => request.bearer_auth(token),
+            header => request.header(header, token),
+        }
+    }
+
+    /// Tells the run what the backend only partly supports.
+    fn report_warnings<R: Runtime>(&self, run: &AgentRun<'_, R>) {
+        for warning in self.capabilities.iter().flat_map(|c| c.warnings()) {
+            run.emit(&diagnostic(warning));
+        }
+    }
+
+    /// A POST of `body` to `endpoint`, without the fields the backend does not accept.
+    fn request<T: Serialize, R: Runtime>(
+        &self,
+        run: &AgentRun<'_, R>,
+        endpoint: &str,
+        body: &T,
+    ) -> Result<RequestBuilder, String> {
+        let mut body = serde_json::to_value(body).map_err(|e| e.to_string())?;
+
+        if let Some(capabilities) = &self.capabilities {
+            let dropped = capabilities.filter_request(endpoint, &mut body);
+            if !dropped.is_empty() {
+                run.emit(&diagnostic(format!(
+                    "The agent backend does not accept {} on /{}; sent without it",
+                    dropped.join(", "),
+                    endpoint
+                )));
+            }
+        }
+
+        if let Some(recorder) = &run.recorder {
+            recorder.request(endpoint, &body);
+        }
+
+        Ok(self
+            .authorize(self.client.post(self.url(endpoint)))
+            .header("X-Agent-Protocol", handshake::PROTOCOL_VERSION)
+            .json(&body))
+    }
+
+    /// Runs `request` to completion on the configured provider.
+    async fn run<R: Runtime>(
+        &self,
+        run: &AgentRun<'_, R>,
+        request: &AgentRequest,
+    ) -> Result<(), String> {
+        match self.provider {
+            Provider::Backend => {
+                let http = self.request(run, request.endpoint(), request)?;
+                stream_agent(run, http).await
+            }
+            Provider::OpenAi => openai::run(run, self, request).await,
+        }
+    }
+}
+
+fn unauthorized_message(status: StatusCode) -> String {
+    if status == StatusCode::FORBIDDEN {
+        format!(
+            "The agent backend refused access ({}). The API token in Settings does not have permission for this server.",
+            status
+        )
+    } else {
+        format!(
+            "The agent backend requires authentication ({}). Add or update the API token in Settings.",
+            status
+        )
+    }
+}
+
+async fn send_request(request: RequestBuilder) -> Result<Response, SendError> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| SendError::Failed(format!("Connection failed: {}", e)))?;
+
+    let status = response.status();
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        return Err(SendError::Unauthorized(status));
+    }
+    if !status.is_success() {
+        return Err(SendError::Failed(format!(
+            "Server returned an error ({})",
+            status
+        )));
+    }
+
+    Ok(response)
+}
+
+/// Streams one agent request to completion, reconnecting as needed.
+async fn stream_agent<R: Runtime>(
+    run: &AgentRun<'_, R>,
+    request: RequestBuilder,
+) -> Result<(), String> {
+    let mut state = ResumeState::default();
+    let mut attempts = 0;
+
+    loop {
+        let mut attempt = request
+            .try_clone()
+            .ok_or("Agent request cannot be retried")?;
+        if let Some(id) = &state.last_event_id {
+            attempt = attempt.header("Last-Event-ID", id);
+        }
+
+        let reason = match send_request(attempt).await {
+            Ok(response) => {
+                match process_stream(run, StreamBody::Live(response), &mut state).await? {
+                    StreamEnd::Finished => return Ok(()),
+                    StreamEnd::Interrupted(reason) => reason,
                 }
             }
+            Err(SendError::Unauthorized(status)) => {
+                run.emit(&AgentEvent::Unauthorized {
+                    status: status.as_u16(),
+                    message: unauthorized_message(status),
+                });
+                return Ok(());
+            }
+            Err(SendError::Failed(e)) if attempts == 0 => return Err(e),
+            Err(SendError::Failed(e)) => e,
+        };
+
+        // Without an event id the server cannot pick up where it left off.
+        if state.last_event_id.is_none() || attempts >= MAX_RECONNECTS {
+            run.emit(&AgentEvent::ResumableFailure {
+                message: reason,
+                last_event_id: state.last_event_id.clone(),
+                attempts,
+                applied_files: state.applied_files(),
+            });
+            return Ok(());
         }
+
+        attempts += 1;
+        run.emit(&AgentEvent::Status {
+            message: format!(
+                "Connection lost ({}), reconnecting {}/{}...",
+                reason, attempts, MAX_RECONNECTS
+            ),
+        });
+        tokio::time::sleep(state.backoff(attempts)).await;
     }
+}
 
-    app.emit(
-        "agent_event",
-        AgentEvent::Error {
-            message: "Stream closed unexpectedly".into(),
-        },
-    )
-    .ok();
+/// Runs `work` as the run's cancellable unit of work.
+async fn run_agent<R: Runtime>(
+    run: &AgentRun<'_, R>,
+    mut active: ActiveRun<'_>,
+    work: impl Future<Output = Result<(), String>>,
+) -> Result<(), String> {
+    let registration = active
+        .registration
+        .take()
+        .ok_or("Agent run already started")?;
+
+    let result = Abortable::new(work, registration).await;
+    drop(active);
+
+    match result {
+        Ok(outcome) => outcome,
+        Err(_) => {
+            // The stream was dropped mid-flight; nothing after this point reaches disk.
+            run.emit(&AgentEvent::Cancelled);
+            Ok(())
+        }
+    }
+}
+
+/// Appends a finished run to its conversation thread. Runs that never got a
+/// response are not part of the conversation.
+fn record_turn<R: Runtime>(
+    run: &AgentRun<'_, R>,
+    thread: conversation::Thread,
+    action: &str,
+    prompt: &str,
+) {
+    let Some(outcome) = run.outcome() else {
+        return;
+    };
+    if let Err(e) = conversation::record(run.app, thread, run.run_id, action, prompt, &outcome) {
+        run.emit(&diagnostic(format!("Failed to save conversation: {}", e)));
+    }
+}
 
-    Ok(())
+/// Adds a finished run to the project's usage ledger.
+fn record_usage<R: Runtime>(run: &AgentRun<'_, R>, action: &str) {
+    let Some(outcome) = run.outcome().as_ref().and_then(AgentEvent::outcome_label) else {
+        return;
+    };
+    if let Err(e) = usage::record(run, action, outcome) {
+        run.emit(&diagnostic(format!("Failed to record usage: {}", e)));
+    }
+}
+
+/// Closes the run's log with its final outcome.
+fn finish_recording<R: Runtime>(run: &AgentRun<'_, R>) {
+    if let Some(recorder) = &run.recorder {
+        recorder.finish(run.outcome());
+    }
 }
 
 #[tauri::command]
-pub async fn scaffold_project(
-    app: AppHandle,
+#[allow(clippy::too_many_arguments)]
+pub async fn scaffold_project<R: Runtime>(
+    app: AppHandle<R>,
     client: tauri::State<'_, Client>,
+    runs: tauri::State<'_, AgentRuns>,
+    backends: tauri::State<'_, handshake::BackendCache>,
+    options: Option<RunOptions>,
     project_path: String,
     prompt: String,
     app_name: String,
     brand_color: String,
     image_urls: Vec<String>,
 ) -> Result<(), String> {
-    let settings = load_settings(app.clone()).await?;
+    let backend = Backend::connect(&app, &client, &backends).await?;
+    let options = options.unwrap_or_default();
+    let generation = models::Generation::resolve(&backend.settings, &options)?;
+    usage::check_budget(&app, &project_path, options.over_soft_budget)?;
+    let run_id = options
+        .run_id
+        .clone()
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
 
-    let base_path = PathBuf::from(&project_path)
-        .canonicalize()
-        .map_err(|e| e.to_string())?;
+    let thread = conversation::resolve(&app, &project_path, options.thread_id.as_deref(), &prompt)?;
 
-    let url = format!("{}/generate", settings.api_url.trim_end_matches('/'));
+    // everything that can fail is done before the run's journal and log exist
+    let active = runs.register(&run_id)?;
+    let mut run = AgentRun::begin(&app, &run_id, &project_path, options.staged)?;
+    run.usage.set_model(backend.model_for(&generation));
+    run.record("scaffold", backend.provider);
 
-    let response = client
-        .post(&url)
-        .json(&ScaffoldRequest {
-            project_path,
-            user_prompt: prompt,
-            app_name,
-            brand_color,
-            image_urls,
-        })
-        .send()
-        .await
-        .map_err(|e| format!("Connection failed: {}", e))?;
+    let history = conversation::history(&thread);
 
-    if !response.status().is_success() {
-        return Err(format!("Server returned an error ({})", response.status()));
-    }
+    backend.report_warnings(&run);
+    let request = AgentRequest::Scaffold(ScaffoldRequest {
+        project_path: project_path.clone(),
+        user_prompt: prompt.clone(),
+        app_name,
+        brand_color,
+        image_urls,
+        history: history.clone(),
+        generation,
+    });
 
-    process_stream(&app, &base_path, response).await
+    let work = async {
+        backend.run(&run, &request).await?;
+        if options.validate {
+            validate::repair(&run, &backend, &options, &history).await?;
+        }
+        Ok(())
+    };
+    let result = run_agent(&run, active, work).await;
+    record_turn(&run, thread, "scaffold", &prompt);
+    record_usage(&run, "scaffold");
+    finish_recording(&run);
+    result
 }
 
 #[tauri::command]
-pub async fn edit_project_file(
-    app: AppHandle,
+#[allow(clippy::too_many_arguments)]
+pub async fn edit_project_file<R: Runtime>(
+    app: AppHandle<R>,
     client: tauri::State<'_, Client>,
+    runs: tauri::State<'_, AgentRuns>,
+    backends: tauri::State<'_, handshake::BackendCache>,
+    options: Option<RunOptions>,
     project_path: String,
     relative_path: String,
     content: String,
     prompt: String,
+    selection: Option<Selection>,
 ) -> Result<(), String> {
-    let settings = load_settings(app.clone()).await?;
+    let backend = Backend::connect(&app, &client, &backends).await?;
+    let options = options.unwrap_or_default();
+    let generation = models::Generation::resolve(&backend.settings, &options)?;
+    usage::check_budget(&app, &project_path, options.over_soft_budget)?;
+    let run_id = options
+        .run_id
+        .clone()
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
 
+    let scope = selection
+        .as_ref()
+        .map(|s| SelectionScope::new(&relative_path, &content, s))
+        .transpose()?;
+    let thread = conversation::resolve(&app, &project_path, options.thread_id.as_deref(), &prompt)?;
     let base_path = PathBuf::from(&project_path)
         .canonicalize()
         .map_err(|e| e.to_string())?;
+    let context = context::build(
+        &base_path,
+        &relative_path,
+        &content,
+        &options.pinned_files,
+        options
+            .context_budget
+            .unwrap_or(context::DEFAULT_CONTEXT_BUDGET),
+    )
+    .await?;
 
-    let url = format!("{}/edit", settings.api_url.trim_end_matches('/'));
+    // everything that can fail is done before the run's journal and log exist
+    let active = runs.register(&run_id)?;
+    let mut run = AgentRun::begin(&app, &run_id, &project_path, options.staged)?;
+    run.usage.set_model(backend.model_for(&generation));
+    run.record("edit", backend.provider);
+    run.selection = Mutex::new(scope.map(Arc::new));
 
-    let response = client
-        .post(&url)
-        .json(&EditRequest {
-            project_path,
-            relative_path,
-            content,
-            user_prompt: prompt,
-        })
-        .send()
-        .await
-        .map_err(|e| format!("Connection failed: {}", e))?;
+    let history = conversation::history(&thread);
 
-    if !response.status().is_success() {
-        return Err(format!("Server returned an error ({})", response.status()));
+    backend.report_warnings(&run);
+    let request = AgentRequest::Edit(EditRequest {
+        project_path: project_path.clone(),
+        relative_path,
+        content,
+        user_prompt: prompt.clone(),
+        history: history.clone(),
+        context,
+        selection,
+        diagnostics: Vec::new(),
+        generation,
+    });
+
+    let work = async {
+        backend.run(&run, &request).await?;
+        if options.validate {
+            validate::repair(&run, &backend, &options, &history).await?;
+        }
+        Ok(())
+    };
+    let result = run_agent(&run, active, work).await;
+    record_turn(&run, thread, "edit", &prompt);
+    record_usage(&run, "edit");
+    finish_recording(&run);
+    result
+}
+
+#[tauri::command]
+pub async fn cancel_agent_run(
+    runs: tauri::State<'_, AgentRuns>,
+    run_id: String,
+) -> Result<(), String> {
+    runs.cancel(&run_id)
+}
+
+/// Restores every file the run touched to its pre-run state.
+#[tauri::command]
+pub async fn undo_agent_run<R: Runtime>(
+    app: AppHandle<R>,
+    runs: tauri::State<'_, AgentRuns>,
+    run_id: String,
+) -> Result<Vec<String>, String> {
+    if runs.is_active(&run_id) {
+        return Err("Agent run is still active — cancel it before undoing".to_string());
     }
 
-    process_stream(&app, &base_path, response).await
+    rollback::restore(&app, &run_id).await
 }
@@ -0,0 +1,401 @@
+use crate::project::{build_tree, FileNode, IGNORED_DIRS};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+pub(super) const DEFAULT_CONTEXT_BUDGET: usize = 200 * 1024; // 200KB
+
+const MAX_IMPORT_DEPTH: usize = 2;
+const RESOLVE_EXTENSIONS: &[&str] = &["tsx", "ts", "jsx", "js", "json"];
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Reason {
+    Pinned,
+    Import,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ContextFile {
+    path: String,
+    content: String,
+    reason: Reason,
+}
+
+/// Everything besides the target file that the edit agent gets to see.
+#[derive(Debug, Clone, Serialize)]
+pub(super) struct ContextBundle {
+    tree: Vec<FileNode>,
+    files: Vec<ContextFile>,
+    // files that were wanted but did not fit in the budget
+    omitted: Vec<String>,
+}
+
+/// A `compilerOptions.paths` entry such as `"@/*": ["./src/*"]`.
+#[derive(Debug)]
+struct Alias {
+    prefix: String,
+    targets: Vec<PathBuf>,
+}
+
+fn import_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r#"(?:import|export)\s[^'"]*?from\s*['"]([^'"]+)['"]|import\s*['"]([^'"]+)['"]|(?:require|import)\(\s*['"]([^'"]+)['"]\s*\)"#,
+        )
+        .expect("valid import regex")
+    })
+}
+
+fn trailing_comma_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r",(\s*[}\]])").expect("valid trailing comma regex"))
+}
+
+fn imports(source: &str) -> Vec<String> {
+    import_regex()
+        .captures_iter(source)
+        .filter_map(|c| c.iter().skip(1).flatten().next())
+        .map(|m| m.as_str().to_string())
+        .collect()
+}
+
+/// Drops `//` and `/* */` comments and trailing commas so tsconfig parses as JSON.
+fn strip_jsonc(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    trailing_comma_regex().replace_all(&out, "$1").into_owned()
+}
+
+fn load_aliases(base: &Path) -> Vec<Alias> {
+    let fallback = || {
+        vec![Alias {
+            prefix: "@/".to_string(),
+            targets: vec![PathBuf::new()],
+        }]
+    };
+
+    let Ok(raw) = fs::read_to_string(base.join("tsconfig.json")) else {
+        return fallback();
+    };
+    let Ok(config) = serde_json::from_str::<serde_json::Value>(&strip_jsonc(&raw)) else {
+        return fallback();
+    };
+
+    let options = &config["compilerOptions"];
+    let base_url = PathBuf::from(options["baseUrl"].as_str().unwrap_or("."));
+    let Some(paths) = options["paths"].as_object() else {
+        return fallback();
+    };
+
+    paths
+        .iter()
+        .filter_map(|(pattern, targets)| {
+            let prefix = pattern.strip_suffix('*')?.to_string();
+            let targets = targets
+                .as_array()?
+                .iter()
+                .filter_map(|t| t.as_str()?.strip_suffix('*'))
+                .map(|t| base_url.join(t))
+                .collect();
+            Some(Alias { prefix, targets })
+        })
+        .collect()
+}
+
+/// Lexically normalizes `path`, returning `None` if it climbs out of the project
+/// or passes through an ignored directory.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => {
+                if IGNORED_DIRS.contains(&part.to_string_lossy().as_ref()) {
+                    return None;
+                }
+                out.push(part);
+            }
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !out.pop() {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+fn with_extensions(base: &Path, candidate: &Path) -> Option<PathBuf> {
+    let candidate = normalize(candidate)?;
+    if base.join(&candidate).is_file() {
+        return Some(candidate);
+    }
+
+    let file_name = candidate.file_name()?.to_string_lossy().to_string();
+    for ext in RESOLVE_EXTENSIONS {
+        let with_ext = candidate.with_file_name(format!("{}.{}", file_name, ext));
+        if base.join(&with_ext).is_file() {
+            return Some(with_ext);
+        }
+    }
+    for ext in RESOLVE_EXTENSIONS {
+        let index = candidate.join(format!("index.{}", ext));
+        if base.join(&index).is_file() {
+            return Some(index);
+        }
+    }
+    None
+}
+
+/// Resolves an import specifier from `from` (project-relative) to a project file.
+fn resolve_import(base: &Path, aliases: &[Alias], from: &Path, spec: &str) -> Option<PathBuf> {
+    if spec.starts_with("./") || spec.starts_with("../") {
+        let dir = from.parent().unwrap_or(Path::new(""));
+        return with_extensions(base, &dir.join(spec));
+    }
+
+    aliases
+        .iter()
+        .filter_map(|alias| Some((spec.strip_prefix(&alias.prefix)?, alias)))
+        .flat_map(|(rest, alias)| alias.targets.iter().map(move |t| t.join(rest)))
+        .find_map(|candidate| with_extensions(base, &candidate))
+}
+
+fn read_text(path: &Path) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+fn rel_string(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Assembles the context for editing `target`: the project tree, pinned files,
+/// and whatever `target` imports (transitively, a couple of levels deep), until
+/// the file contents reach `budget` bytes. The target's imports are read from
+/// `content`, the text being sent, which may have unsaved edits.
+///
+/// Walks and reads the project on a blocking thread.
+pub(super) async fn build(
+    base: &Path,
+    target: &str,
+    content: &str,
+    pinned: &[String],
+    budget: usize,
+) -> Result<ContextBundle, String> {
+    let base = base.to_path_buf();
+    let target = target.to_string();
+    let content = content.to_string();
+    let pinned = pinned.to_vec();
+    tokio::task::spawn_blocking(move || assemble(&base, &target, &content, &pinned, budget))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn assemble(
+    base: &Path,
+    target: &str,
+    content: &str,
+    pinned: &[String],
+    budget: usize,
+) -> Result<ContextBundle, String> {
+    let tree = build_tree(base, base).map_err(|e| format!("Failed to build tree: {}", e))?;
+    let aliases = load_aliases(base);
+    let target = normalize(Path::new(target)).ok_or("Invalid target path")?;
+
+    let mut seen: HashSet<PathBuf> = HashSet::from([target.clone()]);
+    let mut wanted: Vec<(PathBuf, Reason)> = Vec::new();
+
+    for pin in pinned {
+        if let Some(path) = normalize(Path::new(pin)) {
+            if seen.insert(path.clone()) {
+                wanted.push((path, Reason::Pinned));
+            }
+        }
+    }
+
+    // breadth-first, so direct imports win the budget over transitive ones
+    let mut queue: VecDeque<(PathBuf, usize)> = VecDeque::from([(target, 0)]);
+    while let Some((file, depth)) = queue.pop_front() {
+        if depth >= MAX_IMPORT_DEPTH {
+            continue;
+        }
+        let source = if depth == 0 {
+            content.to_string()
+        } else {
+            let Some(source) = read_text(&base.join(&file)) else {
+                continue;
+            };
+            source
+        };
+        for spec in imports(&source) {
+            if let Some(path) = resolve_import(base, &aliases, &file, &spec) {
+                if seen.insert(path.clone()) {
+                    wanted.push((path.clone(), Reason::Import));
+                    queue.push_back((path, depth + 1));
+                }
+            }
+        }
+    }
+
+    let mut used = 0;
+    let mut files = Vec::new();
+    let mut omitted = Vec::new();
+
+    for (path, reason) in wanted {
+        let Some(content) = read_text(&base.join(&path)) else {
+            continue;
+        };
+        if used + content.len() > budget {
+            omitted.push(rel_string(&path));
+            continue;
+        }
+        used += content.len();
+        files.push(ContextFile {
+            path: rel_string(&path),
+            content,
+            reason,
+        });
+    }
+
+    Ok(ContextBundle {
+        tree,
+        files,
+        omitted,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(files: &[(&str, &str)]) -> PathBuf {
+        let base = std::env::temp_dir()
+            .join("coding-editor-context")
+            .join(uuid::Uuid::new_v4().to_string());
+        fs::create_dir_all(&base).unwrap();
+        for (path, content) in files {
+            let file = base.join(path);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, content).unwrap();
+        }
+        base
+    }
+
+    #[test]
+    fn strips_comments_and_trailing_commas() {
+        let raw = r#"{
+            // line comment
+            "a": "keep // this", /* block */
+            "b": ["x", "y",],
+        }"#;
+        let value: serde_json::Value = serde_json::from_str(&strip_jsonc(raw)).unwrap();
+        assert_eq!(value["a"], "keep // this");
+        assert_eq!(value["b"], serde_json::json!(["x", "y"]));
+    }
+
+    #[test]
+    fn keeps_escaped_quotes_in_strings() {
+        let raw = r#"{ "a": "say \"/* hi */\"" }"#;
+        let value: serde_json::Value = serde_json::from_str(&strip_jsonc(raw)).unwrap();
+        assert_eq!(value["a"], "say \"/* hi */\"");
+    }
+
+    #[test]
+    fn reads_paths_from_tsconfig() {
+        let base = project(&[(
+            "tsconfig.json",
+            r#"{
+                // comments are allowed
+                "compilerOptions": {
+                    "baseUrl": "src",
+                    "paths": { "~/*": ["./*", "../shared/*"], "exact": ["./exact.ts"] },
+                },
+            }"#,
+        )]);
+        let aliases = load_aliases(&base);
+        assert_eq!(aliases.len(), 1);
+        assert_eq!(aliases[0].prefix, "~/");
+        assert_eq!(
+            aliases[0].targets,
+            vec![PathBuf::from("src/./"), PathBuf::from("src/../shared/")]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_at_alias() {
+        let aliases = load_aliases(&project(&[("tsconfig.json", "not json")]));
+        assert_eq!(aliases[0].prefix, "@/");
+    }
+
+    #[test]
+    fn resolves_relative_and_aliased_imports() {
+        let base = project(&[
+            ("app/screens/home.tsx", ""),
+            ("app/components/button.tsx", ""),
+            ("app/lib/index.ts", ""),
+            ("node_modules/pkg/index.js", ""),
+        ]);
+        let aliases = vec![Alias {
+            prefix: "@/".into(),
+            targets: vec![PathBuf::from("app")],
+        }];
+        let from = Path::new("app/screens/home.tsx");
+        let resolve = |spec: &str| resolve_import(&base, &aliases, from, spec);
+
+        assert_eq!(
+            resolve("../components/button"),
+            Some(PathBuf::from("app/components/button.tsx"))
+        );
+        assert_eq!(resolve("@/lib"), Some(PathBuf::from("app/lib/index.ts")));
+        assert_eq!(resolve("../../../outside"), None);
+        assert_eq!(resolve("../../node_modules/pkg"), None);
+        assert_eq!(resolve("react"), None);
+    }
+}
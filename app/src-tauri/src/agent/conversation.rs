@@ -0,0 +1,325 @@
+use super::AgentEvent;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, Runtime};
+
+const CONVERSATIONS_DIR: &str = "conversations";
+const MAX_HISTORY_TURNS: usize = 20;
+
+// serializes read-modify-write of thread files; concurrent runs share threads
+static THREAD_WRITES: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Turn {
+    pub run_id: String,
+    pub action: String,
+    pub prompt: String,
+    pub summary: Option<String>,
+    pub files: Vec<String>,
+    pub outcome: String,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Thread {
+    pub id: String,
+    pub project_path: String,
+    pub title: String,
+    pub created_at: String,
+    pub updated_at: String,
+    #[serde(default)]
+    pub forked_from: Option<String>,
+    pub turns: Vec<Turn>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadSummary {
+    pub id: String,
+    pub title: String,
+    pub turns: usize,
+    pub created_at: String,
+    pub updated_at: String,
+    pub forked_from: Option<String>,
+}
+
+/// A previous turn as sent to the backend.
+#[derive(Debug, Clone, Serialize)]
+pub(super) struct HistoryTurn {
+    pub(super) action: String,
+    pub(super) prompt: String,
+    pub(super) summary: Option<String>,
+    pub(super) files: Vec<String>,
+    pub(super) outcome: String,
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
+fn canonical_project(project_path: &str) -> Result<String, String> {
+    PathBuf::from(project_path)
+        .canonicalize()
+        .map(|p| p.to_string_lossy().to_string())
+        .map_err(|e| format!("Invalid project path '{}': {}", project_path, e))
+}
+
+fn project_dir<R: Runtime>(app: &AppHandle<R>, project_path: &str) -> Result<PathBuf, String> {
+    project_data_dir(app, CONVERSATIONS_DIR, project_path)
+}
+
+/// `<app data>/<subdir>/<hash of the project path>`, for per-project records.
+pub(super) fn project_data_dir<R: Runtime>(
+    app: &AppHandle<R>,
+    subdir: &str,
+    project_path: &str,
+) -> Result<PathBuf, String> {
+    let canonical = canonical_project(project_path)?;
+    let digest = Sha256::digest(canonical.as_bytes());
+    let key: String = digest
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {}", e))?;
+
+    Ok(data_dir.join(subdir).join(key))
+}
+
+fn thread_file(dir: &Path, thread_id: &str) -> Result<PathBuf, String> {
+    if thread_id.is_empty()
+        || !thread_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(format!("Invalid thread id: {}", thread_id));
+    }
+    Ok(dir.join(format!("{}.json", thread_id)))
+}
+
+fn load_thread(dir: &Path, thread_id: &str) -> Result<Thread, String> {
+    let raw = fs::read_to_string(thread_file(dir, thread_id)?)
+        .map_err(|_| format!("Conversation not found: {}", thread_id))?;
+    serde_json::from_str(&raw).map_err(|e| format!("Corrupt conversation {}: {}", thread_id, e))
+}
+
+fn save_thread(dir: &Path, thread: &Thread) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create conversation dir: {}", e))?;
+    let raw = serde_json::to_string_pretty(thread).map_err(|e| e.to_string())?;
+    fs::write(thread_file(dir, &thread.id)?, raw)
+        .map_err(|e| format!("Failed to save conversation: {}", e))
+}
+
+fn load_threads(dir: &Path) -> Result<Vec<Thread>, String> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut threads: Vec<Thread> = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read conversations: {}", e))?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|e| fs::read_to_string(e.path()).ok())
+        .filter_map(|raw| serde_json::from_str(&raw).ok())
+        .collect();
+
+    // most recently used first
+    threads.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    Ok(threads)
+}
+
+fn new_thread(project_path: &str, title: &str) -> Thread {
+    let timestamp = now();
+    Thread {
+        id: uuid::Uuid::new_v4().to_string(),
+        project_path: project_path.to_string(),
+        title: title.chars().take(80).collect(),
+        created_at: timestamp.clone(),
+        updated_at: timestamp,
+        forked_from: None,
+        turns: Vec::new(),
+    }
+}
+
+/// Resolves the thread a run belongs to: the one asked for, else the project's
+/// most recently used thread, else a fresh one titled after the prompt.
+pub(super) fn resolve<R: Runtime>(
+    app: &AppHandle<R>,
+    project_path: &str,
+    thread_id: Option<&str>,
+    prompt: &str,
+) -> Result<Thread, String> {
+    let dir = project_dir(app, project_path)?;
+
+    if let Some(id) = thread_id {
+        return load_thread(&dir, id);
+    }
+
+    match load_threads(&dir)?.into_iter().next() {
+        Some(thread) => Ok(thread),
+        None => Ok(new_thread(&canonical_project(project_path)?, prompt)),
+    }
+}
+
+/// The tail of a thread in the shape the backend expects.
+pub(super) fn history(thread: &Thread) -> Vec<HistoryTurn> {
+    let skip = thread.turns.len().saturating_sub(MAX_HISTORY_TURNS);
+    thread.turns[skip..]
+        .iter()
+        .map(|t| HistoryTurn {
+            action: t.action.clone(),
+            prompt: t.prompt.clone(),
+            summary: t.summary.clone(),
+            files: t.files.clone(),
+            outcome: t.outcome.clone(),
+        })
+        .collect()
+}
+
+/// Appends the finished run to its thread as it is on disk now, so turns that
+/// other runs recorded since this one started are kept.
+pub(super) fn record<R: Runtime>(
+    app: &AppHandle<R>,
+    thread: Thread,
+    run_id: &str,
+    action: &str,
+    prompt: &str,
+    outcome: &AgentEvent,
+) -> Result<(), String> {
+    let dir = project_dir(app, &thread.project_path)?;
+
+    let (outcome, summary, files) = match outcome {
+        AgentEvent::Done { summary, files, .. } => ("done", Some(summary.clone()), files.clone()),
+        AgentEvent::Error { message } => ("error", Some(message.clone()), Vec::new()),
+        AgentEvent::ResumableFailure {
+            message,
+            applied_files,
+            ..
+        } => ("interrupted", Some(message.clone()), applied_files.clone()),
+        AgentEvent::Cancelled => ("cancelled", None, Vec::new()),
+        _ => ("unknown", None, Vec::new()),
+    };
+
+    let _guard = THREAD_WRITES.lock().map_err(|e| e.to_string())?;
+    // a thread the run started is only on disk once a run has finished in it
+    let mut thread = if thread_file(&dir, &thread.id)?.exists() {
+        load_thread(&dir, &thread.id)?
+    } else {
+        thread
+    };
+
+    let timestamp = now();
+    thread.turns.push(Turn {
+        run_id: run_id.to_string(),
+        action: action.to_string(),
+        prompt: prompt.to_string(),
+        summary,
+        files,
+        outcome: outcome.to_string(),
+        timestamp: timestamp.clone(),
+    });
+    thread.updated_at = timestamp;
+
+    save_thread(&dir, &thread)
+}
+
+// Commands
+#[tauri::command]
+pub async fn list_conversations<R: Runtime>(
+    app: AppHandle<R>,
+    project_path: String,
+) -> Result<Vec<ThreadSummary>, String> {
+    let dir = project_dir(&app, &project_path)?;
+
+    Ok(load_threads(&dir)?
+        .into_iter()
+        .map(|t| ThreadSummary {
+            id: t.id,
+            title: t.title,
+            turns: t.turns.len(),
+            created_at: t.created_at,
+            updated_at: t.updated_at,
+            forked_from: t.forked_from,
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn get_conversation<R: Runtime>(
+    app: AppHandle<R>,
+    project_path: String,
+    thread_id: String,
+) -> Result<Thread, String> {
+    load_thread(&project_dir(&app, &project_path)?, &thread_id)
+}
+
+/// Makes `thread_id` the project's current thread, so runs without an explicit
+/// thread continue it.
+#[tauri::command]
+pub async fn continue_conversation<R: Runtime>(
+    app: AppHandle<R>,
+    project_path: String,
+    thread_id: String,
+) -> Result<Thread, String> {
+    let dir = project_dir(&app, &project_path)?;
+    let _guard = THREAD_WRITES.lock().map_err(|e| e.to_string())?;
+    let mut thread = load_thread(&dir, &thread_id)?;
+
+    thread.updated_at = now();
+    save_thread(&dir, &thread)?;
+    Ok(thread)
+}
+
+/// Copies a thread, optionally keeping only its first `turns` turns, and makes
+/// the copy the current thread.
+#[tauri::command]
+pub async fn fork_conversation<R: Runtime>(
+    app: AppHandle<R>,
+    project_path: String,
+    thread_id: String,
+    turns: Option<usize>,
+) -> Result<Thread, String> {
+    let dir = project_dir(&app, &project_path)?;
+    let source = load_thread(&dir, &thread_id)?;
+
+    let mut fork = new_thread(&source.project_path, &format!("{} (fork)", source.title));
+    fork.forked_from = Some(source.id.clone());
+    fork.turns = source.turns;
+    if let Some(keep) = turns {
+        fork.turns.truncate(keep);
+    }
+
+    save_thread(&dir, &fork)?;
+    Ok(fork)
+}
+
+/// Deletes one thread, or every thread of the project when `thread_id` is omitted.
+#[tauri::command]
+pub async fn clear_conversation<R: Runtime>(
+    app: AppHandle<R>,
+    project_path: String,
+    thread_id: Option<String>,
+) -> Result<(), String> {
+    let dir = project_dir(&app, &project_path)?;
+
+    match thread_id {
+        Some(id) => {
+            let file = thread_file(&dir, &id)?;
+            if file.exists() {
+                fs::remove_file(&file)
+                    .map_err(|e| format!("Failed to delete conversation: {}", e))?;
+            }
+            Ok(())
+        }
+        None if dir.exists() => {
+            fs::remove_dir_all(&dir).map_err(|e| format!("Failed to clear conversations: {}", e))
+        }
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,419 @@
+use super::{Backend, Provider};
+use crate::config::AppSettings;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Runtime};
+
+/// Version of the request/event contract this client speaks.
+pub const PROTOCOL_VERSION: u32 = 1;
+// oldest backend protocol we can still drive
+const MIN_BACKEND_PROTOCOL: u32 = 1;
+const CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+// while the backend is down, runs skip the probe instead of each waiting it out
+const UNREACHABLE_TTL: Duration = Duration::from_secs(30);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Event types the backend may put on the stream that this client understands.
+const STREAM_EVENTS: &[&str] = &[
+    "file_write",
+    "file_delete",
+    "file_rename",
+    "file_patch",
+    "selection_edit",
+    "conflict",
+    "status",
+    "text_delta",
+    "tool_call_start",
+    "tool_call_finish",
+    "progress",
+    "done",
+    "error",
+];
+// a backend that cannot send these never ends a run
+const REQUIRED_EVENTS: &[&str] = &["done", "error"];
+
+/// What `GET /capabilities` reports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Capabilities {
+    // 0 for backends that predate the handshake
+    pub protocol_version: u32,
+    #[serde(default)]
+    pub min_client_version: u32,
+    #[serde(default)]
+    pub server_version: Option<String>,
+    // event types the backend may send
+    #[serde(default)]
+    pub events: Vec<String>,
+    // request fields each endpoint accepts; an endpoint missing here accepts everything
+    #[serde(default)]
+    pub request_fields: HashMap<String, Vec<String>>,
+}
+
+impl Capabilities {
+    fn legacy() -> Self {
+        Self {
+            protocol_version: 0,
+            min_client_version: 0,
+            server_version: None,
+            events: Vec::new(),
+            request_fields: HashMap::new(),
+        }
+    }
+
+    fn check_compatible(&self) -> Result<(), String> {
+        self.check_against(MIN_BACKEND_PROTOCOL)
+    }
+
+    fn check_against(&self, min_backend_protocol: u32) -> Result<(), String> {
+        if self.protocol_version != 0 && self.protocol_version < min_backend_protocol {
+            return Err(format!(
+                "The agent backend speaks protocol {}, but this app needs at least {}. Update the backend.",
+                self.protocol_version, min_backend_protocol
+            ));
+        }
+        if self.min_client_version > PROTOCOL_VERSION {
+            return Err(format!(
+                "The agent backend needs protocol {} or newer, but this app speaks {}. Update the app.",
+                self.min_client_version, PROTOCOL_VERSION
+            ));
+        }
+
+        let missing: Vec<&str> = REQUIRED_EVENTS
+            .iter()
+            .copied()
+            .filter(|e| !self.events.is_empty() && !self.events.iter().any(|s| s == e))
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "The agent backend does not send required events: {}",
+                missing.join(", ")
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Things that still work but not fully, worth surfacing at the start of a run.
+    pub(super) fn warnings(&self) -> Vec<String> {
+        if self.protocol_version == 0 {
+            return vec![
+                "The agent backend does not report its capabilities; assuming the legacy protocol"
+                    .into(),
+            ];
+        }
+
+        let mut warnings = Vec::new();
+        if self.protocol_version > PROTOCOL_VERSION {
+            warnings.push(format!(
+                "The agent backend speaks protocol {}, newer than this app's {}; some features may be unavailable",
+                self.protocol_version, PROTOCOL_VERSION
+            ));
+        }
+
+        let unknown: Vec<&str> = self
+            .events
+            .iter()
+            .map(String::as_str)
+            .filter(|e| !STREAM_EVENTS.contains(e))
+            .collect();
+        if !unknown.is_empty() {
+            warnings.push(format!(
+                "The agent backend may send events this app ignores: {}",
+                unknown.join(", ")
+            ));
+        }
+
+        warnings
+    }
+
+    /// Drops top-level request fields `endpoint` does not accept, returning
+    /// the names of the dropped fields.
+    pub(super) fn filter_request(
+        &self,
+        endpoint: &str,
+        body: &mut serde_json::Value,
+    ) -> Vec<String> {
+        let (Some(accepted), Some(fields)) =
+            (self.request_fields.get(endpoint), body.as_object_mut())
+        else {
+            return Vec::new();
+        };
+
+        let dropped: Vec<String> = fields
+            .keys()
+            .filter(|k| !accepted.contains(k))
+            .cloned()
+            .collect();
+        for key in &dropped {
+            fields.remove(key);
+        }
+        dropped
+    }
+}
+
+/// Outcome of calling `GET /capabilities` once.
+enum Probe {
+    Reported(Capabilities),
+    // 404: the backend predates the handshake
+    Legacy,
+    Unauthorized(StatusCode),
+    // answered, but not with capabilities
+    Failed(String),
+    Unreachable(String),
+}
+
+async fn probe(backend: &Backend<'_>) -> (Probe, Duration) {
+    let started = Instant::now();
+    let request = backend
+        .authorize(backend.client.get(backend.url("capabilities")))
+        .timeout(PROBE_TIMEOUT);
+
+    let probe = match request.send().await {
+        Err(e) => Probe::Unreachable(format!("Connection failed: {}", e)),
+        Ok(response) => match response.status() {
+            StatusCode::NOT_FOUND => Probe::Legacy,
+            status @ (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => {
+                Probe::Unauthorized(status)
+            }
+            status if !status.is_success() => {
+                Probe::Failed(format!("Server returned an error ({})", status))
+            }
+            _ => match response.json::<Capabilities>().await {
+                Ok(capabilities) => Probe::Reported(capabilities),
+                Err(e) => Probe::Failed(format!("Unreadable capabilities response: {}", e)),
+            },
+        },
+    };
+
+    (probe, started.elapsed())
+}
+
+/// Capabilities per backend URL, so runs do not handshake every time. `None`
+/// marks a backend that could not be reached.
+#[derive(Default)]
+pub struct BackendCache {
+    entries: Mutex<HashMap<String, (Instant, Option<Capabilities>)>>,
+}
+
+impl BackendCache {
+    fn get(&self, api_url: &str) -> Option<Option<Capabilities>> {
+        let entries = self.entries.lock().ok()?;
+        let (at, capabilities) = entries.get(api_url)?;
+        let ttl = match capabilities {
+            Some(_) => CACHE_TTL,
+            None => UNREACHABLE_TTL,
+        };
+        (at.elapsed() < ttl).then(|| capabilities.clone())
+    }
+
+    fn insert(&self, api_url: &str, capabilities: &Capabilities) {
+        self.set(api_url, Some(capabilities.clone()));
+    }
+
+    fn insert_unreachable(&self, api_url: &str) {
+        self.set(api_url, None);
+    }
+
+    fn set(&self, api_url: &str, capabilities: Option<Capabilities>) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(api_url.to_string(), (Instant::now(), capabilities));
+        }
+    }
+
+    fn remove(&self, api_url: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(api_url);
+        }
+    }
+}
+
+/// Learns what the backend supports, refusing incompatible ones. `None` means
+/// the handshake itself failed; the agent request that follows reports why.
+pub(super) async fn negotiate(
+    backend: &Backend<'_>,
+    cache: &BackendCache,
+) -> Result<Option<Capabilities>, String> {
+    let api_url = &backend.settings.api_url;
+
+    let capabilities = match cache.get(api_url) {
+        Some(Some(capabilities)) => capabilities,
+        Some(None) => return Ok(None),
+        None => match probe(backend).await.0 {
+            Probe::Reported(capabilities) => capabilities,
+            Probe::Legacy => Capabilities::legacy(),
+            Probe::Unreachable(_) => {
+                cache.insert_unreachable(api_url);
+                return Ok(None);
+            }
+            Probe::Unauthorized(_) | Probe::Failed(_) => return Ok(None),
+        },
+    };
+
+    capabilities.check_compatible()?;
+    cache.insert(api_url, &capabilities);
+    Ok(Some(capabilities))
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackendStatus {
+    pub reachable: bool,
+    pub latency_ms: Option<u64>,
+    pub protocol_version: Option<u32>,
+    pub server_version: Option<String>,
+    pub compatible: bool,
+    pub message: String,
+}
+
+/// Probes the backend for the settings screen. Checks `settings` when given,
+/// so unsaved values can be tried, otherwise the saved settings.
+#[tauri::command]
+pub async fn check_backend<R: Runtime>(
+    app: AppHandle<R>,
+    client: tauri::State<'_, Client>,
+    cache: tauri::State<'_, BackendCache>,
+    settings: Option<AppSettings>,
+) -> Result<BackendStatus, String> {
+    let backend = match settings {
+        Some(settings) => Backend::new(&client, settings)?,
+        None => Backend::load(&app, &client).await?,
+    };
+    if backend.provider == Provider::OpenAi {
+        return Ok(super::openai::check(&backend).await);
+    }
+    let api_url = &backend.settings.api_url;
+
+    cache.remove(api_url);
+    let (probe, latency) = probe(&backend).await;
+    let latency_ms = Some(latency.as_millis() as u64);
+
+    let status = match probe {
+        Probe::Reported(capabilities) => {
+            let compatible = capabilities.check_compatible();
+            if compatible.is_ok() {
+                cache.insert(api_url, &capabilities);
+            }
+            let message = match &compatible {
+                Ok(()) => capabilities
+                    .warnings()
+                    .into_iter()
+                    .next()
+                    .unwrap_or_else(|| "Connected".into()),
+                Err(e) => e.clone(),
+            };
+            BackendStatus {
+                reachable: true,
+                latency_ms,
+                protocol_version: Some(capabilities.protocol_version),
+                server_version: capabilities.server_version,
+                compatible: compatible.is_ok(),
+                message,
+            }
+        }
+        Probe::Legacy => {
+            let capabilities = Capabilities::legacy();
+            cache.insert(api_url, &capabilities);
+            BackendStatus {
+                reachable: true,
+                latency_ms,
+                protocol_version: Some(0),
+                server_version: None,
+                compatible: true,
+                message: capabilities.warnings().remove(0),
+            }
+        }
+        Probe::Unauthorized(status) => BackendStatus {
+            reachable: true,
+            latency_ms,
+            protocol_version: None,
+            server_version: None,
+            compatible: false,
+            message: super::unauthorized_message(status),
+        },
+        Probe::Failed(message) => BackendStatus {
+            reachable: true,
+            latency_ms,
+            protocol_version: None,
+            server_version: None,
+            compatible: false,
+            message,
+        },
+        Probe::Unreachable(message) => {
+            cache.insert_unreachable(api_url);
+            BackendStatus {
+                reachable: false,
+                latency_ms: None,
+                protocol_version: None,
+                server_version: None,
+                compatible: false,
+                message,
+            }
+        }
+    };
+
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reported(protocol_version: u32) -> Capabilities {
+        Capabilities {
+            protocol_version,
+            ..Capabilities::legacy()
+        }
+    }
+
+    #[test]
+    fn backends_below_the_minimum_protocol_are_refused() {
+        assert!(reported(1).check_against(2).is_err());
+        assert!(reported(2).check_against(2).is_ok());
+        // backends without the handshake are driven with the legacy protocol
+        assert!(Capabilities::legacy().check_against(2).is_ok());
+    }
+
+    #[test]
+    fn backends_needing_a_newer_client_are_refused() {
+        let capabilities = Capabilities {
+            min_client_version: PROTOCOL_VERSION + 1,
+            ..reported(PROTOCOL_VERSION)
+        };
+        assert!(capabilities.check_compatible().is_err());
+    }
+
+    #[test]
+    fn backends_that_cannot_end_a_run_are_refused() {
+        let capabilities = Capabilities {
+            events: vec!["file_write".into(), "done".into()],
+            ..reported(PROTOCOL_VERSION)
+        };
+        let error = capabilities.check_compatible().unwrap_err();
+        assert!(error.ends_with(": error"), "{}", error);
+    }
+
+    #[test]
+    fn unreachable_backends_are_remembered_briefly() {
+        let cache = BackendCache::default();
+        cache.insert_unreachable("http://backend");
+        assert!(matches!(cache.get("http://backend"), Some(None)));
+
+        let expired = Instant::now() - UNREACHABLE_TTL;
+        cache
+            .entries
+            .lock()
+            .unwrap()
+            .insert("http://backend".into(), (expired, None));
+        assert!(cache.get("http://backend").is_none());
+
+        // a reachable backend is kept for longer
+        cache
+            .entries
+            .lock()
+            .unwrap()
+            .insert("http://backend".into(), (expired, Some(reported(1))));
+        assert!(matches!(cache.get("http://backend"), Some(Some(_))));
+    }
+}
@@ -0,0 +1,121 @@
+use super::{Backend, Provider, RunOptions};
+use crate::config::AppSettings;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+
+/// Model and sampling settings sent with a request. Unset fields leave the
+/// choice to the backend.
+#[derive(Debug, Clone, Default, Serialize)]
+pub(super) struct Generation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+impl Generation {
+    /// Per-call options win over the defaults in settings.
+    pub(super) fn resolve(settings: &AppSettings, options: &RunOptions) -> Result<Self, String> {
+        let model = options
+            .model
+            .clone()
+            .or_else(|| Some(settings.model.clone()))
+            .filter(|m| !m.trim().is_empty());
+        let temperature = options.temperature.or(settings.temperature);
+        let max_tokens = options.max_tokens.or(settings.max_tokens);
+
+        if let Some(t) = temperature {
+            if !(0.0..=2.0).contains(&t) {
+                return Err(format!("Temperature must be between 0 and 2, got {}", t));
+            }
+        }
+        if max_tokens == Some(0) {
+            return Err("Max tokens must be greater than 0".to_string());
+        }
+
+        Ok(Self {
+            model,
+            temperature,
+            max_tokens,
+        })
+    }
+}
+
+impl Backend<'_> {
+    /// The model requests made with `generation` go to, when known here.
+    pub(super) fn model_for(&self, generation: &Generation) -> Option<String> {
+        generation.model.clone().or_else(|| match self.provider {
+            Provider::OpenAi => Some(self.settings.openai_model.clone()),
+            Provider::Backend => None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub context_length: Option<u64>,
+}
+
+// OpenAI-style `{"data": [...]}`, `{"models": [...]}`, or a bare array
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ModelList {
+    Data { data: Vec<ModelInfo> },
+    Models { models: Vec<ModelInfo> },
+    Bare(Vec<ModelInfo>),
+}
+
+/// Models the configured provider offers. Asks with `settings` when given,
+/// like `check_backend`, otherwise with the saved settings.
+#[tauri::command]
+pub async fn list_models<R: Runtime>(
+    app: AppHandle<R>,
+    client: tauri::State<'_, Client>,
+    settings: Option<AppSettings>,
+) -> Result<Vec<ModelInfo>, String> {
+    let backend = match settings {
+        Some(settings) => Backend::new(&client, settings)?,
+        None => Backend::load(&app, &client).await?,
+    };
+
+    let request = match backend.provider {
+        Provider::Backend => backend.authorize(client.get(backend.url("models"))),
+        Provider::OpenAi => backend.openai_authorize(client.get(backend.openai_url("models"))),
+    };
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Connection failed: {}", e))?;
+    let status = response.status();
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        return Err(super::unauthorized_message(status));
+    }
+    if status == StatusCode::NOT_FOUND {
+        return Err("The agent backend does not list its models".to_string());
+    }
+    if !status.is_success() {
+        return Err(format!("Server returned an error ({})", status));
+    }
+
+    let mut models = match response
+        .json::<ModelList>()
+        .await
+        .map_err(|e| format!("Unreadable model list: {}", e))?
+    {
+        ModelList::Data { data } => data,
+        ModelList::Models { models } => models,
+        ModelList::Bare(models) => models,
+    };
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(models)
+}
@@ -0,0 +1,600 @@
+//! Agent provider that drives an OpenAI-compatible chat-completions endpoint
+//! (OpenAI, llama.cpp, Ollama, ...) directly. The model works through file
+//! tools, and every tool call is applied and reported as the same
+//! `AgentEvent`s the Python backend would stream.
+
+use super::sse::{SseDecoder, SseItem};
+use super::{
+    execute_file_op, handshake::BackendStatus, read_for_run, send_request, usage::Usage,
+    AgentEvent, AgentRequest, AgentRun, Backend, SendError, MAX_FRAME_SIZE,
+};
+use crate::project::{build_tree, FileNode};
+use reqwest::{RequestBuilder, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tauri::Runtime;
+
+// model round-trips per request before we give up
+const MAX_TURNS: usize = 30;
+const MAX_LISTED_FILES: usize = 500;
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+const SCAFFOLD_PROMPT: &str =
+    "You are an expert React Native engineer. Build a complete, polished \
+Expo Router app (Expo SDK 54, TypeScript) in the current project from the user's description.\n\
+- Use write_file for every file you create or update; all new files go inside app/\n\
+- app/_layout.tsx must render <Slot /> inside a SafeAreaProvider\n\
+- Do not write app.json, package.json or tsconfig.json; they already exist\n\
+- React Native components only, never HTML elements; use onPress, not onClick\n\
+- Do not ask clarifying questions; make reasonable decisions and finish every screen\n\
+- When you are done, reply with a 2-3 sentence summary of what you built and call no more tools.";
+
+const EDIT_PROMPT: &str = "You are an expert React Native engineer working inside an existing \
+Expo Router project (Expo SDK 54, TypeScript).\n\
+- Apply the requested change to the target file with write_file (the complete file) or apply_patch (a unified diff)\n\
+- Make surgical edits; do not change unrelated code or rename imports without need\n\
+- You may read other files with read_file and list_files, and change them when the request requires it\n\
+- React Native components only, never HTML elements; never remove <Slot /> from app/_layout.tsx\n\
+- When you are done, reply with a single sentence summarising the change and call no more tools.";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Message {
+    role: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<ToolCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+impl Message {
+    fn text(role: &str, content: impl Into<String>) -> Self {
+        Self {
+            role: role.to_string(),
+            content: Some(content.into()),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ToolCall {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    function: FunctionCall,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct FunctionCall {
+    name: String,
+    arguments: String,
+}
+
+// streamed chunk shapes; everything is optional because servers differ
+#[derive(Debug, Deserialize)]
+struct Chunk {
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    model: Option<String>,
+    // only on the last chunk, with `stream_options.include_usage`
+    usage: Option<ChunkUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkUsage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkChoice {
+    #[serde(default)]
+    delta: Delta,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Delta {
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCallDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ToolCallDelta {
+    #[serde(default)]
+    index: usize,
+    id: Option<String>,
+    function: Option<FunctionDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FunctionDelta {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+/// One assistant turn, reassembled from the stream.
+#[derive(Debug, Default)]
+struct Completion {
+    text: String,
+    tool_calls: Vec<ToolCall>,
+    usage: Usage,
+}
+
+fn tools() -> serde_json::Value {
+    let tool = |name: &str, description: &str, properties: serde_json::Value| {
+        let required: Vec<&String> = properties
+            .as_object()
+            .map(|p| p.keys().collect())
+            .unwrap_or_default();
+        json!({
+            "type": "function",
+            "function": {
+                "name": name,
+                "description": description,
+                "parameters": {
+                    "type": "object",
+                    "properties": properties,
+                    "required": required,
+                },
+            },
+        })
+    };
+    let string = |description: &str| json!({ "type": "string", "description": description });
+
+    json!([
+        tool(
+            "write_file",
+            "Create or overwrite a file with its complete content.",
+            json!({
+                "path": string("Path relative to the project root, e.g. app/index.tsx"),
+                "content": string("The complete file content"),
+            }),
+        ),
+        tool(
+            "apply_patch",
+            "Change an existing file with a unified diff.",
+            json!({
+                "path": string("Path relative to the project root"),
+                "diff": string("Unified diff against the current file"),
+            }),
+        ),
+        tool(
+            "delete_file",
+            "Delete a file.",
+            json!({ "path": string("Path relative to the project root") }),
+        ),
+        tool(
+            "rename_file",
+            "Move a file to a new path.",
+            json!({
+                "from": string("Current path relative to the project root"),
+                "to": string("New path relative to the project root"),
+            }),
+        ),
+        tool(
+            "read_file",
+            "Read a file from the project.",
+            json!({ "path": string("Path relative to the project root") }),
+        ),
+        tool("list_files", "List the files in the project.", json!({})),
+    ])
+}
+
+fn history_messages(request: &AgentRequest) -> Vec<Message> {
+    let history = match request {
+        AgentRequest::Scaffold(r) => &r.history,
+        AgentRequest::Edit(r) => &r.history,
+    };
+
+    history
+        .iter()
+        .flat_map(|turn| {
+            let mut reply = format!("[{}: {}]", turn.action, turn.outcome);
+            if let Some(summary) = &turn.summary {
+                reply.push(' ');
+                reply.push_str(summary);
+            }
+            if !turn.files.is_empty() {
+                reply.push_str(&format!("\nFiles: {}", turn.files.join(", ")));
+            }
+            [
+                Message::text("user", turn.prompt.clone()),
+                Message::text("assistant", reply),
+            ]
+        })
+        .collect()
+}
+
+/// The conversation to start from: instructions, previous turns, then the request.
+fn initial_messages(request: &AgentRequest) -> Result<Vec<Message>, String> {
+    let (system, user) = match request {
+        AgentRequest::Scaffold(r) => {
+            let mut user = format!(
+                "The app is called \"{}\"; use the name in headers and branding.\n\
+                 Primary brand color: {}; use it for buttons, active tabs, headers and key accents.\n",
+                r.app_name, r.brand_color
+            );
+            if !r.image_urls.is_empty() {
+                user.push_str(&format!(
+                    "Reference images, for visual direction only: {}\n",
+                    r.image_urls.join(", ")
+                ));
+            }
+            user.push_str(&format!("\n{}", r.user_prompt));
+            (SCAFFOLD_PROMPT, user)
+        }
+        AgentRequest::Edit(r) => {
+            let context = serde_json::to_string(&r.context).map_err(|e| e.to_string())?;
+            let mut user = format!(
+                "Target file: {}\n```\n{}\n```\n\nProject context (tree and related files, JSON):\n{}\n",
+                r.relative_path, r.content, context
+            );
+            if let Some(selection) = &r.selection {
+                user.push_str(&format!(
+                    "\nOnly change the selected code, lines {}:{} to {}:{} (end exclusive).\n",
+                    selection.start_line,
+                    selection.start_column,
+                    selection.end_line,
+                    selection.end_column
+                ));
+            }
+            user.push_str(&format!("\n{}", r.user_prompt));
+            (EDIT_PROMPT, user)
+        }
+    };
+
+    let mut messages = vec![Message::text("system", system)];
+    messages.extend(history_messages(request));
+    messages.push(Message::text("user", user));
+    Ok(messages)
+}
+
+impl Backend<'_> {
+    pub(super) fn openai_url(&self, endpoint: &str) -> String {
+        format!(
+            "{}/{}",
+            self.settings.openai_base_url.trim_end_matches('/'),
+            endpoint
+        )
+    }
+
+    pub(super) fn openai_authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match self.settings.openai_api_key.trim() {
+            "" => request,
+            key => request.bearer_auth(key),
+        }
+    }
+}
+
+/// Streams one completion, forwarding text as it arrives.
+async fn complete<R: Runtime>(
+    run: &AgentRun<'_, R>,
+    backend: &Backend<'_>,
+    request: &AgentRequest,
+    messages: &[Message],
+) -> Result<Completion, SendError> {
+    let generation = request.generation();
+    let mut body = json!({
+        "model": generation.model.as_deref().unwrap_or(&backend.settings.openai_model),
+        "messages": messages,
+        "tools": tools(),
+        "stream": true,
+        "stream_options": { "include_usage": true },
+    });
+    if let Some(temperature) = generation.temperature {
+        body["temperature"] = json!(temperature);
+    }
+    if let Some(max_tokens) = generation.max_tokens {
+        body["max_tokens"] = json!(max_tokens);
+    }
+    if let Some(recorder) = &run.recorder {
+        recorder.request("chat/completions", &body);
+    }
+    let request = backend.openai_authorize(
+        backend
+            .client
+            .post(backend.openai_url("chat/completions"))
+            .json(&body),
+    );
+    let mut response = send_request(request).await?;
+
+    let mut decoder = SseDecoder::new(MAX_FRAME_SIZE);
+    let mut completion = Completion::default();
+    // tool calls arrive in fragments keyed by index
+    let mut calls: BTreeMap<usize, ToolCall> = BTreeMap::new();
+
+    'stream: loop {
+        let chunk = match response.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) => return Err(SendError::Failed(format!("Connection lost: {}", e))),
+        };
+
+        for item in decoder.feed(&chunk).map_err(SendError::Failed)? {
+            let SseItem::Event(frame) = item else {
+                continue;
+            };
+            if let Some(recorder) = &run.recorder {
+                recorder.frame(&frame);
+            }
+            if frame.data.trim() == "[DONE]" {
+                break 'stream;
+            }
+
+            let chunk: Chunk = match serde_json::from_str(&frame.data) {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    run.emit(&super::diagnostic(format!(
+                        "Malformed completion chunk ({}): {}",
+                        e,
+                        frame.data.chars().take(200).collect::<String>()
+                    )));
+                    continue;
+                }
+            };
+
+            if let Some(model) = chunk.model {
+                completion.usage.model = Some(model);
+            }
+            if let Some(usage) = chunk.usage {
+                completion.usage.prompt_tokens = usage.prompt_tokens;
+                completion.usage.completion_tokens = usage.completion_tokens;
+            }
+            for choice in chunk.choices {
+                if let Some(delta) = choice.delta.content.filter(|d| !d.is_empty()) {
+                    completion.text.push_str(&delta);
+                    run.emit(&AgentEvent::TextDelta { delta });
+                }
+                for part in choice.delta.tool_calls {
+                    let call = calls.entry(part.index).or_default();
+                    if let Some(id) = part.id {
+                        call.id = id;
+                    }
+                    if let Some(function) = part.function {
+                        call.function
+                            .name
+                            .push_str(&function.name.unwrap_or_default());
+                        call.function
+                            .arguments
+                            .push_str(&function.arguments.unwrap_or_default());
+                    }
+                }
+            }
+        }
+    }
+
+    completion.tool_calls = calls
+        .into_iter()
+        .map(|(index, mut call)| {
+            call.kind = "function".into();
+            if call.id.is_empty() {
+                call.id = format!("call_{}", index);
+            }
+            call
+        })
+        .collect();
+    Ok(completion)
+}
+
+fn flatten_tree(nodes: &[FileNode], out: &mut Vec<String>) {
+    for node in nodes {
+        match &node.children {
+            Some(children) => flatten_tree(children, out),
+            None => out.push(node.path.replace('\\', "/")),
+        }
+    }
+}
+
+fn arg<'v>(args: &'v serde_json::Value, name: &str) -> Result<&'v str, String> {
+    args[name]
+        .as_str()
+        .ok_or_else(|| format!("Missing `{}` argument", name))
+}
+
+/// Runs one tool call, returning the text the model gets back.
+async fn execute<R: Runtime>(
+    run: &AgentRun<'_, R>,
+    call: &ToolCall,
+    written: &mut Vec<String>,
+) -> String {
+    let tool = call.function.name.clone();
+    let args: serde_json::Value =
+        serde_json::from_str(&call.function.arguments).unwrap_or(serde_json::Value::Null);
+    let path = args["path"]
+        .as_str()
+        .or_else(|| args["to"].as_str())
+        .map(String::from);
+
+    run.emit(&AgentEvent::ToolCallStart {
+        call_id: call.id.clone(),
+        tool: tool.clone(),
+        path: path.clone(),
+    });
+
+    let result: Result<String, String> = async {
+        let file_op = match tool.as_str() {
+            "write_file" => AgentEvent::FileWrite {
+                path: arg(&args, "path")?.to_string(),
+                content: arg(&args, "content")?.to_string(),
+            },
+            "apply_patch" => AgentEvent::FilePatch {
+                path: arg(&args, "path")?.to_string(),
+                diff: arg(&args, "diff")?.to_string(),
+            },
+            "delete_file" => AgentEvent::FileDelete {
+                path: arg(&args, "path")?.to_string(),
+            },
+            "rename_file" => AgentEvent::FileRename {
+                from: arg(&args, "from")?.to_string(),
+                to: arg(&args, "to")?.to_string(),
+            },
+            "read_file" => {
+                let path = arg(&args, "path")?;
+                return read_for_run(run, path)
+                    .await?
+                    .ok_or_else(|| format!("{} does not exist", path));
+            }
+            "list_files" => {
+                let base = run.base_path.clone();
+                let tree = tokio::task::spawn_blocking(move || build_tree(&base, &base))
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .map_err(|e| format!("Failed to list files: {}", e))?;
+                let mut files = Vec::new();
+                flatten_tree(&tree, &mut files);
+                files.truncate(MAX_LISTED_FILES);
+                return Ok(files.join("\n"));
+            }
+            other => return Err(format!("Unknown tool: {}", other)),
+        };
+
+        execute_file_op(run, &file_op).await?;
+        for path in file_op.touched_paths() {
+            if !written.iter().any(|p| p == path) {
+                written.push(path.to_string());
+            }
+        }
+        Ok("OK".to_string())
+    }
+    .await;
+
+    run.emit(&AgentEvent::ToolCallFinish {
+        call_id: call.id.clone(),
+        tool,
+        path,
+        error: result.as_ref().err().cloned(),
+    });
+
+    result.unwrap_or_else(|e| format!("Error: {}", e))
+}
+
+/// Runs `request` against the configured chat-completions endpoint until the
+/// model stops calling tools, ending with `Done` or `Error` like a backend stream.
+pub(super) async fn run<R: Runtime>(
+    run: &AgentRun<'_, R>,
+    backend: &Backend<'_>,
+    request: &AgentRequest,
+) -> Result<(), String> {
+    if backend.settings.openai_base_url.trim().is_empty()
+        || (backend.settings.openai_model.trim().is_empty() && request.generation().model.is_none())
+    {
+        return Err("Set the OpenAI-compatible base URL and model in Settings".to_string());
+    }
+
+    let mut messages = initial_messages(request)?;
+    let mut written: Vec<String> = Vec::new();
+
+    for turn in 0..MAX_TURNS {
+        let completion = match complete(run, backend, request, &messages).await {
+            Ok(completion) => completion,
+            Err(SendError::Unauthorized(status)) => {
+                run.emit(&AgentEvent::Unauthorized {
+                    status: status.as_u16(),
+                    message: openai_unauthorized_message(status),
+                });
+                return Ok(());
+            }
+            // nothing has been applied yet, so the caller may just retry
+            Err(SendError::Failed(e)) if turn == 0 => return Err(e),
+            Err(SendError::Failed(message)) => {
+                run.emit(&AgentEvent::Error { message });
+                return Ok(());
+            }
+        };
+
+        run.usage.add(&completion.usage);
+        messages.push(Message {
+            role: "assistant".into(),
+            content: (!completion.text.is_empty()).then(|| completion.text.clone()),
+            tool_calls: completion.tool_calls.clone(),
+            tool_call_id: None,
+        });
+
+        if completion.tool_calls.is_empty() {
+            let summary = completion.text.trim();
+            run.emit(&AgentEvent::Done {
+                summary: if summary.is_empty() {
+                    "Done".to_string()
+                } else {
+                    summary.to_string()
+                },
+                files: written,
+                usage: Some(run.usage.total()),
+            });
+            return Ok(());
+        }
+
+        for call in &completion.tool_calls {
+            let output = execute(run, call, &mut written).await;
+            messages.push(Message {
+                role: "tool".into(),
+                content: Some(output),
+                tool_calls: Vec::new(),
+                tool_call_id: Some(call.id.clone()),
+            });
+        }
+    }
+
+    run.emit(&AgentEvent::Error {
+        message: format!(
+            "The model was still calling tools after {} turns",
+            MAX_TURNS
+        ),
+    });
+    Ok(())
+}
+
+/// Like `unauthorized_message`, but pointing at the OpenAI-compatible API key.
+fn openai_unauthorized_message(status: StatusCode) -> String {
+    if status == StatusCode::FORBIDDEN {
+        format!(
+            "The OpenAI-compatible endpoint refused access ({}). The API key in Settings does not have permission for this model.",
+            status
+        )
+    } else {
+        format!(
+            "The OpenAI-compatible endpoint requires authentication ({}). Add or update its API key in Settings.",
+            status
+        )
+    }
+}
+
+/// `check_backend` for this provider: can we list models at the endpoint?
+pub(super) async fn check(backend: &Backend<'_>) -> BackendStatus {
+    let started = Instant::now();
+    let request = backend
+        .openai_authorize(backend.client.get(backend.openai_url("models")))
+        .timeout(PROBE_TIMEOUT);
+
+    let (reachable, compatible, message) = match request.send().await {
+        Err(e) => (false, false, format!("Connection failed: {}", e)),
+        Ok(response) => match response.status() {
+            status @ (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => {
+                (true, false, openai_unauthorized_message(status))
+            }
+            status if !status.is_success() => (
+                true,
+                false,
+                format!("Server returned an error ({})", status),
+            ),
+            _ => (true, true, "Connected to OpenAI-compatible endpoint".into()),
+        },
+    };
+
+    BackendStatus {
+        reachable,
+        latency_ms: reachable.then(|| started.elapsed().as_millis() as u64),
+        protocol_version: None,
+        server_version: None,
+        compatible,
+        message,
+    }
+}
@@ -0,0 +1,336 @@
+use super::{normalize_rel, AgentEvent};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::StoreExt;
+
+const STORE_FILE: &str = "agent-policies.json";
+
+/// Which files the agent may write in a project, and how much per run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WritePolicy {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+    pub max_file_size: usize,
+    pub max_files: usize,
+}
+
+impl Default for WritePolicy {
+    fn default() -> Self {
+        Self {
+            allow: vec!["**".into()],
+            deny: [
+                "package.json",
+                "app.json",
+                "babel.config.js",
+                "**/package-lock.json",
+                "**/yarn.lock",
+                "**/pnpm-lock.yaml",
+                "**/bun.lock",
+                "**/bun.lockb",
+            ]
+            .map(String::from)
+            .to_vec(),
+            max_file_size: 512 * 1024, // 512KB
+            max_files: 50,
+        }
+    }
+}
+
+/// A policy violation, shaped for `AgentEvent::PolicyViolation`.
+pub(super) struct Violation {
+    pub path: String,
+    pub rule: &'static str,
+    pub message: String,
+}
+
+impl From<Violation> for AgentEvent {
+    fn from(v: Violation) -> Self {
+        AgentEvent::PolicyViolation {
+            path: v.path,
+            rule: v.rule.to_string(),
+            message: v.message,
+        }
+    }
+}
+
+/// A compiled `WritePolicy` plus the files the current run has touched so far.
+#[derive(Debug)]
+pub(super) struct PolicyGuard {
+    allow: GlobSet,
+    deny: GlobSet,
+    max_file_size: usize,
+    max_files: usize,
+    touched: Mutex<HashSet<String>>,
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .map_err(|e| format!("Invalid write policy glob '{}': {}", pattern, e))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Invalid write policy: {}", e))
+}
+
+impl PolicyGuard {
+    pub(super) fn new(policy: &WritePolicy) -> Result<Self, String> {
+        Ok(Self {
+            allow: glob_set(&policy.allow)?,
+            deny: glob_set(&policy.deny)?,
+            max_file_size: policy.max_file_size,
+            max_files: policy.max_files,
+            touched: Mutex::new(HashSet::new()),
+        })
+    }
+
+    pub(super) fn check_size(&self, path: &str, size: usize) -> Result<(), Violation> {
+        if size > self.max_file_size {
+            return Err(Violation {
+                path: path.to_string(),
+                rule: "max_file_size",
+                message: format!(
+                    "{} is {} bytes, over the {} byte limit",
+                    path, size, self.max_file_size
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks a file operation against the policy before it is applied. Paths
+    /// that fail to normalize are left for the file helpers to reject.
+    pub(super) fn check(&self, event: &AgentEvent) -> Result<(), Violation> {
+        let paths = normalized_paths(event);
+
+        for path in &paths {
+            if self.deny.is_match(path) {
+                return Err(Violation {
+                    path: path.clone(),
+                    rule: "deny",
+                    message: format!("{} is protected by the write policy", path),
+                });
+            }
+            if !self.allow.is_match(path) {
+                return Err(Violation {
+                    path: path.clone(),
+                    rule: "allow",
+                    message: format!("{} is outside the paths the agent may write", path),
+                });
+            }
+        }
+
+        match event {
+            AgentEvent::FileWrite { path, content }
+            | AgentEvent::SelectionEdit { path, content } => {
+                self.check_size(path, content.len())?
+            }
+            _ => {}
+        }
+
+        let touched = self.touched.lock().unwrap_or_else(|e| e.into_inner());
+        let new: Vec<&String> = paths.iter().filter(|p| !touched.contains(*p)).collect();
+        if touched.len() + new.len() > self.max_files {
+            return Err(Violation {
+                path: new[0].clone(),
+                rule: "max_files",
+                message: format!(
+                    "Run already touched {} files, the write policy allows {}",
+                    touched.len(),
+                    self.max_files
+                ),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Counts the paths of an operation that landed towards `max_files`.
+    pub(super) fn record(&self, event: &AgentEvent) {
+        let mut touched = self.touched.lock().unwrap_or_else(|e| e.into_inner());
+        touched.extend(normalized_paths(event));
+    }
+}
+
+fn normalized_paths(event: &AgentEvent) -> Vec<String> {
+    event
+        .touched_paths()
+        .into_iter()
+        .filter_map(|p| normalize_rel(p).ok())
+        .collect()
+}
+
+fn policy_key(project_path: &str) -> Result<String, String> {
+    PathBuf::from(project_path)
+        .canonicalize()
+        .map(|p| p.to_string_lossy().to_string())
+        .map_err(|e| format!("Invalid project path '{}': {}", project_path, e))
+}
+
+/// The project's write policy, or the default when none was saved.
+pub(super) fn load<R: Runtime>(
+    app: &AppHandle<R>,
+    project_path: &str,
+) -> Result<WritePolicy, String> {
+    let store = app
+        .store(STORE_FILE)
+        .map_err(|e| format!("Store error: {}", e))?;
+
+    match store.get(policy_key(project_path)?) {
+        Some(value) => serde_json::from_value(value)
+            .map_err(|e| format!("Corrupt write policy for {}: {}", project_path, e)),
+        None => Ok(WritePolicy::default()),
+    }
+}
+
+// Commands
+#[tauri::command]
+pub async fn get_write_policy<R: Runtime>(
+    app: AppHandle<R>,
+    project_path: String,
+) -> Result<WritePolicy, String> {
+    load(&app, &project_path)
+}
+
+#[tauri::command]
+pub async fn set_write_policy<R: Runtime>(
+    app: AppHandle<R>,
+    project_path: String,
+    policy: WritePolicy,
+) -> Result<(), String> {
+    // refuse to save globs that would fail every later run
+    PolicyGuard::new(&policy)?;
+
+    let store = app
+        .store(STORE_FILE)
+        .map_err(|e| format!("Store error: {}", e))?;
+
+    store.set(
+        policy_key(&project_path)?,
+        serde_json::to_value(&policy).map_err(|e| e.to_string())?,
+    );
+    store
+        .save()
+        .map_err(|e| format!("Store save failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn reset_write_policy<R: Runtime>(
+    app: AppHandle<R>,
+    project_path: String,
+) -> Result<(), String> {
+    let store = app
+        .store(STORE_FILE)
+        .map_err(|e| format!("Store error: {}", e))?;
+
+    store.delete(policy_key(&project_path)?);
+    store
+        .save()
+        .map_err(|e| format!("Store save failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &str, size: usize) -> AgentEvent {
+        AgentEvent::FileWrite {
+            path: path.into(),
+            content: "x".repeat(size),
+        }
+    }
+
+    fn rule(guard: &PolicyGuard, event: &AgentEvent) -> Option<&'static str> {
+        guard.check(event).err().map(|v| v.rule)
+    }
+
+    #[test]
+    fn default_policy_protects_manifests_and_lockfiles() {
+        let guard = PolicyGuard::new(&WritePolicy::default()).unwrap();
+        for path in [
+            "package.json",
+            "app.json",
+            "yarn.lock",
+            "web/pnpm-lock.yaml",
+        ] {
+            assert_eq!(rule(&guard, &write(path, 1)), Some("deny"), "{}", path);
+        }
+        assert_eq!(rule(&guard, &write("app/package.json", 1)), None);
+        assert_eq!(rule(&guard, &write("./app/index.tsx", 1)), None);
+    }
+
+    #[test]
+    fn a_rename_is_checked_on_both_ends() {
+        let guard = PolicyGuard::new(&WritePolicy::default()).unwrap();
+        let rename = AgentEvent::FileRename {
+            from: "app/config.js".into(),
+            to: "babel.config.js".into(),
+        };
+        let violation = guard.check(&rename).unwrap_err();
+        assert_eq!(violation.path, "babel.config.js");
+    }
+
+    #[test]
+    fn allow_list_narrows_and_deny_still_wins() {
+        let guard = PolicyGuard::new(&WritePolicy {
+            allow: vec!["app/**".into()],
+            deny: vec!["app/generated/**".into()],
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(rule(&guard, &write("app/screens/home.tsx", 1)), None);
+        assert_eq!(rule(&guard, &write("scripts/build.js", 1)), Some("allow"));
+        assert_eq!(
+            rule(&guard, &write("app/generated/api.ts", 1)),
+            Some("deny")
+        );
+    }
+
+    #[test]
+    fn enforces_the_size_limit() {
+        let guard = PolicyGuard::new(&WritePolicy {
+            max_file_size: 10,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(rule(&guard, &write("app/a.ts", 10)), None);
+        assert_eq!(rule(&guard, &write("app/a.ts", 11)), Some("max_file_size"));
+        let selection = AgentEvent::SelectionEdit {
+            path: "app/a.ts".into(),
+            content: "x".repeat(11),
+        };
+        assert_eq!(rule(&guard, &selection), Some("max_file_size"));
+    }
+
+    #[test]
+    fn counts_distinct_recorded_paths_towards_max_files() {
+        let guard = PolicyGuard::new(&WritePolicy {
+            max_files: 2,
+            ..Default::default()
+        })
+        .unwrap();
+        guard.record(&write("app/a.ts", 1));
+        guard.record(&write("./app/a.ts", 1));
+        guard.record(&write("app/b.ts", 1));
+
+        // paths already touched stay writable
+        assert_eq!(rule(&guard, &write("app/a.ts", 1)), None);
+        assert_eq!(rule(&guard, &write("app/c.ts", 1)), Some("max_files"));
+    }
+
+    #[test]
+    fn rejects_invalid_globs() {
+        let policy = WritePolicy {
+            deny: vec!["app/[".into()],
+            ..Default::default()
+        };
+        assert!(PolicyGuard::new(&policy).is_err());
+    }
+}
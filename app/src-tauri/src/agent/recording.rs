@@ -0,0 +1,456 @@
+//! Append-only record of each agent run: the requests sent, the response
+//! bytes as they arrived and the SSE frames decoded from them, the events
+//! applied and the outcome. A recorded backend run can be replayed into a
+//! scratch directory without contacting the backend.
+
+use super::conversation::project_data_dir;
+use super::sse::SseEvent;
+use super::{
+    policy, process_stream, rollback, run_data_dir, AgentEvent, AgentRun, ResumeState, StreamBody,
+    StreamEnd,
+};
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Runtime};
+
+const RUNS_DIR: &str = "agent-runs";
+const REPLAY_DIR: &str = "agent-replays";
+
+/// One line of a run's log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Record {
+    Start {
+        run_id: String,
+        project_path: String,
+        action: String,
+        provider: String,
+        staged: bool,
+        timestamp: String,
+    },
+    // a request body as sent, after capability filtering
+    Request {
+        endpoint: String,
+        body: serde_json::Value,
+        timestamp: String,
+    },
+    // a connection's stream starts; its chunks and frames follow
+    Stream {
+        timestamp: String,
+    },
+    // response bytes exactly as read, base64; what replay feeds the decoder
+    Chunk {
+        data: String,
+    },
+    Frame {
+        event: String,
+        id: Option<String>,
+        data: String,
+    },
+    // everything the run emitted, in order
+    Event {
+        event: AgentEvent,
+    },
+    Outcome {
+        outcome: Option<AgentEvent>,
+        timestamp: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordedRun {
+    pub run_id: String,
+    pub action: String,
+    pub provider: String,
+    pub staged: bool,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    // "done", "error", "cancelled", ...; `None` while running or after a crash
+    pub outcome: Option<String>,
+    pub requests: usize,
+    pub frames: usize,
+    pub events: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReplayReport {
+    pub scratch_path: String,
+    // backend requests replayed, each through its own recorded connections
+    pub requests: usize,
+    pub frames: usize,
+    // false when the run's undo snapshots were gone and replay started empty
+    pub seeded: bool,
+    pub outcome: Option<AgentEvent>,
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
+fn log_file<R: Runtime>(
+    app: &AppHandle<R>,
+    project_path: &str,
+    run_id: &str,
+) -> Result<PathBuf, String> {
+    // validates the id as well
+    run_data_dir(app, RUNS_DIR, run_id)?;
+    Ok(project_data_dir(app, RUNS_DIR, project_path)?.join(format!("{}.jsonl", run_id)))
+}
+
+/// Refuses a run id that already has a log, so two runs never share one.
+pub(super) fn check_unused<R: Runtime>(
+    app: &AppHandle<R>,
+    project_path: &str,
+    run_id: &str,
+) -> Result<(), String> {
+    if log_file(app, project_path, run_id)?.exists() {
+        return Err(format!("Run id already has a run log: {}", run_id));
+    }
+    Ok(())
+}
+
+fn read_log(file: &Path) -> Result<Vec<Record>, String> {
+    let raw = fs::read_to_string(file).map_err(|_| "Run log not found".to_string())?;
+    // a torn last line from a crash should not hide the rest
+    Ok(raw
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Writes a run's log as it happens. Recording is best effort: a failed write
+/// never fails the run.
+#[derive(Debug)]
+pub(super) struct Recorder {
+    file: Mutex<fs::File>,
+}
+
+impl Recorder {
+    pub(super) fn begin<R: Runtime>(
+        app: &AppHandle<R>,
+        run: &AgentRun<'_, R>,
+        action: &str,
+        provider: &str,
+    ) -> Result<Self, String> {
+        let file = log_file(app, run.project_path, run.run_id)?;
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create run log dir: {}", e))?;
+        }
+        let file = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(&file)
+            .map_err(|e| format!("Failed to open run log: {}", e))?;
+
+        let recorder = Self {
+            file: Mutex::new(file),
+        };
+        recorder.record(&Record::Start {
+            run_id: run.run_id.to_string(),
+            project_path: run.base_path.to_string_lossy().to_string(),
+            action: action.to_string(),
+            provider: provider.to_string(),
+            staged: run.staging.is_some(),
+            timestamp: now(),
+        });
+        Ok(recorder)
+    }
+
+    pub(super) fn record(&self, record: &Record) {
+        let Ok(mut line) = serde_json::to_string(record) else {
+            return;
+        };
+        line.push('\n');
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.write_all(line.as_bytes());
+        }
+    }
+
+    pub(super) fn request(&self, endpoint: &str, body: &serde_json::Value) {
+        self.record(&Record::Request {
+            endpoint: endpoint.to_string(),
+            body: body.clone(),
+            timestamp: now(),
+        });
+    }
+
+    pub(super) fn stream(&self) {
+        self.record(&Record::Stream { timestamp: now() });
+    }
+
+    pub(super) fn chunk(&self, chunk: &[u8]) {
+        self.record(&Record::Chunk {
+            data: B64.encode(chunk),
+        });
+    }
+
+    pub(super) fn frame(&self, frame: &SseEvent) {
+        self.record(&Record::Frame {
+            event: frame.event.clone(),
+            id: frame.id.clone(),
+            data: frame.data.clone(),
+        });
+    }
+
+    pub(super) fn finish(&self, outcome: Option<AgentEvent>) {
+        self.record(&Record::Outcome {
+            outcome,
+            timestamp: now(),
+        });
+    }
+}
+
+/// Re-encodes a recorded frame as it came off the wire.
+fn encode_frame(event: &str, id: Option<&str>, data: &str) -> Vec<u8> {
+    let mut out = String::new();
+    if !event.is_empty() {
+        out.push_str(&format!("event: {}\n", event));
+    }
+    if let Some(id) = id {
+        out.push_str(&format!("id: {}\n", id));
+    }
+    for line in data.split('\n') {
+        out.push_str(&format!("data: {}\n", line));
+    }
+    out.push('\n');
+    out.into_bytes()
+}
+
+/// One connection's response as recorded: its raw chunks, and its decoded
+/// frames for logs written before chunks were recorded.
+#[derive(Default)]
+struct RecordedStream {
+    chunks: Vec<Vec<u8>>,
+    frames: Vec<Vec<u8>>,
+}
+
+impl RecordedStream {
+    fn into_body(self) -> Vec<Vec<u8>> {
+        if self.chunks.is_empty() {
+            self.frames
+        } else {
+            self.chunks
+        }
+    }
+}
+
+/// Recorded responses grouped by request, then by connection. Logs without
+/// `Stream` records hold one connection per request.
+fn recorded_streams(records: &[Record]) -> Result<Vec<Vec<Vec<Vec<u8>>>>, String> {
+    let mut requests: Vec<Vec<RecordedStream>> = Vec::new();
+    for record in records {
+        let stream = match record {
+            Record::Request { .. } => {
+                requests.push(Vec::new());
+                continue;
+            }
+            Record::Stream { .. } => {
+                if requests.is_empty() {
+                    requests.push(Vec::new());
+                }
+                if let Some(connections) = requests.last_mut() {
+                    connections.push(RecordedStream::default());
+                }
+                continue;
+            }
+            Record::Chunk { .. } | Record::Frame { .. } => {
+                if requests.is_empty() {
+                    requests.push(Vec::new());
+                }
+                let Some(connections) = requests.last_mut() else {
+                    continue;
+                };
+                if connections.is_empty() {
+                    connections.push(RecordedStream::default());
+                }
+                let Some(stream) = connections.last_mut() else {
+                    continue;
+                };
+                stream
+            }
+            _ => continue,
+        };
+
+        match record {
+            Record::Chunk { data } => stream.chunks.push(
+                B64.decode(data)
+                    .map_err(|e| format!("Corrupt chunk in run log: {}", e))?,
+            ),
+            Record::Frame { event, id, data } => {
+                stream.frames.push(encode_frame(event, id.as_deref(), data))
+            }
+            _ => {}
+        }
+    }
+
+    Ok(requests
+        .into_iter()
+        .map(|connections| {
+            connections
+                .into_iter()
+                .map(RecordedStream::into_body)
+                .collect()
+        })
+        .collect())
+}
+
+fn summarize(records: &[Record]) -> Option<RecordedRun> {
+    let Some(Record::Start {
+        run_id,
+        action,
+        provider,
+        staged,
+        timestamp,
+        ..
+    }) = records.first()
+    else {
+        return None;
+    };
+
+    let mut run = RecordedRun {
+        run_id: run_id.clone(),
+        action: action.clone(),
+        provider: provider.clone(),
+        staged: *staged,
+        started_at: timestamp.clone(),
+        finished_at: None,
+        outcome: None,
+        requests: 0,
+        frames: 0,
+        events: 0,
+    };
+    for record in records {
+        match record {
+            Record::Request { .. } => run.requests += 1,
+            Record::Frame { .. } => run.frames += 1,
+            Record::Stream { .. } | Record::Chunk { .. } => {}
+            Record::Event { .. } => run.events += 1,
+            Record::Outcome { outcome, timestamp } => {
+                run.finished_at = Some(timestamp.clone());
+                run.outcome = outcome
+                    .as_ref()
+                    .and_then(AgentEvent::outcome_label)
+                    .map(String::from);
+            }
+            Record::Start { .. } => {}
+        }
+    }
+    Some(run)
+}
+
+// Commands
+/// Recorded runs of a project, newest first.
+#[tauri::command]
+pub async fn list_agent_runs<R: Runtime>(
+    app: AppHandle<R>,
+    project_path: String,
+) -> Result<Vec<RecordedRun>, String> {
+    let dir = project_data_dir(&app, RUNS_DIR, &project_path)?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut runs: Vec<RecordedRun> = fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read run logs: {}", e))?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "jsonl"))
+        .filter_map(|e| read_log(&e.path()).ok())
+        .filter_map(|records| summarize(&records))
+        .collect();
+
+    runs.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+    Ok(runs)
+}
+
+/// The full log of one run, for inspection or a bug report.
+#[tauri::command]
+pub async fn get_agent_run_log<R: Runtime>(
+    app: AppHandle<R>,
+    project_path: String,
+    run_id: String,
+) -> Result<Vec<Record>, String> {
+    read_log(&log_file(&app, &project_path, &run_id)?)
+}
+
+/// Feeds a run's recorded backend responses, byte for byte, through
+/// `process_stream` into a scratch directory, one request after another and
+/// each connection in turn.
+/// The directory starts from the run's undo snapshots when they still exist,
+/// so patches apply against the files the run originally saw.
+#[tauri::command]
+pub async fn replay_agent_run<R: Runtime>(
+    app: AppHandle<R>,
+    project_path: String,
+    run_id: String,
+    scratch_path: Option<String>,
+) -> Result<ReplayReport, String> {
+    let records = read_log(&log_file(&app, &project_path, &run_id)?)?;
+    match records.first() {
+        Some(Record::Start { provider, .. }) if provider == "backend" => {}
+        Some(Record::Start { .. }) => {
+            return Err("Only runs against the agent backend can be replayed".to_string())
+        }
+        _ => return Err("Run log has no start record".to_string()),
+    }
+
+    let replay_id = format!("replay-{}", uuid::Uuid::new_v4());
+    let scratch = match scratch_path {
+        Some(path) => {
+            let path = PathBuf::from(path);
+            // never replay over real files
+            if path.exists()
+                && fs::read_dir(&path)
+                    .map_err(|e| format!("Failed to read scratch dir: {}", e))?
+                    .next()
+                    .is_some()
+            {
+                return Err(format!(
+                    "Scratch directory is not empty: {}",
+                    path.display()
+                ));
+            }
+            path
+        }
+        None => run_data_dir(&app, REPLAY_DIR, &replay_id)?,
+    };
+    fs::create_dir_all(&scratch).map_err(|e| format!("Failed to create scratch dir: {}", e))?;
+    let seeded = rollback::seed(&app, &run_id, &scratch).await?;
+
+    let requests = recorded_streams(&records)?;
+    let frames = records
+        .iter()
+        .filter(|r| matches!(r, Record::Frame { .. }))
+        .count();
+
+    let scratch_path = scratch.to_string_lossy().to_string();
+    let run = AgentRun::replay(
+        &app,
+        &replay_id,
+        &scratch_path,
+        &policy::load(&app, &project_path)?,
+    )?;
+    let replayed = requests.len();
+    for connections in requests {
+        // as in `stream_agent`: one resume state per request, shared by its reconnects
+        let mut state = ResumeState::default();
+        for chunks in connections {
+            let body = StreamBody::Replay(chunks.into());
+            if let StreamEnd::Finished = process_stream(&run, body, &mut state).await? {
+                break;
+            }
+        }
+    }
+    let outcome = run.outcome();
+
+    Ok(ReplayReport {
+        scratch_path: scratch_path.clone(),
+        requests: replayed,
+        frames,
+        seeded,
+        outcome,
+    })
+}
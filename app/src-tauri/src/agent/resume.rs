@@ -0,0 +1,97 @@
+use super::AgentEvent;
+use std::collections::HashSet;
+use std::time::Duration;
+
+pub(super) const MAX_RECONNECTS: u32 = 5;
+
+const BASE_DELAY_MS: u64 = 500;
+const MAX_DELAY_MS: u64 = 10_000;
+
+/// What survives a dropped connection: where to resume from and which file
+/// operations have already landed, so a replayed frame is not applied twice.
+#[derive(Debug, Default)]
+pub(super) struct ResumeState {
+    pub last_event_id: Option<String>,
+    pub retry_ms: Option<u64>,
+    // event ids applied on earlier connections, all at or before the resume point
+    replayed: HashSet<String>,
+    // event ids applied on the current connection
+    current: HashSet<String>,
+    touched: Vec<String>,
+}
+
+impl ResumeState {
+    /// Call as each connection's stream starts.
+    pub fn begin_stream(&mut self) {
+        self.replayed.extend(self.current.drain());
+    }
+
+    /// Records a file operation carried by a frame with event id `id`,
+    /// returning `false` for a frame an earlier connection already applied.
+    /// Repeats within one stream are real operations and always apply.
+    pub fn mark_applied(&mut self, event: &AgentEvent, id: Option<&str>) -> bool {
+        if let Some(id) = id {
+            if self.replayed.contains(id) {
+                return false;
+            }
+            self.current.insert(id.to_string());
+        }
+        for path in event.touched_paths() {
+            if !self.touched.iter().any(|p| p == path) {
+                self.touched.push(path.to_string());
+            }
+        }
+        true
+    }
+
+    pub fn applied_files(&self) -> Vec<String> {
+        self.touched.clone()
+    }
+
+    /// Exponential backoff, starting from the server's `retry:` hint when it gave one.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let base = self.retry_ms.unwrap_or(BASE_DELAY_MS);
+        let delay = base.saturating_mul(1 << attempt.saturating_sub(1).min(16));
+        Duration::from_millis(delay.min(MAX_DELAY_MS.max(base)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &str, content: &str) -> AgentEvent {
+        AgentEvent::FileWrite {
+            path: path.into(),
+            content: content.into(),
+        }
+    }
+
+    #[test]
+    fn repeats_within_a_stream_apply() {
+        let mut state = ResumeState::default();
+        state.begin_stream();
+
+        assert!(state.mark_applied(&write("a.ts", "1"), Some("1")));
+        assert!(state.mark_applied(&write("b.ts", "2"), Some("2")));
+        assert!(state.mark_applied(&write("a.ts", "1"), Some("3")));
+        // frames without an id of their own share the last one
+        assert!(state.mark_applied(&write("a.ts", "1"), Some("3")));
+        assert!(state.mark_applied(&write("a.ts", "1"), None));
+        assert_eq!(state.applied_files(), vec!["a.ts", "b.ts"]);
+    }
+
+    #[test]
+    fn frames_from_before_the_resume_point_are_skipped() {
+        let mut state = ResumeState::default();
+        state.begin_stream();
+        assert!(state.mark_applied(&write("a.ts", "1"), Some("1")));
+        assert!(state.mark_applied(&write("b.ts", "2"), Some("2")));
+
+        state.begin_stream();
+        assert!(!state.mark_applied(&write("b.ts", "2"), Some("2")));
+        // the same operation under a new id is a new operation
+        assert!(state.mark_applied(&write("a.ts", "1"), Some("3")));
+        assert!(state.mark_applied(&write("c.ts", "3"), None));
+    }
+}
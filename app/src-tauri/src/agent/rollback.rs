@@ -0,0 +1,294 @@
+use super::run_data_dir;
+use crate::project::{resolve_safe, resolve_within};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Runtime};
+use tokio::fs;
+
+const ROLLBACK_DIR: &str = "agent-rollback";
+const JOURNAL_FILE: &str = "journal.json";
+const ORIGINALS_DIR: &str = "originals";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    path: String,
+    // false when the run created the file
+    existed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Journal {
+    project_path: String,
+    entries: Vec<Entry>,
+}
+
+/// Records the pre-run state of each file an agent run touches.
+pub(super) struct Snapshots {
+    dir: PathBuf,
+    base: PathBuf,
+    journal: Mutex<Journal>,
+}
+
+impl Snapshots {
+    pub(super) fn begin<R: Runtime>(
+        app: &AppHandle<R>,
+        run_id: &str,
+        base_path: &Path,
+    ) -> Result<Self, String> {
+        let dir = run_data_dir(app, ROLLBACK_DIR, run_id)?;
+        // an earlier run with this id can still be undone; never reset its journal
+        if dir.join(JOURNAL_FILE).exists() {
+            return Err(format!("Run id already has an undo journal: {}", run_id));
+        }
+
+        std::fs::create_dir_all(dir.join(ORIGINALS_DIR))
+            .map_err(|e| format!("Failed to create rollback area: {}", e))?;
+
+        let journal = Journal {
+            project_path: base_path.to_string_lossy().to_string(),
+            entries: Vec::new(),
+        };
+        let raw = serde_json::to_string_pretty(&journal).map_err(|e| e.to_string())?;
+        std::fs::write(dir.join(JOURNAL_FILE), raw)
+            .map_err(|e| format!("Failed to write rollback journal: {}", e))?;
+
+        Ok(Self {
+            dir,
+            base: base_path.to_path_buf(),
+            journal: Mutex::new(journal),
+        })
+    }
+
+    /// Saves the current content of `rel_path` the first time the run touches it.
+    pub(super) async fn record(&self, rel_path: &str) -> Result<(), String> {
+        let index = {
+            let journal = self.journal.lock().map_err(|e| e.to_string())?;
+            if journal.entries.iter().any(|e| e.path == rel_path) {
+                return Ok(());
+            }
+            journal.entries.len()
+        };
+
+        // the same check writes go through, so a symlink cannot point the snapshot outside
+        let target = resolve_within(&self.base.to_string_lossy(), rel_path)?;
+        let existed = fs::metadata(&target)
+            .await
+            .map(|m| m.is_file())
+            .unwrap_or(false);
+
+        if existed {
+            fs::copy(
+                &target,
+                self.dir.join(ORIGINALS_DIR).join(index.to_string()),
+            )
+            .await
+            .map_err(|e| format!("Failed to snapshot {}: {}", rel_path, e))?;
+        }
+
+        let raw = {
+            let mut journal = self.journal.lock().map_err(|e| e.to_string())?;
+            journal.entries.push(Entry {
+                path: rel_path.to_string(),
+                existed,
+            });
+            serde_json::to_string_pretty(&*journal).map_err(|e| e.to_string())?
+        };
+
+        fs::write(self.dir.join(JOURNAL_FILE), raw)
+            .await
+            .map_err(|e| format!("Failed to write rollback journal: {}", e))
+    }
+}
+
+/// Recreates the files the run touched as they were before it, under `dest`.
+/// Returns `false` when the run left no snapshots (staged, or undone).
+pub(super) async fn seed<R: Runtime>(
+    app: &AppHandle<R>,
+    run_id: &str,
+    dest: &Path,
+) -> Result<bool, String> {
+    let dir = run_data_dir(app, ROLLBACK_DIR, run_id)?;
+
+    let Ok(raw) = fs::read_to_string(dir.join(JOURNAL_FILE)).await else {
+        return Ok(false);
+    };
+    let journal: Journal =
+        serde_json::from_str(&raw).map_err(|e| format!("Corrupt rollback journal: {}", e))?;
+
+    for (index, entry) in journal.entries.iter().enumerate() {
+        if !entry.existed {
+            continue;
+        }
+        let target = dest.join(&entry.path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::copy(dir.join(ORIGINALS_DIR).join(index.to_string()), &target)
+            .await
+            .map_err(|e| format!("Failed to seed {}: {}", entry.path, e))?;
+    }
+
+    Ok(true)
+}
+
+/// Puts every journaled file back the way it was and deletes the ones the run
+/// created. Returns the restored paths.
+pub(super) async fn restore<R: Runtime>(
+    app: &AppHandle<R>,
+    run_id: &str,
+) -> Result<Vec<String>, String> {
+    restore_dir(&run_data_dir(app, ROLLBACK_DIR, run_id)?).await
+}
+
+async fn restore_entry(
+    dir: &Path,
+    project_path: &str,
+    index: usize,
+    entry: &Entry,
+) -> Result<(), String> {
+    let target = resolve_safe(project_path, &entry.path)?;
+
+    if entry.existed {
+        fs::copy(dir.join(ORIGINALS_DIR).join(index.to_string()), &target)
+            .await
+            .map_err(|e| format!("Failed to restore {}: {}", entry.path, e))?;
+        return Ok(());
+    }
+
+    match fs::metadata(&target).await {
+        // whatever is there now is not what the run created
+        Ok(meta) if meta.is_dir() => Err(format!("{} is now a directory", entry.path)),
+        Ok(_) => fs::remove_file(&target)
+            .await
+            .map_err(|e| format!("Failed to remove {}: {}", entry.path, e)),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Restores every entry it can. When some fail, the journal is kept so the
+/// undo can be retried, and the failures are reported together.
+async fn restore_dir(dir: &Path) -> Result<Vec<String>, String> {
+    let raw = fs::read_to_string(dir.join(JOURNAL_FILE))
+        .await
+        .map_err(|_| "Nothing to undo for this run".to_string())?;
+    let journal: Journal =
+        serde_json::from_str(&raw).map_err(|e| format!("Corrupt rollback journal: {}", e))?;
+
+    let mut restored = Vec::new();
+    let mut failed = Vec::new();
+
+    // newest first, so the oldest snapshot of any path wins
+    for (index, entry) in journal.entries.iter().enumerate().rev() {
+        match restore_entry(dir, &journal.project_path, index, entry).await {
+            Ok(()) => restored.push(entry.path.clone()),
+            Err(e) => failed.push(e),
+        }
+    }
+
+    if !failed.is_empty() {
+        failed.reverse();
+        return Err(format!(
+            "Undo restored {} of {} file(s); fix these and undo again: {}",
+            restored.len(),
+            journal.entries.len(),
+            failed.join("; ")
+        ));
+    }
+
+    fs::remove_dir_all(dir)
+        .await
+        .map_err(|e| format!("Failed to clear rollback area: {}", e))?;
+
+    restored.reverse();
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshots() -> (Snapshots, PathBuf) {
+        let root = std::env::temp_dir()
+            .join("coding-editor-rollback")
+            .join(uuid::Uuid::new_v4().to_string());
+        let base = root.join("project");
+        let dir = root.join("run");
+        std::fs::create_dir_all(&base).unwrap();
+        std::fs::create_dir_all(dir.join(ORIGINALS_DIR)).unwrap();
+        let base = base.canonicalize().unwrap();
+        let snapshots = Snapshots {
+            dir,
+            journal: Mutex::new(Journal {
+                project_path: base.to_string_lossy().to_string(),
+                entries: Vec::new(),
+            }),
+            base: base.clone(),
+        };
+        (snapshots, base)
+    }
+
+    #[tokio::test]
+    async fn restores_the_first_snapshot_of_a_path() {
+        let (snapshots, base) = snapshots();
+        std::fs::write(base.join("a.ts"), "original\n").unwrap();
+
+        snapshots.record("a.ts").await.unwrap();
+        std::fs::write(base.join("a.ts"), "first edit\n").unwrap();
+        snapshots.record("a.ts").await.unwrap();
+        std::fs::write(base.join("a.ts"), "second edit\n").unwrap();
+
+        let restored = restore_dir(&snapshots.dir).await.unwrap();
+        assert_eq!(restored, vec!["a.ts"]);
+        assert_eq!(
+            std::fs::read_to_string(base.join("a.ts")).unwrap(),
+            "original\n"
+        );
+        assert!(!snapshots.dir.exists());
+    }
+
+    #[tokio::test]
+    async fn removes_files_the_run_created() {
+        let (snapshots, base) = snapshots();
+
+        snapshots.record("app/new.ts").await.unwrap();
+        std::fs::create_dir_all(base.join("app")).unwrap();
+        std::fs::write(base.join("app/new.ts"), "new\n").unwrap();
+
+        restore_dir(&snapshots.dir).await.unwrap();
+        assert!(!base.join("app/new.ts").exists());
+    }
+
+    #[tokio::test]
+    async fn reports_what_it_could_not_restore_and_keeps_going() {
+        let (snapshots, base) = snapshots();
+        std::fs::write(base.join("a.ts"), "original\n").unwrap();
+
+        snapshots.record("a.ts").await.unwrap();
+        snapshots.record("b.ts").await.unwrap();
+        std::fs::write(base.join("a.ts"), "edited\n").unwrap();
+        std::fs::create_dir_all(base.join("b.ts")).unwrap();
+
+        let err = restore_dir(&snapshots.dir).await.unwrap_err();
+        assert!(err.contains("b.ts is now a directory"), "{}", err);
+        assert_eq!(
+            std::fs::read_to_string(base.join("a.ts")).unwrap(),
+            "original\n"
+        );
+        assert!(snapshots.dir.join(JOURNAL_FILE).exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn does_not_snapshot_through_a_symlink_out_of_the_project() {
+        let (snapshots, base) = snapshots();
+        let outside = base.parent().unwrap().join("outside");
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("secret"), "secret\n").unwrap();
+        std::os::unix::fs::symlink(&outside, base.join("link")).unwrap();
+
+        assert!(snapshots.record("link/secret").await.is_err());
+    }
+}
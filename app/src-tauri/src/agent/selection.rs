@@ -0,0 +1,238 @@
+use super::normalize_rel;
+use serde::{Deserialize, Serialize};
+
+/// A 1-based, end-exclusive line/column range in the file being edited.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all(deserialize = "camelCase"))]
+pub struct Selection {
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+/// Byte offset of a 1-based line/column position, counting columns in characters.
+fn offset(text: &str, line: usize, column: usize) -> Option<usize> {
+    if line == 0 || column == 0 {
+        return None;
+    }
+
+    let mut line_start = 0;
+    for (index, current) in text.split_inclusive('\n').enumerate() {
+        if index + 1 == line {
+            let body = current.strip_suffix('\n').unwrap_or(current);
+            let body = body.strip_suffix('\r').unwrap_or(body);
+            return match body.char_indices().nth(column - 1) {
+                Some((byte, _)) => Some(line_start + byte),
+                None if column - 1 == body.chars().count() => Some(line_start + body.len()),
+                None => None,
+            };
+        }
+        line_start += current.len();
+    }
+
+    // the position just past a trailing newline
+    (line == text.split_inclusive('\n').count() + 1 && column == 1).then_some(text.len())
+}
+
+/// The selected region of a file as the agent saw it, and the text around it.
+#[derive(Debug)]
+pub(super) struct SelectionScope {
+    path: String,
+    prefix: String,
+    region: String,
+    suffix: String,
+}
+
+impl SelectionScope {
+    pub(super) fn new(path: &str, content: &str, selection: &Selection) -> Result<Self, String> {
+        let start = offset(content, selection.start_line, selection.start_column);
+        let end = offset(content, selection.end_line, selection.end_column);
+
+        let (start, end) = match (start, end) {
+            (Some(start), Some(end)) if start <= end => (start, end),
+            _ => return Err("Selection is outside the file".to_string()),
+        };
+
+        Ok(Self {
+            path: normalize_rel(path)?,
+            prefix: content[..start].to_string(),
+            region: content[start..end].to_string(),
+            suffix: content[end..].to_string(),
+        })
+    }
+
+    pub(super) fn covers(&self, rel_path: &str) -> bool {
+        normalize_rel(rel_path).is_ok_and(|p| p == self.path)
+    }
+
+    /// The whole file as it was sent to the agent.
+    pub(super) fn original(&self) -> String {
+        format!("{}{}{}", self.prefix, self.region, self.suffix)
+    }
+
+    /// Pulls the new selected region out of a whole-file rewrite, failing if the
+    /// agent changed anything outside the selection.
+    pub(super) fn extract(&self, rewritten: &str) -> Result<String, String> {
+        let outside = self.prefix.len() + self.suffix.len();
+        if rewritten.len() < outside
+            || !rewritten.starts_with(&self.prefix)
+            || !rewritten.ends_with(&self.suffix)
+        {
+            return Err("Agent changed code outside the selection".to_string());
+        }
+
+        Ok(rewritten[self.prefix.len()..rewritten.len() - self.suffix.len()].to_string())
+    }
+
+    /// Splices `replacement` into `current` where the selected region now is.
+    /// Fails if the region itself changed since the edit started.
+    pub(super) fn merge(&self, current: &str, replacement: &str) -> Result<String, String> {
+        let changed =
+            || "The selected code changed on disk while the agent was working".to_string();
+
+        let start = if self.region.is_empty() {
+            if current.starts_with(&self.prefix) {
+                self.prefix.len()
+            } else if current.ends_with(&self.suffix) {
+                current.len() - self.suffix.len()
+            } else {
+                return Err(changed());
+            }
+        } else {
+            let matches: Vec<usize> = current
+                .match_indices(&self.region)
+                .map(|(i, _)| i)
+                .collect();
+            match matches.as_slice() {
+                [] => return Err(changed()),
+                [only] => *only,
+                _ => matches
+                    .iter()
+                    .copied()
+                    .find(|&i| i == self.prefix.len())
+                    .ok_or("The selected code now appears more than once in the file")?,
+            }
+        };
+        let end = start + self.region.len();
+
+        Ok(format!(
+            "{}{}{}",
+            &current[..start],
+            replacement,
+            &current[end..]
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn select(
+        start_line: usize,
+        start_column: usize,
+        end_line: usize,
+        end_column: usize,
+    ) -> Selection {
+        Selection {
+            start_line,
+            start_column,
+            end_line,
+            end_column,
+        }
+    }
+
+    fn scope(content: &str, selection: Selection) -> SelectionScope {
+        SelectionScope::new("app/index.tsx", content, &selection).unwrap()
+    }
+
+    #[test]
+    fn offsets_skip_crlf() {
+        let text = "ab\r\ncd\r\n";
+        assert_eq!(offset(text, 1, 3), Some(2));
+        // the `\r` is not a column
+        assert_eq!(offset(text, 1, 4), None);
+        assert_eq!(offset(text, 2, 1), Some(4));
+        assert_eq!(offset(text, 3, 1), Some(text.len()));
+    }
+
+    #[test]
+    fn offsets_at_end_of_file() {
+        assert_eq!(offset("a\nb", 2, 2), Some(3));
+        assert_eq!(offset("a\n", 2, 1), Some(2));
+        assert_eq!(offset("a\n", 3, 1), None);
+        assert_eq!(offset("", 1, 1), Some(0));
+    }
+
+    #[test]
+    fn offsets_count_characters() {
+        // `é` is two bytes
+        assert_eq!(offset("héllo", 1, 3), Some(3));
+        assert_eq!(offset("héllo", 1, 6), Some(6));
+        assert_eq!(offset("héllo", 1, 7), None);
+    }
+
+    #[test]
+    fn out_of_range_positions_are_rejected() {
+        assert_eq!(offset("abc", 0, 1), None);
+        assert_eq!(offset("abc", 1, 0), None);
+        assert_eq!(offset("abc", 5, 1), None);
+
+        let content = "one\ntwo\n";
+        for selection in [select(1, 1, 9, 1), select(2, 1, 1, 1), select(1, 9, 2, 1)] {
+            assert!(SelectionScope::new("a.ts", content, &selection).is_err());
+        }
+    }
+
+    #[test]
+    fn extract_takes_the_region_of_a_rewrite() {
+        let scope = scope("one\r\ntwo\r\nthree\r\n", select(2, 1, 2, 4));
+        assert_eq!(scope.original(), "one\r\ntwo\r\nthree\r\n");
+        assert_eq!(scope.extract("one\r\nTWO!\r\nthree\r\n").unwrap(), "TWO!");
+        assert!(scope.extract("ONE\r\ntwo\r\nthree\r\n").is_err());
+        assert!(scope.extract("one\r\n").is_err());
+    }
+
+    #[test]
+    fn extract_at_end_of_file() {
+        let scope = scope("a\nb", select(2, 1, 2, 2));
+        assert_eq!(scope.extract("a\nbee").unwrap(), "bee");
+    }
+
+    #[test]
+    fn merge_follows_the_region_when_it_moves() {
+        let scope = scope("one\ntwo\nthree\n", select(2, 1, 2, 4));
+        let current = "zero\none\ntwo\nthree\n";
+        assert_eq!(scope.merge(current, "2").unwrap(), "zero\none\n2\nthree\n");
+    }
+
+    #[test]
+    fn merge_fails_when_the_region_changed() {
+        let scope = scope("one\ntwo\nthree\n", select(2, 1, 2, 4));
+        assert!(scope.merge("one\nTWO\nthree\n", "2").is_err());
+    }
+
+    #[test]
+    fn merge_of_a_repeated_region_needs_it_in_place() {
+        let scope = scope("x\nx\n", select(2, 1, 2, 2));
+        assert_eq!(scope.merge("x\nx\n", "y").unwrap(), "x\ny\n");
+        assert_eq!(scope.merge("x\nx\nx\n", "y").unwrap(), "x\ny\nx\n");
+        assert!(scope.merge("x\nzx\nx\n", "y").is_err());
+    }
+
+    #[test]
+    fn merge_inserts_at_an_empty_selection() {
+        let scope = scope("ab\ncd\n", select(2, 2, 2, 2));
+        assert_eq!(scope.merge("ab\ncd\n", "-").unwrap(), "ab\nc-d\n");
+        // the text after the cursor still anchors it
+        assert_eq!(scope.merge("ab\nxcd\n", "-").unwrap(), "ab\nxc-d\n");
+        assert!(scope.merge("AB\nCD\n", "-").is_err());
+    }
+
+    #[test]
+    fn merge_handles_multibyte_regions() {
+        let scope = scope("let é = 1;\n", select(1, 5, 1, 6));
+        assert_eq!(scope.merge("let é = 1;\n", "ü").unwrap(), "let ü = 1;\n");
+    }
+}
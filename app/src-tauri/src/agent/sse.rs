@@ -0,0 +1,397 @@
+//! Incremental `text/event-stream` decoder following the WHATWG parsing rules.
+//! Input the spec would silently drop is surfaced as [`SseItem::Diagnostic`].
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    pub event: String,
+    pub data: String,
+    pub id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SseItem {
+    Event(SseEvent),
+    Comment(String),
+    Diagnostic(String),
+}
+
+#[derive(Debug)]
+pub struct SseDecoder {
+    buf: Vec<u8>,
+    max_size: usize,
+    // a chunk ended on CR; a leading LF in the next chunk belongs to it
+    pending_cr: bool,
+    started: bool,
+    event_type: String,
+    data: String,
+    has_data: bool,
+    // the `id:` of the event being read; it becomes the last event id only
+    // once that event is dispatched
+    id_buffer: Option<String>,
+    last_event_id: Option<String>,
+    retry: Option<u64>,
+}
+
+impl SseDecoder {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            buf: Vec::new(),
+            max_size,
+            pending_cr: false,
+            started: false,
+            event_type: String::new(),
+            data: String::new(),
+            has_data: false,
+            id_buffer: None,
+            last_event_id: None,
+            retry: None,
+        }
+    }
+
+    /// The `id:` of the most recently dispatched event, which is what a
+    /// reconnect sends as `Last-Event-ID`.
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+
+    /// Reconnection delay requested by the server, in milliseconds.
+    pub fn retry(&self) -> Option<u64> {
+        self.retry
+    }
+
+    /// Consumes a chunk of the response body and returns everything it completed.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<SseItem>, String> {
+        let mut chunk = chunk;
+
+        if self.pending_cr {
+            self.pending_cr = false;
+            if let Some(rest) = chunk.strip_prefix(b"\n") {
+                chunk = rest;
+            }
+        }
+
+        self.buf.extend_from_slice(chunk);
+
+        if !self.started {
+            if self.buf.len() < BOM.len() && BOM.starts_with(&self.buf) {
+                return Ok(Vec::new());
+            }
+            self.started = true;
+            if self.buf.starts_with(BOM) {
+                self.buf.drain(..BOM.len());
+            }
+        }
+
+        let mut items = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+
+        while i < self.buf.len() {
+            let byte = self.buf[i];
+            if byte != b'\n' && byte != b'\r' {
+                i += 1;
+                continue;
+            }
+
+            let line = self.buf[start..i].to_vec();
+            let mut next = i + 1;
+            if byte == b'\r' {
+                match self.buf.get(next) {
+                    Some(b'\n') => next += 1,
+                    None => self.pending_cr = true,
+                    _ => {}
+                }
+            }
+
+            self.process_line(&line, &mut items);
+            start = next;
+            i = next;
+        }
+
+        self.buf.drain(..start);
+
+        if self.buf.len() + self.data.len() > self.max_size {
+            return Err("Response exceeded safety limit".into());
+        }
+
+        Ok(items)
+    }
+
+    /// Flushes the decoder at end of stream. Per the spec an event that was not
+    /// terminated by a blank line is discarded, but we say so.
+    pub fn finish(&mut self) -> Vec<SseItem> {
+        let mut items = Vec::new();
+
+        if !self.buf.is_empty() {
+            let line = std::mem::take(&mut self.buf);
+            self.process_line(&line, &mut items);
+        }
+
+        if self.has_data {
+            items.push(SseItem::Diagnostic(
+                "Stream ended in the middle of an event; partial event discarded".into(),
+            ));
+            self.reset_event();
+        }
+
+        items
+    }
+
+    fn process_line(&mut self, line: &[u8], items: &mut Vec<SseItem>) {
+        let line = match std::str::from_utf8(line) {
+            Ok(s) => s.to_string(),
+            Err(_) => {
+                items.push(SseItem::Diagnostic(
+                    "Invalid UTF-8 in event stream; replaced undecodable bytes".into(),
+                ));
+                String::from_utf8_lossy(line).into_owned()
+            }
+        };
+
+        if line.is_empty() {
+            self.dispatch(items);
+            return;
+        }
+
+        if let Some(comment) = line.strip_prefix(':') {
+            items.push(SseItem::Comment(
+                comment.strip_prefix(' ').unwrap_or(comment).to_string(),
+            ));
+            return;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_str(), ""),
+        };
+
+        match field {
+            "event" => self.event_type = value.to_string(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+                self.has_data = true;
+            }
+            "id" => {
+                if value.contains('\0') {
+                    items.push(SseItem::Diagnostic(
+                        "Ignored `id` field containing a NUL character".into(),
+                    ));
+                } else {
+                    self.id_buffer = Some(value.to_string());
+                }
+            }
+            "retry" => match value.parse::<u64>() {
+                Ok(ms) if value.bytes().all(|b| b.is_ascii_digit()) => self.retry = Some(ms),
+                _ => items.push(SseItem::Diagnostic(format!(
+                    "Ignored malformed `retry` value: {}",
+                    value
+                ))),
+            },
+            _ => items.push(SseItem::Diagnostic(format!(
+                "Ignored unknown event stream field: {}",
+                field
+            ))),
+        }
+    }
+
+    fn dispatch(&mut self, items: &mut Vec<SseItem>) {
+        self.last_event_id = self.id_buffer.clone();
+        if !self.has_data {
+            self.event_type.clear();
+            return;
+        }
+
+        let mut data = std::mem::take(&mut self.data);
+        data.pop(); // trailing LF
+
+        let event = if self.event_type.is_empty() {
+            "message".to_string()
+        } else {
+            std::mem::take(&mut self.event_type)
+        };
+
+        items.push(SseItem::Event(SseEvent {
+            event,
+            data,
+            id: self.last_event_id.clone(),
+        }));
+        self.reset_event();
+    }
+
+    fn reset_event(&mut self) {
+        self.event_type.clear();
+        self.data.clear();
+        self.has_data = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(chunks: &[&[u8]]) -> Vec<SseItem> {
+        let mut decoder = SseDecoder::new(1024 * 1024);
+        let mut items = Vec::new();
+        for chunk in chunks {
+            items.extend(decoder.feed(chunk).unwrap());
+        }
+        items.extend(decoder.finish());
+        items
+    }
+
+    fn events(items: &[SseItem]) -> Vec<&SseEvent> {
+        items
+            .iter()
+            .filter_map(|item| match item {
+                SseItem::Event(e) => Some(e),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn message(data: &str) -> SseEvent {
+        SseEvent {
+            event: "message".into(),
+            data: data.into(),
+            id: None,
+        }
+    }
+
+    #[test]
+    fn decodes_lf_frames() {
+        let items = decode(&[b"data: one\n\ndata: two\n\n"]);
+        assert_eq!(events(&items), vec![&message("one"), &message("two")]);
+    }
+
+    #[test]
+    fn decodes_crlf_and_cr_frames() {
+        let items = decode(&[b"data: one\r\n\r\ndata: two\r\rdata: three\r\n\n"]);
+        assert_eq!(
+            events(&items),
+            vec![&message("one"), &message("two"), &message("three")]
+        );
+    }
+
+    #[test]
+    fn crlf_split_across_chunks_is_one_line_ending() {
+        let items = decode(&[b"data: one\r", b"\ndata: two\r", b"\n\r", b"\n"]);
+        assert_eq!(events(&items), vec![&message("one\ntwo")]);
+    }
+
+    #[test]
+    fn joins_multi_line_data() {
+        let items = decode(&[b"data: {\"a\":\ndata: 1}\n\n"]);
+        assert_eq!(events(&items), vec![&message("{\"a\":\n1}")]);
+    }
+
+    #[test]
+    fn strips_only_one_leading_space() {
+        let items = decode(&[b"data:no-space\n\ndata:  two\n\n"]);
+        assert_eq!(events(&items), vec![&message("no-space"), &message(" two")]);
+    }
+
+    #[test]
+    fn tracks_event_type_and_id() {
+        let mut decoder = SseDecoder::new(1024);
+        let items = decoder
+            .feed(b"event: update\nid: 7\ndata: x\n\ndata: y\n\n")
+            .unwrap();
+
+        assert_eq!(
+            events(&items),
+            vec![
+                &SseEvent {
+                    event: "update".into(),
+                    data: "x".into(),
+                    id: Some("7".into()),
+                },
+                &SseEvent {
+                    event: "message".into(),
+                    data: "y".into(),
+                    id: Some("7".into()),
+                },
+            ]
+        );
+        assert_eq!(decoder.last_event_id(), Some("7"));
+    }
+
+    #[test]
+    fn id_counts_only_once_its_event_is_dispatched() {
+        let mut decoder = SseDecoder::new(1024);
+        decoder.feed(b"id: 1\ndata: x\n\n").unwrap();
+        // the connection drops before the blank line that ends event 2
+        let items = decoder.feed(b"id: 2\ndata: y\n").unwrap();
+
+        assert!(items.is_empty());
+        assert_eq!(decoder.last_event_id(), Some("1"));
+
+        let items = decoder.feed(b"\n").unwrap();
+        assert_eq!(events(&items)[0].id.as_deref(), Some("2"));
+        assert_eq!(decoder.last_event_id(), Some("2"));
+    }
+
+    #[test]
+    fn parses_retry_and_reports_bad_values() {
+        let mut decoder = SseDecoder::new(1024);
+        let items = decoder.feed(b"retry: 2500\nretry: soon\n\n").unwrap();
+
+        assert_eq!(decoder.retry(), Some(2500));
+        assert!(matches!(&items[..], [SseItem::Diagnostic(m)] if m.contains("retry")));
+    }
+
+    #[test]
+    fn surfaces_comments() {
+        let items = decode(&[b": keep-alive\n\n"]);
+        assert_eq!(items, vec![SseItem::Comment("keep-alive".into())]);
+    }
+
+    #[test]
+    fn reports_unknown_fields() {
+        let items = decode(&[b"dat: typo\ndata: ok\n\n"]);
+        assert!(matches!(&items[0], SseItem::Diagnostic(m) if m.contains("dat")));
+        assert_eq!(events(&items), vec![&message("ok")]);
+    }
+
+    #[test]
+    fn blank_frame_without_data_dispatches_nothing() {
+        let items = decode(&[b"event: ping\n\n\n\n"]);
+        assert!(items.is_empty());
+    }
+
+    #[test]
+    fn reassembles_utf8_split_across_chunks() {
+        let bytes = "data: héllo ✓\n\n".as_bytes();
+        let chunks: Vec<&[u8]> = bytes.chunks(1).collect();
+        let items = decode(&chunks);
+        assert_eq!(events(&items), vec![&message("héllo ✓")]);
+    }
+
+    #[test]
+    fn reports_invalid_utf8() {
+        let items = decode(&[b"data: \xFF\n\n"]);
+        assert!(matches!(&items[0], SseItem::Diagnostic(m) if m.contains("UTF-8")));
+        assert_eq!(events(&items), vec![&message("\u{FFFD}")]);
+    }
+
+    #[test]
+    fn strips_leading_bom_split_across_chunks() {
+        let items = decode(&[b"\xEF\xBB", b"\xBFdata: x\n\n"]);
+        assert_eq!(events(&items), vec![&message("x")]);
+    }
+
+    #[test]
+    fn reports_truncated_event_at_end_of_stream() {
+        let items = decode(&[b"data: partial"]);
+        assert!(events(&items).is_empty());
+        assert!(matches!(&items[..], [SseItem::Diagnostic(m)] if m.contains("partial")));
+    }
+
+    #[test]
+    fn enforces_size_limit() {
+        let mut decoder = SseDecoder::new(16);
+        assert!(decoder.feed(b"data: 0123456789abcdef").is_err());
+    }
+}
@@ -0,0 +1,678 @@
+use super::{run_data_dir, AgentRuns};
+use crate::project::{resolve_safe, resolve_within};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use similar::{DiffOp, TextDiff};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Runtime};
+
+const STAGING_DIR: &str = "agent-staging";
+const MANIFEST_FILE: &str = "manifest.json";
+const FILES_DIR: &str = "files";
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    #[default]
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FileDecision {
+    decision: Decision,
+    // per-hunk overrides of the file-level decision
+    #[serde(default)]
+    hunks: BTreeMap<usize, Decision>,
+    // hash of the project file when the decisions were made; hunk indices
+    // only mean the same hunks while it is unchanged
+    #[serde(default)]
+    base_hash: Option<String>,
+}
+
+impl FileDecision {
+    fn hunk(&self, index: usize) -> Decision {
+        self.hunks.get(&index).copied().unwrap_or(self.decision)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    project_path: String,
+    #[serde(default)]
+    files: BTreeMap<String, FileDecision>,
+    // paths the run wants removed from the project
+    #[serde(default)]
+    deletions: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StagedHunk {
+    pub index: usize,
+    pub header: String,
+    pub diff: String,
+    pub decision: Decision,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StagedFile {
+    pub path: String,
+    pub is_new: bool,
+    pub is_deleted: bool,
+    pub decision: Decision,
+    pub diff: String,
+    pub hunks: Vec<StagedHunk>,
+}
+
+struct PendingWrite {
+    path: String,
+    target: PathBuf,
+    temp: PathBuf,
+    // None removes the file
+    content: Option<String>,
+    original: Option<String>,
+}
+
+/// Shadow area of a staged run.
+pub(super) struct StagingArea {
+    dir: PathBuf,
+}
+
+impl StagingArea {
+    /// Creates the shadow area for a staged run.
+    pub(super) fn begin<R: Runtime>(
+        app: &AppHandle<R>,
+        run_id: &str,
+        base_path: &Path,
+    ) -> Result<Self, String> {
+        let dir = run_dir(app, run_id)?;
+        // a staged run awaiting review keeps its id until applied or discarded
+        if dir.join(MANIFEST_FILE).exists() {
+            return Err(format!("Run id already has staged changes: {}", run_id));
+        }
+
+        fs::create_dir_all(dir.join(FILES_DIR))
+            .map_err(|e| format!("Failed to create staging area: {}", e))?;
+
+        save_manifest(
+            &dir,
+            &Manifest {
+                project_path: base_path.to_string_lossy().to_string(),
+                files: BTreeMap::new(),
+                deletions: BTreeSet::new(),
+            },
+        )?;
+
+        Ok(Self { dir })
+    }
+
+    /// Directory that agent writes land in instead of the project.
+    pub(super) fn files_dir(&self) -> PathBuf {
+        self.dir.join(FILES_DIR)
+    }
+
+    /// Content of `rel_path` as the run currently sees it: staged if the run
+    /// wrote it, absent if the run deleted it, otherwise what is on disk.
+    pub(super) fn read(&self, base_path: &Path, rel_path: &str) -> Result<Option<String>, String> {
+        if load_manifest(&self.dir)?.deletions.contains(rel_path) {
+            return Ok(None);
+        }
+
+        match read_optional(&self.files_dir().join(rel_path))? {
+            Some(staged) => Ok(Some(staged)),
+            None => read_optional(&base_path.join(rel_path)),
+        }
+    }
+
+    /// Call after a write lands in the shadow area, so an earlier deletion is superseded.
+    pub(super) fn staged_write(&self, rel_path: &str) -> Result<(), String> {
+        let mut manifest = load_manifest(&self.dir)?;
+        if manifest.deletions.remove(rel_path) {
+            save_manifest(&self.dir, &manifest)?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn stage_delete(&self, rel_path: &str) -> Result<(), String> {
+        let staged = self.files_dir().join(rel_path);
+        if staged.exists() {
+            fs::remove_file(&staged)
+                .map_err(|e| format!("Failed to unstage '{}': {}", rel_path, e))?;
+        }
+
+        let mut manifest = load_manifest(&self.dir)?;
+        manifest.deletions.insert(rel_path.to_string());
+        save_manifest(&self.dir, &manifest)
+    }
+}
+
+/// Hash of a project file's content, or of its absence.
+fn digest(content: Option<&str>) -> String {
+    let Some(content) = content else {
+        return "absent".to_string();
+    };
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn run_dir<R: Runtime>(app: &AppHandle<R>, run_id: &str) -> Result<PathBuf, String> {
+    run_data_dir(app, STAGING_DIR, run_id)
+}
+
+fn load_manifest(dir: &Path) -> Result<Manifest, String> {
+    let raw = fs::read_to_string(dir.join(MANIFEST_FILE))
+        .map_err(|_| "No staged changes for this run".to_string())?;
+    serde_json::from_str(&raw).map_err(|e| format!("Corrupt staging manifest: {}", e))
+}
+
+fn save_manifest(dir: &Path, manifest: &Manifest) -> Result<(), String> {
+    let raw = serde_json::to_string_pretty(manifest).map_err(|e| e.to_string())?;
+    fs::write(dir.join(MANIFEST_FILE), raw)
+        .map_err(|e| format!("Failed to write staging manifest: {}", e))
+}
+
+fn collect_staged(base: &Path, dir: &Path, out: &mut Vec<String>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect_staged(base, &path, out)?;
+        } else if let Ok(relative) = path.strip_prefix(base) {
+            out.push(relative.to_string_lossy().to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Every staged path with its staged content, `None` for a staged deletion.
+fn staged_entries(
+    dir: &Path,
+    manifest: &Manifest,
+) -> Result<Vec<(String, Option<String>)>, String> {
+    let files_dir = dir.join(FILES_DIR);
+    let mut paths = Vec::new();
+
+    if files_dir.exists() {
+        collect_staged(&files_dir, &files_dir, &mut paths)
+            .map_err(|e| format!("Failed to read staging area: {}", e))?;
+    }
+
+    let mut entries = Vec::new();
+    for path in paths {
+        let staged = fs::read_to_string(files_dir.join(&path))
+            .map_err(|e| format!("Failed to read staged '{}': {}", path, e))?;
+        entries.push((path, Some(staged)));
+    }
+    for path in &manifest.deletions {
+        if !entries.iter().any(|(p, _)| p == path) {
+            entries.push((path.clone(), None));
+        }
+    }
+
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(entries)
+}
+
+fn read_optional(path: &Path) -> Result<Option<String>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    fs::read_to_string(path)
+        .map(Some)
+        .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))
+}
+
+/// Rebuilds `new` on top of `old`, keeping only the hunks `accept` selects.
+fn merge_hunks(old: &str, new: &str, accept: impl Fn(usize) -> bool) -> String {
+    let diff = TextDiff::from_lines(old, new);
+
+    let mut owner: HashMap<(usize, usize), usize> = HashMap::new();
+    for (index, group) in diff.grouped_ops(CONTEXT_LINES).iter().enumerate() {
+        for op in group {
+            if !matches!(op, DiffOp::Equal { .. }) {
+                owner.insert((op.old_range().start, op.new_range().start), index);
+            }
+        }
+    }
+
+    let old_lines = diff.old_slices();
+    let new_lines = diff.new_slices();
+    let mut out = String::with_capacity(new.len().max(old.len()));
+
+    for op in diff.ops() {
+        let take_new = match op {
+            DiffOp::Equal { .. } => false,
+            _ => owner
+                .get(&(op.old_range().start, op.new_range().start))
+                .is_some_and(|&index| accept(index)),
+        };
+
+        let lines = if take_new {
+            &new_lines[op.new_range()]
+        } else {
+            &old_lines[op.old_range()]
+        };
+        lines.iter().for_each(|line| out.push_str(line));
+    }
+
+    out
+}
+
+fn describe(
+    path: &str,
+    old: Option<&str>,
+    new: Option<&str>,
+    decision: &FileDecision,
+) -> StagedFile {
+    let diff = TextDiff::from_lines(old.unwrap_or(""), new.unwrap_or(""));
+    let mut unified = diff.unified_diff();
+    unified.context_radius(CONTEXT_LINES);
+
+    let hunks = unified
+        .iter_hunks()
+        .enumerate()
+        .map(|(index, hunk)| StagedHunk {
+            index,
+            header: hunk.header().to_string(),
+            diff: hunk.to_string(),
+            decision: decision.hunk(index),
+        })
+        .collect();
+
+    StagedFile {
+        path: path.to_string(),
+        is_new: old.is_none(),
+        is_deleted: new.is_none(),
+        decision: decision.decision,
+        diff: unified
+            .header(&format!("a/{}", path), &format!("b/{}", path))
+            .to_string(),
+        hunks,
+    }
+}
+
+fn set_decision<R: Runtime>(
+    app: &AppHandle<R>,
+    run_id: &str,
+    path: &str,
+    hunk: Option<usize>,
+    decision: Decision,
+) -> Result<(), String> {
+    let dir = run_dir(app, run_id)?;
+    let mut manifest = load_manifest(&dir)?;
+
+    if !staged_entries(&dir, &manifest)?
+        .iter()
+        .any(|(p, _)| p == path)
+    {
+        return Err(format!("'{}' is not staged in this run", path));
+    }
+
+    let current = read_optional(&resolve_within(&manifest.project_path, path)?)?;
+    let base_hash = digest(current.as_deref());
+    let entry = manifest.files.entry(path.to_string()).or_default();
+    // decisions made against older content counted different hunks
+    if entry.base_hash.as_ref().is_some_and(|h| *h != base_hash) {
+        entry.decision = Decision::Pending;
+        entry.hunks.clear();
+    }
+    entry.base_hash = Some(base_hash);
+    match hunk {
+        Some(index) => {
+            entry.hunks.insert(index, decision);
+        }
+        None => {
+            entry.decision = decision;
+            entry.hunks.clear();
+        }
+    }
+
+    save_manifest(&dir, &manifest)
+}
+
+fn cleanup_temps(writes: &[PendingWrite]) {
+    for write in writes {
+        fs::remove_file(&write.temp).ok();
+    }
+}
+
+// Commands
+#[tauri::command]
+pub async fn list_staged_files<R: Runtime>(
+    app: AppHandle<R>,
+    run_id: String,
+) -> Result<Vec<StagedFile>, String> {
+    let dir = run_dir(&app, &run_id)?;
+    let manifest = load_manifest(&dir)?;
+    let base = PathBuf::from(&manifest.project_path);
+    let default_decision = FileDecision::default();
+
+    let mut files = Vec::new();
+    for (path, staged) in staged_entries(&dir, &manifest)? {
+        let current = read_optional(&base.join(&path))?;
+        if current.is_none() && staged.is_none() {
+            continue;
+        }
+        let decision = manifest.files.get(&path).unwrap_or(&default_decision);
+
+        files.push(describe(
+            &path,
+            current.as_deref(),
+            staged.as_deref(),
+            decision,
+        ));
+    }
+
+    Ok(files)
+}
+
+#[tauri::command]
+pub async fn accept_staged_change<R: Runtime>(
+    app: AppHandle<R>,
+    run_id: String,
+    path: String,
+    hunk: Option<usize>,
+) -> Result<(), String> {
+    set_decision(&app, &run_id, &path, hunk, Decision::Accepted)
+}
+
+#[tauri::command]
+pub async fn reject_staged_change<R: Runtime>(
+    app: AppHandle<R>,
+    run_id: String,
+    path: String,
+    hunk: Option<usize>,
+) -> Result<(), String> {
+    set_decision(&app, &run_id, &path, hunk, Decision::Rejected)
+}
+
+fn apply_staged(dir: &Path) -> Result<Vec<String>, String> {
+    let manifest = load_manifest(dir)?;
+    let default_decision = FileDecision::default();
+
+    let mut writes: Vec<PendingWrite> = Vec::new();
+    for (path, staged) in staged_entries(dir, &manifest)? {
+        let decision = manifest.files.get(&path).unwrap_or(&default_decision);
+
+        let target = resolve_within(&manifest.project_path, &path)?;
+        let original = read_optional(&target)?;
+        if decision
+            .base_hash
+            .as_ref()
+            .is_some_and(|h| *h != digest(original.as_deref()))
+        {
+            return Err(format!(
+                "'{}' changed since its changes were reviewed — review it again",
+                path
+            ));
+        }
+
+        let content = match &staged {
+            // deletions are all-or-nothing
+            None if original.is_some() && decision.decision == Decision::Accepted => None,
+            None => continue,
+            Some(staged) => {
+                let merged = merge_hunks(original.as_deref().unwrap_or(""), staged, |index| {
+                    decision.hunk(index) == Decision::Accepted
+                });
+                let changed = match &original {
+                    Some(current) => *current != merged,
+                    None => decision.decision == Decision::Accepted || !merged.is_empty(),
+                };
+                if !changed {
+                    continue;
+                }
+                Some(merged)
+            }
+        };
+        // only files that are written get their directories created
+        let target = match content {
+            Some(_) => resolve_safe(&manifest.project_path, &path)?,
+            None => target,
+        };
+
+        let name = target
+            .file_name()
+            .ok_or_else(|| format!("Invalid file path: {}", path))?
+            .to_string_lossy()
+            .to_string();
+        let temp = target.with_file_name(format!(".{}.agent-staged", name));
+
+        writes.push(PendingWrite {
+            path,
+            target,
+            temp,
+            content,
+            original,
+        });
+    }
+
+    // Phase 1: stage everything next to its target so a failure leaves the project untouched.
+    for write in &writes {
+        let Some(content) = &write.content else {
+            continue;
+        };
+        if let Err(e) = fs::write(&write.temp, content) {
+            cleanup_temps(&writes);
+            return Err(format!("Failed to write '{}': {}", write.path, e));
+        }
+    }
+
+    // Phase 2: swap the files in, restoring what was already swapped if a step fails.
+    for (i, write) in writes.iter().enumerate() {
+        let result = match write.content {
+            Some(_) => fs::rename(&write.temp, &write.target),
+            None => fs::remove_file(&write.target),
+        };
+        if let Err(e) = result {
+            for done in &writes[..i] {
+                match &done.original {
+                    Some(content) => fs::write(&done.target, content).ok(),
+                    None => fs::remove_file(&done.target).ok(),
+                };
+            }
+            cleanup_temps(&writes[i..]);
+            return Err(format!("Failed to apply '{}': {}", write.path, e));
+        }
+    }
+
+    fs::remove_dir_all(dir).map_err(|e| format!("Failed to clear staging area: {}", e))?;
+
+    Ok(writes.into_iter().map(|w| w.path).collect())
+}
+
+/// Writes every accepted file or hunk into the project and closes the review.
+/// Either all accepted changes land on disk or none do.
+#[tauri::command]
+pub async fn apply_staged_changes<R: Runtime>(
+    app: AppHandle<R>,
+    runs: tauri::State<'_, AgentRuns>,
+    run_id: String,
+) -> Result<Vec<String>, String> {
+    // the run may still be writing into its staging area
+    if runs.is_active(&run_id) {
+        return Err(
+            "Agent run is still active — wait for it to finish before applying".to_string(),
+        );
+    }
+    let dir = run_dir(&app, &run_id)?;
+
+    tokio::task::spawn_blocking(move || apply_staged(&dir))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn discard_staged_changes<R: Runtime>(
+    app: AppHandle<R>,
+    run_id: String,
+) -> Result<(), String> {
+    let dir = run_dir(&app, &run_id)?;
+
+    if !dir.exists() {
+        return Ok(());
+    }
+
+    fs::remove_dir_all(&dir).map_err(|e| format!("Failed to clear staging area: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n";
+    // two changes far enough apart to be separate hunks
+    const NEW: &str = "A\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nL\n";
+
+    fn decided(decision: Decision, hunks: &[(usize, Decision)]) -> FileDecision {
+        FileDecision {
+            decision,
+            hunks: hunks.iter().copied().collect(),
+            base_hash: None,
+        }
+    }
+
+    fn staging(project: &[(&str, &str)], staged: &[(&str, &str)], manifest: Manifest) -> PathBuf {
+        let root = std::env::temp_dir()
+            .join("coding-editor-staging")
+            .join(uuid::Uuid::new_v4().to_string());
+        let write = |dir: &Path, path: &str, content: &str| {
+            let file = dir.join(path);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, content).unwrap();
+        };
+        for (path, content) in project {
+            write(&root.join("project"), path, content);
+        }
+        for (path, content) in staged {
+            write(&root.join("run").join(FILES_DIR), path, content);
+        }
+        fs::create_dir_all(root.join("project")).unwrap();
+        let project_path = root.join("project").to_string_lossy().to_string();
+        save_manifest(
+            &root.join("run"),
+            &Manifest {
+                project_path,
+                ..manifest
+            },
+        )
+        .unwrap();
+        root
+    }
+
+    fn manifest(files: Vec<(&str, FileDecision)>, deletions: &[&str]) -> Manifest {
+        Manifest {
+            project_path: String::new(),
+            files: files.into_iter().map(|(p, d)| (p.to_string(), d)).collect(),
+            deletions: deletions.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn merges_only_accepted_hunks() {
+        assert_eq!(merge_hunks(OLD, NEW, |_| true), NEW);
+        assert_eq!(merge_hunks(OLD, NEW, |_| false), OLD);
+        assert_eq!(
+            merge_hunks(OLD, NEW, |i| i == 1),
+            "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nL\n"
+        );
+    }
+
+    #[test]
+    fn describes_hunks_with_their_decisions() {
+        let decision = decided(Decision::Accepted, &[(1, Decision::Rejected)]);
+        let file = describe("app/a.ts", Some(OLD), Some(NEW), &decision);
+        assert!(!file.is_new && !file.is_deleted);
+        assert_eq!(file.hunks.len(), 2);
+        assert_eq!(file.hunks[0].decision, Decision::Accepted);
+        assert_eq!(file.hunks[1].decision, Decision::Rejected);
+        assert!(file.diff.starts_with("--- a/app/a.ts\n+++ b/app/a.ts\n"));
+
+        let created = describe("app/b.ts", None, Some("x\n"), &FileDecision::default());
+        assert!(created.is_new);
+        let deleted = describe("app/a.ts", Some(OLD), None, &FileDecision::default());
+        assert!(deleted.is_deleted);
+    }
+
+    #[test]
+    fn applies_accepted_files_hunks_and_deletions() {
+        let root = staging(
+            &[("a.ts", OLD), ("gone.ts", "x\n"), ("kept.ts", "y\n")],
+            &[("a.ts", NEW), ("new/b.ts", "b\n"), ("skipped/c.ts", "c\n")],
+            manifest(
+                vec![
+                    (
+                        "a.ts",
+                        decided(Decision::Pending, &[(0, Decision::Accepted)]),
+                    ),
+                    ("new/b.ts", decided(Decision::Accepted, &[])),
+                    ("gone.ts", decided(Decision::Accepted, &[])),
+                ],
+                &["gone.ts", "kept.ts"],
+            ),
+        );
+        let project = root.join("project");
+
+        let mut applied = apply_staged(&root.join("run")).unwrap();
+        applied.sort();
+
+        assert_eq!(applied, vec!["a.ts", "gone.ts", "new/b.ts"]);
+        assert_eq!(
+            fs::read_to_string(project.join("a.ts")).unwrap(),
+            "A\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n"
+        );
+        assert!(!project.join("gone.ts").exists());
+        assert!(project.join("kept.ts").exists());
+        assert!(!project.join("skipped").exists());
+        assert!(!root.join("run").exists());
+    }
+
+    #[test]
+    fn refuses_files_changed_since_review() {
+        let reviewed = FileDecision {
+            base_hash: Some(digest(Some(OLD))),
+            ..decided(Decision::Accepted, &[])
+        };
+        let root = staging(
+            &[("a.ts", "edited meanwhile\n")],
+            &[("a.ts", NEW)],
+            manifest(vec![("a.ts", reviewed)], &[]),
+        );
+
+        let err = apply_staged(&root.join("run")).unwrap_err();
+        assert!(err.contains("review it again"));
+        assert_eq!(
+            fs::read_to_string(root.join("project/a.ts")).unwrap(),
+            "edited meanwhile\n"
+        );
+        assert!(root.join("run").join(MANIFEST_FILE).exists());
+    }
+
+    #[test]
+    fn a_failed_swap_leaves_the_project_untouched() {
+        let root = staging(
+            &[("a.ts", OLD)],
+            &[("a.ts", NEW), ("b.ts/inner.ts", "x\n")],
+            manifest(
+                vec![
+                    ("a.ts", decided(Decision::Accepted, &[])),
+                    ("b.ts/inner.ts", decided(Decision::Accepted, &[])),
+                ],
+                &[],
+            ),
+        );
+        let project = root.join("project");
+        // a file where a directory needs to go makes the second write fail
+        fs::write(project.join("b.ts"), "blocker\n").unwrap();
+
+        assert!(apply_staged(&root.join("run")).is_err());
+        assert_eq!(fs::read_to_string(project.join("a.ts")).unwrap(), OLD);
+        assert!(!project.join(".a.ts.agent-staged").exists());
+    }
+}
@@ -0,0 +1,457 @@
+use super::conversation::project_data_dir;
+use super::AgentRun;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_store::StoreExt;
+
+const USAGE_DIR: &str = "usage";
+const LEDGER_FILE: &str = "ledger.jsonl";
+const STORE_FILE: &str = "agent-budgets.json";
+// store key for the price table; the other keys are project paths
+const PRICES_KEY: &str = "model_prices";
+
+/// Tokens and time spent on a request, as reported with `Done`. The backend
+/// may leave any field out.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Usage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub model: Option<String>,
+    pub duration_ms: u64,
+    // what the provider charged, in USD, when it says
+    pub cost: Option<f64>,
+}
+
+impl Usage {
+    fn add(&mut self, other: &Usage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        if other.model.is_some() {
+            self.model = other.model.clone();
+        }
+        self.cost = match (self.cost, other.cost) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(0.0) + b.unwrap_or(0.0)),
+        };
+    }
+}
+
+/// Usage of every request a run makes: the first one, reconnects, repairs.
+#[derive(Debug)]
+pub(super) struct RunUsage {
+    started: Instant,
+    total: Mutex<Usage>,
+}
+
+impl RunUsage {
+    pub(super) fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            total: Mutex::new(Usage::default()),
+        }
+    }
+
+    /// The model requests go to unless the provider reports another.
+    pub(super) fn set_model(&self, model: Option<String>) {
+        if let Ok(mut total) = self.total.lock() {
+            total.model = model;
+        }
+    }
+
+    pub(super) fn add(&self, usage: &Usage) {
+        if let Ok(mut total) = self.total.lock() {
+            total.add(usage);
+        }
+    }
+
+    /// Totals so far, timed from the start of the run.
+    pub(super) fn total(&self) -> Usage {
+        let mut total = self.total.lock().map(|t| t.clone()).unwrap_or_default();
+        total.duration_ms = self.started.elapsed().as_millis() as u64;
+        total
+    }
+}
+
+/// One finished run in the ledger.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageEntry {
+    pub run_id: String,
+    pub project_path: String,
+    pub action: String,
+    pub outcome: String,
+    pub model: Option<String>,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub duration_ms: u64,
+    // `None` when neither the provider nor the price table gave one
+    pub cost: Option<f64>,
+    pub timestamp: String,
+}
+
+/// USD per million tokens.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetPeriod {
+    #[default]
+    Day,
+    Month,
+}
+
+/// Spending limits for a project. Past a soft limit a run only starts when the
+/// caller insists; past a hard limit it does not start at all.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Budget {
+    pub period: BudgetPeriod,
+    pub soft_tokens: Option<u64>,
+    pub hard_tokens: Option<u64>,
+    pub soft_cost: Option<f64>,
+    pub hard_cost: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageTotals {
+    pub runs: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub duration_ms: u64,
+    pub cost: f64,
+    // runs whose cost is unknown, so `cost` understates the spend
+    pub unpriced_runs: u64,
+}
+
+impl UsageTotals {
+    fn tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    fn add(&mut self, entry: &UsageEntry) {
+        self.runs += 1;
+        self.prompt_tokens += entry.prompt_tokens;
+        self.completion_tokens += entry.completion_tokens;
+        self.duration_ms += entry.duration_ms;
+        match entry.cost {
+            Some(cost) => self.cost += cost,
+            None => self.unpriced_runs += 1,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct UsageSummary {
+    pub total: UsageTotals,
+    pub by_project: BTreeMap<String, UsageTotals>,
+    // keyed by UTC date, YYYY-MM-DD
+    pub by_day: BTreeMap<String, UsageTotals>,
+    pub by_model: BTreeMap<String, UsageTotals>,
+}
+
+fn usage_root<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(USAGE_DIR))
+        .map_err(|e| format!("Failed to resolve app data dir: {}", e))
+}
+
+fn read_ledger(file: &Path) -> Vec<UsageEntry> {
+    // a torn last line from a crash should not hide the rest
+    fs::read_to_string(file)
+        .map(|raw| {
+            raw.lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn project_entries<R: Runtime>(
+    app: &AppHandle<R>,
+    project_path: &str,
+) -> Result<Vec<UsageEntry>, String> {
+    let dir = project_data_dir(app, USAGE_DIR, project_path)?;
+    Ok(read_ledger(&dir.join(LEDGER_FILE)))
+}
+
+fn all_entries<R: Runtime>(app: &AppHandle<R>) -> Result<Vec<UsageEntry>, String> {
+    let root = usage_root(app)?;
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+
+    Ok(fs::read_dir(&root)
+        .map_err(|e| format!("Failed to read usage ledger: {}", e))?
+        .filter_map(|e| e.ok())
+        .flat_map(|e| read_ledger(&e.path().join(LEDGER_FILE)))
+        .collect())
+}
+
+fn load_prices<R: Runtime>(app: &AppHandle<R>) -> Result<HashMap<String, ModelPrice>, String> {
+    let store = app
+        .store(STORE_FILE)
+        .map_err(|e| format!("Store error: {}", e))?;
+
+    match store.get(PRICES_KEY) {
+        Some(value) => {
+            serde_json::from_value(value).map_err(|e| format!("Corrupt model prices: {}", e))
+        }
+        None => Ok(HashMap::new()),
+    }
+}
+
+fn budget_key(project_path: &str) -> Result<String, String> {
+    PathBuf::from(project_path)
+        .canonicalize()
+        .map(|p| p.to_string_lossy().to_string())
+        .map_err(|e| format!("Invalid project path '{}': {}", project_path, e))
+}
+
+fn load_budget<R: Runtime>(
+    app: &AppHandle<R>,
+    project_path: &str,
+) -> Result<Option<Budget>, String> {
+    let store = app
+        .store(STORE_FILE)
+        .map_err(|e| format!("Store error: {}", e))?;
+
+    store
+        .get(budget_key(project_path)?)
+        .map(|value| {
+            serde_json::from_value(value)
+                .map_err(|e| format!("Corrupt budget for {}: {}", project_path, e))
+        })
+        .transpose()
+}
+
+fn period_key(timestamp: &str, period: BudgetPeriod) -> &str {
+    let len = match period {
+        BudgetPeriod::Day => 10,  // YYYY-MM-DD
+        BudgetPeriod::Month => 7, // YYYY-MM
+    };
+    timestamp.get(..len).unwrap_or(timestamp)
+}
+
+/// Refuses to start a run once the project's budget for the current period
+/// is spent. `over_soft_limit` lets a run through a soft limit.
+pub(super) fn check_budget<R: Runtime>(
+    app: &AppHandle<R>,
+    project_path: &str,
+    over_soft_limit: bool,
+) -> Result<(), String> {
+    let Some(budget) = load_budget(app, project_path)? else {
+        return Ok(());
+    };
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let current = period_key(&now, budget.period);
+    let mut spent = UsageTotals::default();
+    for entry in project_entries(app, project_path)? {
+        if period_key(&entry.timestamp, budget.period) == current {
+            spent.add(&entry);
+        }
+    }
+
+    let period = match budget.period {
+        BudgetPeriod::Day => "today",
+        BudgetPeriod::Month => "this month",
+    };
+    let tokens = spent.tokens();
+
+    if let Some(limit) = budget.hard_tokens.filter(|l| tokens >= *l) {
+        return Err(format!(
+            "Token budget reached: {} of {} tokens used {}",
+            tokens, limit, period
+        ));
+    }
+    if let Some(limit) = budget.hard_cost.filter(|l| spent.cost >= *l) {
+        return Err(format!(
+            "Spending budget reached: ${:.2} of ${:.2} spent {}",
+            spent.cost, limit, period
+        ));
+    }
+    if over_soft_limit {
+        return Ok(());
+    }
+    if let Some(limit) = budget.soft_tokens.filter(|l| tokens >= *l) {
+        return Err(format!(
+            "Soft token budget reached: {} of {} tokens used {}. Start the run anyway to continue.",
+            tokens, limit, period
+        ));
+    }
+    if let Some(limit) = budget.soft_cost.filter(|l| spent.cost >= *l) {
+        return Err(format!(
+            "Soft spending budget reached: ${:.2} of ${:.2} spent {}. Start the run anyway to continue.",
+            spent.cost, limit, period
+        ));
+    }
+
+    Ok(())
+}
+
+/// Appends the finished run to the project's ledger.
+pub(super) fn record<R: Runtime>(
+    run: &AgentRun<'_, R>,
+    action: &str,
+    outcome: &str,
+) -> Result<(), String> {
+    let usage = run.usage.total();
+    let cost = usage.cost.or_else(|| {
+        let prices = load_prices(run.app).ok()?;
+        let price = prices.get(usage.model.as_deref()?)?;
+        Some(
+            (usage.prompt_tokens as f64 * price.input
+                + usage.completion_tokens as f64 * price.output)
+                / 1_000_000.0,
+        )
+    });
+
+    let entry = UsageEntry {
+        run_id: run.run_id.to_string(),
+        project_path: run.base_path.to_string_lossy().to_string(),
+        action: action.to_string(),
+        outcome: outcome.to_string(),
+        model: usage.model,
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        duration_ms: usage.duration_ms,
+        cost,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    };
+
+    let dir = project_data_dir(run.app, USAGE_DIR, run.project_path)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create usage dir: {}", e))?;
+    let mut line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+    line.push('\n');
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(LEDGER_FILE))
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|e| format!("Failed to record usage: {}", e))
+}
+
+// Commands
+/// Spend per project, per day and per model, for one project or all of them.
+/// `since`/`until` are inclusive `YYYY-MM-DD` dates.
+#[tauri::command]
+pub async fn usage_summary<R: Runtime>(
+    app: AppHandle<R>,
+    project_path: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+) -> Result<UsageSummary, String> {
+    let entries = match &project_path {
+        Some(project_path) => project_entries(&app, project_path)?,
+        None => all_entries(&app)?,
+    };
+
+    let mut summary = UsageSummary::default();
+    for entry in &entries {
+        let day = period_key(&entry.timestamp, BudgetPeriod::Day);
+        if since.as_deref().is_some_and(|s| day < s) || until.as_deref().is_some_and(|u| day > u) {
+            continue;
+        }
+
+        summary.total.add(entry);
+        summary
+            .by_project
+            .entry(entry.project_path.clone())
+            .or_default()
+            .add(entry);
+        summary
+            .by_day
+            .entry(day.to_string())
+            .or_default()
+            .add(entry);
+        summary
+            .by_model
+            .entry(entry.model.clone().unwrap_or_else(|| "unknown".into()))
+            .or_default()
+            .add(entry);
+    }
+
+    Ok(summary)
+}
+
+#[tauri::command]
+pub async fn list_usage<R: Runtime>(
+    app: AppHandle<R>,
+    project_path: String,
+) -> Result<Vec<UsageEntry>, String> {
+    let mut entries = project_entries(&app, &project_path)?;
+    // newest first
+    entries.reverse();
+    Ok(entries)
+}
+
+#[tauri::command]
+pub async fn get_usage_budget<R: Runtime>(
+    app: AppHandle<R>,
+    project_path: String,
+) -> Result<Option<Budget>, String> {
+    load_budget(&app, &project_path)
+}
+
+/// Saves the project's budget; `None` removes it.
+#[tauri::command]
+pub async fn set_usage_budget<R: Runtime>(
+    app: AppHandle<R>,
+    project_path: String,
+    budget: Option<Budget>,
+) -> Result<(), String> {
+    let store = app
+        .store(STORE_FILE)
+        .map_err(|e| format!("Store error: {}", e))?;
+
+    let key = budget_key(&project_path)?;
+    match budget {
+        Some(budget) => store.set(
+            key,
+            serde_json::to_value(&budget).map_err(|e| e.to_string())?,
+        ),
+        None => {
+            store.delete(key);
+        }
+    }
+    store
+        .save()
+        .map_err(|e| format!("Store save failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_model_prices<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<HashMap<String, ModelPrice>, String> {
+    load_prices(&app)
+}
+
+#[tauri::command]
+pub async fn set_model_prices<R: Runtime>(
+    app: AppHandle<R>,
+    prices: HashMap<String, ModelPrice>,
+) -> Result<(), String> {
+    let store = app
+        .store(STORE_FILE)
+        .map_err(|e| format!("Store error: {}", e))?;
+
+    store.set(
+        PRICES_KEY,
+        serde_json::to_value(&prices).map_err(|e| e.to_string())?,
+    );
+    store
+        .save()
+        .map_err(|e| format!("Store save failed: {}", e))
+}
@@ -0,0 +1,323 @@
+use super::{
+    context, conversation::HistoryTurn, models::Generation, normalize_rel, read_for_run,
+    AgentEvent, AgentRequest, AgentRun, Backend, EditRequest, RunOptions,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::Ordering;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::Runtime;
+use tokio::process::Command;
+
+const DEFAULT_REPAIR_ROUNDS: u32 = 3;
+const MAX_REPAIR_ROUNDS: u32 = 10;
+// files sent back to the agent per round, worst first
+const MAX_REPAIR_FILES: usize = 5;
+const MAX_DIAGNOSTICS_PER_FILE: usize = 20;
+const CHECK_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// One error reported by the check command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckDiagnostic {
+    // project-relative; `None` for errors not tied to a file
+    pub path: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub code: Option<String>,
+    pub message: String,
+}
+
+struct CheckCommand {
+    program: String,
+    args: Vec<String>,
+}
+
+impl CheckCommand {
+    fn label(&self) -> String {
+        std::iter::once(self.program.as_str())
+            .chain(self.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// The configured check command, else the project's own `tsc` when it has one.
+fn check_command(base: &Path, configured: Option<&str>) -> Option<CheckCommand> {
+    if let Some(configured) = configured.filter(|c| !c.trim().is_empty()) {
+        let mut parts = configured.split_whitespace().map(String::from);
+        return Some(CheckCommand {
+            program: parts.next()?,
+            args: parts.collect(),
+        });
+    }
+
+    let tsc = if cfg!(windows) { "tsc.cmd" } else { "tsc" };
+    let tsc = base.join("node_modules").join(".bin").join(tsc);
+    if !tsc.is_file() || !base.join("tsconfig.json").is_file() {
+        return None;
+    }
+
+    Some(CheckCommand {
+        program: tsc.to_string_lossy().to_string(),
+        args: vec!["--noEmit".into(), "--pretty".into(), "false".into()],
+    })
+}
+
+fn tsc_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^(?:(.+?)\((\d+),(\d+)\): )?error (TS\d+): (.*)$").expect("valid tsc regex")
+    })
+}
+
+/// Parses `tsc --pretty false` output. Indented lines continue the previous message.
+fn parse_diagnostics(base: &Path, output: &str) -> Vec<CheckDiagnostic> {
+    let mut diagnostics: Vec<CheckDiagnostic> = Vec::new();
+
+    for line in output.lines() {
+        if let Some(caps) = tsc_regex().captures(line) {
+            let path = caps.get(1).and_then(|m| {
+                let file = Path::new(m.as_str());
+                let rel = file.strip_prefix(base).unwrap_or(file);
+                normalize_rel(&rel.to_string_lossy()).ok()
+            });
+            diagnostics.push(CheckDiagnostic {
+                path,
+                line: caps.get(2).and_then(|m| m.as_str().parse().ok()),
+                column: caps.get(3).and_then(|m| m.as_str().parse().ok()),
+                code: Some(caps[4].to_string()),
+                message: caps[5].to_string(),
+            });
+        } else if line.starts_with(char::is_whitespace) && !line.trim().is_empty() {
+            if let Some(last) = diagnostics.last_mut() {
+                last.message.push('\n');
+                last.message.push_str(line.trim());
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Runs the check, returning no diagnostics when it passes.
+async fn run_check(base: &Path, command: &CheckCommand) -> Result<Vec<CheckDiagnostic>, String> {
+    let child = Command::new(&command.program)
+        .args(&command.args)
+        .current_dir(base)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+
+    let output = tokio::time::timeout(CHECK_TIMEOUT, child)
+        .await
+        .map_err(|_| format!("`{}` timed out", command.label()))?
+        .map_err(|e| format!("Failed to run `{}`: {}", command.label(), e))?;
+
+    if output.status.success() {
+        return Ok(Vec::new());
+    }
+
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let diagnostics = parse_diagnostics(base, &text);
+    if !diagnostics.is_empty() {
+        return Ok(diagnostics);
+    }
+
+    // failed without anything we could parse; surface the tail of the output
+    let tail: Vec<&str> = text.lines().rev().take(20).collect();
+    Ok(vec![CheckDiagnostic {
+        path: None,
+        line: None,
+        column: None,
+        code: None,
+        message: tail.into_iter().rev().collect::<Vec<_>>().join("\n"),
+    }])
+}
+
+fn repair_prompt(path: &str, diagnostics: &[&CheckDiagnostic]) -> String {
+    let mut prompt = format!(
+        "The type check reported these errors in {}. Fix them without changing unrelated code.\n",
+        path
+    );
+    for d in diagnostics.iter().take(MAX_DIAGNOSTICS_PER_FILE) {
+        prompt.push_str(&format!(
+            "- line {}, column {}: {} {}\n",
+            d.line.unwrap_or(0),
+            d.column.unwrap_or(0),
+            d.code.as_deref().unwrap_or(""),
+            d.message
+        ));
+    }
+    prompt
+}
+
+/// Type-checks the project after a finished run and sends the errors back to
+/// `/edit`, one request per file, until the check passes or the rounds run out.
+/// Every outcome ends with `AgentEvent::ValidationFinished`.
+pub(super) async fn repair<R: Runtime>(
+    run: &AgentRun<'_, R>,
+    backend: &Backend<'_>,
+    options: &RunOptions,
+    history: &[HistoryTurn],
+) -> Result<(), String> {
+    let finish = |passed: bool, rounds: u32, remaining: usize, reason: Option<&str>| {
+        run.emit(&AgentEvent::ValidationFinished {
+            passed,
+            rounds,
+            remaining,
+            reason: reason.map(String::from),
+        });
+    };
+
+    let Some(AgentEvent::Done {
+        summary,
+        files: mut all_files,
+        ..
+    }) = run.outcome()
+    else {
+        finish(false, 0, 0, Some("The run did not finish"));
+        return Ok(());
+    };
+    if run.staging.is_some() {
+        finish(false, 0, 0, Some("Staged changes are not type-checked"));
+        return Ok(());
+    }
+    let Some(command) = check_command(&run.base_path, options.check_command.as_deref()) else {
+        finish(false, 0, 0, Some("No local TypeScript compiler found"));
+        return Ok(());
+    };
+
+    let generation = Generation::resolve(&backend.settings, options)?;
+    let max_rounds = options
+        .max_repair_rounds
+        .unwrap_or(DEFAULT_REPAIR_ROUNDS)
+        .min(MAX_REPAIR_ROUNDS);
+    let mut round = 0;
+    // repairs fix whatever the check reports, inside the selection or not
+    run.end_selection();
+    // the run already emitted its `done`; repairs report `repair_finished`
+    run.repairing.store(true, Ordering::Relaxed);
+    // set when a repair request did not end in `Done`; its outcome then stands
+    let mut failed = false;
+
+    let result = 'rounds: loop {
+        run.emit(&AgentEvent::CheckStarted {
+            round,
+            command: command.label(),
+        });
+        let diagnostics = match run_check(&run.base_path, &command).await {
+            Ok(diagnostics) => diagnostics,
+            Err(e) => break (false, 0, Some(e)),
+        };
+        run.emit(&AgentEvent::CheckFinished {
+            round,
+            diagnostics: diagnostics.clone(),
+        });
+
+        if diagnostics.is_empty() {
+            break (true, 0, None);
+        }
+        if round == max_rounds {
+            break (false, diagnostics.len(), None);
+        }
+
+        let mut by_file: BTreeMap<&str, Vec<&CheckDiagnostic>> = BTreeMap::new();
+        for d in &diagnostics {
+            if let Some(path) = &d.path {
+                by_file.entry(path).or_default().push(d);
+            }
+        }
+        let mut targets: Vec<(&str, Vec<&CheckDiagnostic>)> = by_file.into_iter().collect();
+        targets.sort_by_key(|(_, d)| std::cmp::Reverse(d.len()));
+        targets.truncate(MAX_REPAIR_FILES);
+
+        if targets.is_empty() {
+            break (false, diagnostics.len(), None);
+        }
+
+        round += 1;
+        run.emit(&AgentEvent::RepairStarted {
+            round,
+            max_rounds,
+            files: targets.iter().map(|(p, _)| p.to_string()).collect(),
+        });
+
+        for (path, file_diagnostics) in targets {
+            let Some(content) = read_for_run(run, path).await? else {
+                continue;
+            };
+            let context = context::build(
+                &run.base_path,
+                path,
+                &content,
+                &options.pinned_files,
+                options
+                    .context_budget
+                    .unwrap_or(context::DEFAULT_CONTEXT_BUDGET),
+            )
+            .await?;
+
+            let request = AgentRequest::Edit(EditRequest {
+                project_path: run.project_path.to_string(),
+                relative_path: path.to_string(),
+                content,
+                user_prompt: repair_prompt(path, &file_diagnostics),
+                history: history.to_vec(),
+                context,
+                selection: None,
+                diagnostics: file_diagnostics.into_iter().cloned().collect(),
+                generation: generation.clone(),
+            });
+            // the repair's own terminal event decides how it went
+            run.set_outcome(None);
+            let sent = backend.run(run, &request).await;
+
+            match (sent, run.outcome()) {
+                (Ok(()), Some(AgentEvent::Done { files, .. })) => {
+                    for file in files {
+                        if !all_files.contains(&file) {
+                            all_files.push(file);
+                        }
+                    }
+                }
+                (sent, outcome) => {
+                    let reason = match &sent {
+                        Err(e) => format!("Repair of {} failed: {}", path, e),
+                        Ok(()) => format!("Repair of {} did not finish", path),
+                    };
+                    // a run that never got an outcome would not be recorded at all
+                    if sent.is_err() || outcome.is_none() {
+                        run.set_outcome(Some(AgentEvent::Error {
+                            message: reason.clone(),
+                        }));
+                    }
+                    failed = true;
+                    break 'rounds (false, diagnostics.len(), Some(reason));
+                }
+            }
+        }
+    };
+
+    run.repairing.store(false, Ordering::Relaxed);
+
+    // the conversation turn is the user's request; repairs are part of it
+    if !failed {
+        run.set_outcome(Some(AgentEvent::Done {
+            summary,
+            files: all_files,
+            usage: Some(run.usage.total()),
+        }));
+    }
+
+    let (passed, remaining, reason) = result;
+    finish(passed, round, remaining, reason.as_deref());
+    Ok(())
+}
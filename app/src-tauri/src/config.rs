@@ -5,7 +5,7 @@ use aes_gcm::{
 use base64::{engine::general_purpose::STANDARD as B64, Engine};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use tauri::AppHandle;
+use tauri::{AppHandle, Runtime};
 use tauri_plugin_store::StoreExt;
 
 const STORE_FILE: &str = "config.json";
@@ -19,6 +19,29 @@ pub struct AppSettings {
     pub aws_secret_access_key: String,
     pub aws_region: String,
     pub aws_bucket: String,
+    // credential for the agent backend, sent with every agent request
+    #[serde(default)]
+    pub api_token: String,
+    // header the token goes in; empty means `Authorization: Bearer <token>`
+    #[serde(default)]
+    pub api_auth_header: String,
+    // "backend" (or empty) for the FastAPI agent, "openai" for a direct
+    // OpenAI-compatible endpoint
+    #[serde(default)]
+    pub agent_provider: String,
+    #[serde(default)]
+    pub openai_base_url: String,
+    #[serde(default)]
+    pub openai_model: String,
+    #[serde(default)]
+    pub openai_api_key: String,
+    // generation defaults for agent runs; empty/`None` leaves them to the provider
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
 }
 
 fn enc_key() -> [u8; 32] {
@@ -64,7 +87,7 @@ fn decrypt(b64: &str) -> Result<String, String> {
     String::from_utf8(plain).map_err(|e| e.to_string())
 }
 
-fn get_str(store: &tauri_plugin_store::Store<tauri::Wry>, key: &str) -> String {
+fn get_str<R: Runtime>(store: &tauri_plugin_store::Store<R>, key: &str) -> String {
     store
         .get(key)
         .and_then(|v| v.as_str().map(str::to_string))
@@ -72,7 +95,10 @@ fn get_str(store: &tauri_plugin_store::Store<tauri::Wry>, key: &str) -> String {
 }
 
 #[tauri::command]
-pub async fn save_settings(app: AppHandle, settings: AppSettings) -> Result<(), String> {
+pub async fn save_settings<R: Runtime>(
+    app: AppHandle<R>,
+    settings: AppSettings,
+) -> Result<(), String> {
     let store = app
         .store(STORE_FILE)
         .map_err(|e| format!("Store error: {}", e))?;
@@ -82,6 +108,19 @@ pub async fn save_settings(app: AppHandle, settings: AppSettings) -> Result<(),
     store.set("ws_url", serde_json::json!(settings.ws_url));
     store.set("aws_region", serde_json::json!(settings.aws_region));
     store.set("aws_bucket", serde_json::json!(settings.aws_bucket));
+    store.set(
+        "api_auth_header",
+        serde_json::json!(settings.api_auth_header),
+    );
+    store.set("agent_provider", serde_json::json!(settings.agent_provider));
+    store.set(
+        "openai_base_url",
+        serde_json::json!(settings.openai_base_url),
+    );
+    store.set("openai_model", serde_json::json!(settings.openai_model));
+    store.set("model", serde_json::json!(settings.model));
+    store.set("temperature", serde_json::json!(settings.temperature));
+    store.set("max_tokens", serde_json::json!(settings.max_tokens));
 
     store.set(
         "aws_access_key_id",
@@ -91,6 +130,14 @@ pub async fn save_settings(app: AppHandle, settings: AppSettings) -> Result<(),
         "aws_secret_access_key",
         serde_json::json!(encrypt(&settings.aws_secret_access_key)?),
     );
+    store.set(
+        "api_token",
+        serde_json::json!(encrypt(&settings.api_token)?),
+    );
+    store.set(
+        "openai_api_key",
+        serde_json::json!(encrypt(&settings.openai_api_key)?),
+    );
 
     store.set("configured", serde_json::json!(true));
     store
@@ -100,7 +147,7 @@ pub async fn save_settings(app: AppHandle, settings: AppSettings) -> Result<(),
 }
 
 #[tauri::command]
-pub async fn load_settings(app: AppHandle) -> Result<AppSettings, String> {
+pub async fn load_settings<R: Runtime>(app: AppHandle<R>) -> Result<AppSettings, String> {
     let store = app
         .store(STORE_FILE)
         .map_err(|e| format!("Store error: {}", e))?;
@@ -113,11 +160,26 @@ pub async fn load_settings(app: AppHandle) -> Result<AppSettings, String> {
         aws_bucket: get_str(&store, "aws_bucket"),
         aws_access_key_id: decrypt(&get_str(&store, "aws_access_key_id"))?,
         aws_secret_access_key: decrypt(&get_str(&store, "aws_secret_access_key"))?,
+        api_token: decrypt(&get_str(&store, "api_token"))?,
+        api_auth_header: get_str(&store, "api_auth_header"),
+        agent_provider: get_str(&store, "agent_provider"),
+        openai_base_url: get_str(&store, "openai_base_url"),
+        openai_model: get_str(&store, "openai_model"),
+        openai_api_key: decrypt(&get_str(&store, "openai_api_key"))?,
+        model: get_str(&store, "model"),
+        temperature: store
+            .get("temperature")
+            .and_then(|v| v.as_f64())
+            .map(|t| t as f32),
+        max_tokens: store
+            .get("max_tokens")
+            .and_then(|v| v.as_u64())
+            .and_then(|n| u32::try_from(n).ok()),
     })
 }
 
 #[tauri::command]
-pub async fn has_settings(app: AppHandle) -> Result<bool, String> {
+pub async fn has_settings<R: Runtime>(app: AppHandle<R>) -> Result<bool, String> {
     let store = app
         .store(STORE_FILE)
         .map_err(|e| format!("Store error: {}", e))?;
@@ -129,7 +191,7 @@ pub async fn has_settings(app: AppHandle) -> Result<bool, String> {
 }
 
 #[tauri::command]
-pub async fn clear_settings(app: AppHandle) -> Result<(), String> {
+pub async fn clear_settings<R: Runtime>(app: AppHandle<R>) -> Result<(), String> {
     let store = app
         .store(STORE_FILE)
         .map_err(|e| format!("Store error: {}", e))?;
@@ -1,6 +1,6 @@
-mod agent;
+pub mod agent;
 mod aws;
-mod config;
+pub mod config;
 mod project;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -15,6 +15,10 @@ pub fn run() {
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .manage(client)
+        .manage(agent::AgentRuns::default())
+        .manage(agent::handshake::BackendCache::default())
+        .manage(project::ProjectWatchers::default())
+        .manage(project::search::Searches::default())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_dialog::init())
@@ -30,6 +34,13 @@ pub fn run() {
             project::delete_project,
             project::list_files,
             project::get_file_tree,
+            project::list_directory,
+            project::watch_project,
+            project::unwatch_project,
+            project::search::search_project,
+            project::search::cancel_search,
+            project::replace::replace_in_project,
+            project::replace::undo_replace,
             project::open_in_terminal,
             project::open_in_finder,
             // config
@@ -43,6 +54,32 @@ pub fn run() {
             // agent
             agent::scaffold_project,
             agent::edit_project_file,
+            agent::cancel_agent_run,
+            agent::undo_agent_run,
+            agent::staging::list_staged_files,
+            agent::staging::accept_staged_change,
+            agent::staging::reject_staged_change,
+            agent::staging::apply_staged_changes,
+            agent::staging::discard_staged_changes,
+            agent::conversation::list_conversations,
+            agent::conversation::get_conversation,
+            agent::conversation::continue_conversation,
+            agent::conversation::fork_conversation,
+            agent::conversation::clear_conversation,
+            agent::policy::get_write_policy,
+            agent::policy::set_write_policy,
+            agent::policy::reset_write_policy,
+            agent::handshake::check_backend,
+            agent::models::list_models,
+            agent::usage::usage_summary,
+            agent::usage::list_usage,
+            agent::usage::get_usage_budget,
+            agent::usage::set_usage_budget,
+            agent::usage::get_model_prices,
+            agent::usage::set_model_prices,
+            agent::recording::list_agent_runs,
+            agent::recording::get_agent_run_log,
+            agent::recording::replay_agent_run,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,8 +1,19 @@
+mod ignores;
+pub mod replace;
+pub mod search;
+
+use ignores::{IgnoreRules, WatchedRules};
+use notify_debouncer_full::notify::event::{ModifyKind, RenameMode};
+use notify_debouncer_full::notify::{EventKind, RecommendedWatcher, RecursiveMode};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, RecommendedCache};
 use serde::Serialize;
+use std::collections::HashMap;
 use std::fs;
 use std::io::Read;
 use std::path::{Path, PathBuf};
-use tauri::{AppHandle, Manager};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
 
 #[derive(Debug, Clone, Serialize)]
 pub struct FileNode {
@@ -14,33 +25,91 @@ pub struct FileNode {
     pub lang: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub children: Option<Vec<FileNode>>,
+    // set by `list_directory`, which leaves `children` unloaded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub child_count: Option<usize>,
+    // only present when ignored entries are shown
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub ignored: bool,
+}
+
+/// A change on disk, relative to the project root.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FsChange {
+    Create { path: String, is_dir: bool },
+    Modify { path: String },
+    Delete { path: String },
+    Rename { from: String, to: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FsEvent {
+    pub project_path: String,
+    pub changes: Vec<FsChange>,
+}
+
+// OS events arriving within this window are emitted as one batch
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
+
+type ProjectWatcher = Debouncer<RecommendedWatcher, RecommendedCache>;
+
+/// Active watchers keyed by canonical project path. Dropping one stops it.
+#[derive(Default)]
+pub struct ProjectWatchers(Mutex<HashMap<PathBuf, ProjectWatcher>>);
+
+/// One page of a directory listing.
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectoryPage {
+    pub entries: Vec<FileNode>,
+    // entries in the whole directory, after filtering
+    pub total: usize,
+    pub next_offset: Option<usize>,
 }
 
-const IGNORED_DIRS: &[&str] = &["node_modules", ".vscode", ".expo", ".git", ".DS_Store"];
+const DEFAULT_PAGE_SIZE: usize = 200;
+const MAX_PAGE_SIZE: usize = 5000;
+
+pub(crate) const IGNORED_DIRS: &[&str] = &["node_modules", ".vscode", ".expo", ".git", ".DS_Store"];
+
+pub(crate) fn resolve_safe(project_path: &str, relative: &str) -> Result<PathBuf, String> {
+    let resolved = resolve_within(project_path, relative)?;
 
-fn resolve_safe(project_path: &str, relative: &str) -> Result<PathBuf, String> {
+    if let Some(parent) = resolved.parent() {
+        if !parent.exists() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directories: {}", e))?;
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Like `resolve_safe`, but creates nothing: a path that does not exist yet is
+/// checked through its nearest existing ancestor.
+pub(crate) fn resolve_within(project_path: &str, relative: &str) -> Result<PathBuf, String> {
     let base = PathBuf::from(project_path)
         .canonicalize()
         .map_err(|e| format!("Invalid project path '{}': {}", project_path, e))?;
     let target = base.join(relative);
 
-    let resolved = if target.exists() {
-        target
-            .canonicalize()
-            .map_err(|e| format!("Failed to resolve '{}': {}", relative, e))?
-    } else {
-        let parent = target
+    let mut existing = target.as_path();
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        missing.push(
+            existing
+                .file_name()
+                .ok_or_else(|| "Invalid file path".to_string())?,
+        );
+        existing = existing
             .parent()
             .ok_or_else(|| "Invalid file path".to_string())?;
-        if !parent.exists() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create directories: {}", e))?;
-        }
-        let parent_canon = parent
-            .canonicalize()
-            .map_err(|e| format!("Failed to resolve parent: {}", e))?;
-        parent_canon.join(target.file_name().unwrap())
-    };
+    }
+
+    let mut resolved = existing
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve '{}': {}", relative, e))?;
+    resolved.extend(missing.iter().rev());
 
     if !resolved.starts_with(&base) {
         return Err("Path traversal detected — access denied".to_string());
@@ -82,53 +151,154 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
-fn build_tree(base: &Path, dir: &Path) -> std::io::Result<Vec<FileNode>> {
-    let mut nodes: Vec<FileNode> = Vec::new();
+/// The tree under `dir` with ignored entries left out.
+pub(crate) fn build_tree(base: &Path, dir: &Path) -> std::io::Result<Vec<FileNode>> {
+    let (rules, ignored) = IgnoreRules::for_dir(base, dir);
+    walk_tree(base, dir, &rules, false, ignored)
+}
 
+struct Listed {
+    entry: fs::DirEntry,
+    is_dir: bool,
+    ignored: bool,
+}
+
+/// `dir`'s entries as the tree shows them: no `IGNORED_DIRS`, ignored entries
+/// only with `show_ignored`, folders first, then by name.
+fn list_entries(
+    dir: &Path,
+    rules: &IgnoreRules,
+    show_ignored: bool,
+    parent_ignored: bool,
+) -> std::io::Result<Vec<Listed>> {
     let mut entries: Vec<_> = fs::read_dir(dir)?.filter_map(|e| e.ok()).collect();
     entries.sort_by_key(|e| {
         let is_file = e.file_type().map(|ft| ft.is_file()).unwrap_or(true);
         (is_file, e.file_name())
     });
 
+    let mut listed = Vec::new();
     for entry in entries {
-        let name = entry.file_name().to_string_lossy().to_string();
+        if IGNORED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()) {
+            continue;
+        }
 
-        if IGNORED_DIRS.contains(&name.as_str()) {
+        let is_dir = entry.file_type()?.is_dir();
+        let ignored = parent_ignored || rules.is_ignored(&entry.path(), is_dir);
+        if ignored && !show_ignored {
             continue;
         }
 
-        let path = entry.path();
-        let relative = path
-            .strip_prefix(base)
-            .unwrap_or(&path)
-            .to_string_lossy()
-            .to_string();
+        listed.push(Listed {
+            entry,
+            is_dir,
+            ignored,
+        });
+    }
+    Ok(listed)
+}
 
-        if entry.file_type()?.is_dir() {
-            let children = build_tree(base, &path)?;
-            nodes.push(FileNode {
-                name,
-                path: relative,
-                node_type: "folder".to_string(),
-                lang: None,
-                children: Some(children),
-            });
-        } else {
-            nodes.push(FileNode {
-                name: name.clone(),
-                path: relative,
-                node_type: "file".to_string(),
-                lang: get_lang(&name),
-                children: None,
-            });
+fn to_node(base: &Path, listed: &Listed) -> FileNode {
+    let name = listed.entry.file_name().to_string_lossy().to_string();
+    let path = listed.entry.path();
+    let relative = path
+        .strip_prefix(base)
+        .unwrap_or(&path)
+        .to_string_lossy()
+        .to_string();
+
+    FileNode {
+        lang: if listed.is_dir { None } else { get_lang(&name) },
+        name,
+        path: relative,
+        node_type: if listed.is_dir { "folder" } else { "file" }.to_string(),
+        children: None,
+        child_count: None,
+        ignored: listed.ignored,
+    }
+}
+
+/// With `show_ignored`, entries matched by the ignore rules are kept and
+/// flagged instead of dropped. `IGNORED_DIRS` are always dropped.
+fn walk_tree(
+    base: &Path,
+    dir: &Path,
+    rules: &IgnoreRules,
+    show_ignored: bool,
+    parent_ignored: bool,
+) -> std::io::Result<Vec<FileNode>> {
+    let mut nodes: Vec<FileNode> = Vec::new();
+
+    for listed in list_entries(dir, rules, show_ignored, parent_ignored)? {
+        let mut node = to_node(base, &listed);
+        if listed.is_dir {
+            let path = listed.entry.path();
+            node.children = Some(walk_tree(
+                base,
+                &path,
+                &rules.enter(&path),
+                show_ignored,
+                listed.ignored,
+            )?);
         }
+        nodes.push(node);
     }
 
     Ok(nodes)
 }
 
-fn collect_files(base: &Path, dir: &Path, out: &mut Vec<String>) -> std::io::Result<()> {
+/// One page of `dir`'s direct entries, each folder with its own entry count.
+fn list_page(
+    base: &Path,
+    dir: &Path,
+    offset: usize,
+    limit: usize,
+    show_ignored: bool,
+) -> std::io::Result<DirectoryPage> {
+    let (rules, dir_ignored) = IgnoreRules::for_dir(base, dir);
+    let listed = list_entries(dir, &rules, show_ignored, dir_ignored)?;
+    let total = listed.len();
+
+    let entries: Vec<FileNode> = listed
+        .iter()
+        .skip(offset)
+        .take(limit)
+        .map(|listed| {
+            let mut node = to_node(base, listed);
+            if listed.is_dir {
+                let path = listed.entry.path();
+                // an unreadable folder shows as empty rather than failing the page
+                node.child_count = Some(
+                    list_entries(&path, &rules.enter(&path), show_ignored, listed.ignored)
+                        .map(|children| children.len())
+                        .unwrap_or(0),
+                );
+            }
+            node
+        })
+        .collect();
+
+    let end = offset.saturating_add(entries.len());
+    Ok(DirectoryPage {
+        entries,
+        total,
+        next_offset: (end < total).then_some(end),
+    })
+}
+
+/// Sniffs the first 512 bytes; anything that isn't UTF-8 is treated as binary.
+fn looks_binary(file: &mut fs::File) -> std::io::Result<bool> {
+    let mut sniff = [0u8; 512];
+    let n = file.read(&mut sniff)?;
+    Ok(std::str::from_utf8(&sniff[..n]).is_err())
+}
+
+fn collect_files(
+    base: &Path,
+    dir: &Path,
+    rules: &IgnoreRules,
+    out: &mut Vec<String>,
+) -> std::io::Result<()> {
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
         let name = entry.file_name().to_string_lossy().to_string();
@@ -138,9 +308,13 @@ fn collect_files(base: &Path, dir: &Path, out: &mut Vec<String>) -> std::io::Res
         }
 
         let path = entry.path();
+        let is_dir = path.is_dir();
+        if rules.is_ignored(&path, is_dir) {
+            continue;
+        }
 
-        if path.is_dir() {
-            collect_files(base, &path, out)?;
+        if is_dir {
+            collect_files(base, &path, &rules.enter(&path), out)?;
         } else {
             let relative = path.strip_prefix(base).unwrap();
             out.push(relative.to_string_lossy().to_string());
@@ -149,10 +323,95 @@ fn collect_files(base: &Path, dir: &Path, out: &mut Vec<String>) -> std::io::Res
     Ok(())
 }
 
+/// The project-relative path, or `None` when it falls under an ignored
+/// directory or the ignore rules leave it out.
+fn watched_relative(base: &Path, rules: &WatchedRules, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(base).ok()?;
+    if relative.as_os_str().is_empty()
+        || relative
+            .components()
+            .any(|c| IGNORED_DIRS.contains(&c.as_os_str().to_string_lossy().as_ref()))
+        || rules.is_ignored(path)
+    {
+        return None;
+    }
+    Some(relative.to_string_lossy().to_string())
+}
+
+fn to_changes(
+    base: &Path,
+    rules: &mut WatchedRules,
+    events: Vec<notify_debouncer_full::DebouncedEvent>,
+) -> Vec<FsChange> {
+    // a changed ignore file applies to the whole batch
+    rules.refresh(events.iter().flat_map(|e| &e.paths));
+    let rules = &*rules;
+    let mut changes: Vec<FsChange> = Vec::new();
+
+    for event in events {
+        let paths = &event.paths;
+        let created = |path: &Path| {
+            watched_relative(base, rules, path).map(|rel| FsChange::Create {
+                path: rel,
+                is_dir: path.is_dir(),
+            })
+        };
+        let deleted = |path: &Path| {
+            watched_relative(base, rules, path).map(|rel| FsChange::Delete { path: rel })
+        };
+
+        let change = match event.kind {
+            EventKind::Create(_) => paths.first().and_then(|p| created(p)),
+            EventKind::Remove(_) => paths.first().and_then(|p| deleted(p)),
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if paths.len() == 2 => {
+                // moving into or out of an ignored dir reads as a delete or create
+                match (
+                    watched_relative(base, rules, &paths[0]),
+                    watched_relative(base, rules, &paths[1]),
+                ) {
+                    (Some(from), Some(to)) => Some(FsChange::Rename { from, to }),
+                    (Some(from), None) => Some(FsChange::Delete { path: from }),
+                    (None, Some(_)) => created(&paths[1]),
+                    (None, None) => None,
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                paths.first().and_then(|p| deleted(p))
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                paths.first().and_then(|p| created(p))
+            }
+            // one half of a rename the OS could not pair up
+            EventKind::Modify(ModifyKind::Name(_)) => {
+                paths
+                    .first()
+                    .and_then(|p| if p.exists() { created(p) } else { deleted(p) })
+            }
+            // permission and timestamp changes don't affect content
+            EventKind::Modify(ModifyKind::Metadata(_)) => None,
+            // a directory's mtime changes with its children, which report themselves
+            EventKind::Modify(_) => paths
+                .first()
+                .filter(|p| !p.is_dir())
+                .and_then(|p| watched_relative(base, rules, p))
+                .map(|rel| FsChange::Modify { path: rel }),
+            _ => None,
+        };
+
+        if let Some(change) = change {
+            if changes.last() != Some(&change) {
+                changes.push(change);
+            }
+        }
+    }
+
+    changes
+}
+
 // Commands
 #[tauri::command]
-pub async fn create_project(
-    app: AppHandle,
+pub async fn create_project<R: Runtime>(
+    app: AppHandle<R>,
     name: String,
     working_dir: String,
 ) -> Result<String, String> {
@@ -222,10 +481,7 @@ pub async fn read_file(project_path: String, file_path: String) -> Result<String
 
     // Read first 512 bytes to sniff if the file is text
     let mut file = fs::File::open(&full).map_err(|e| e.to_string())?;
-    let mut sniff = [0u8; 512];
-    let n = file.read(&mut sniff).map_err(|e| e.to_string())?;
-
-    if std::str::from_utf8(&sniff[..n]).is_err() {
+    if looks_binary(&mut file).map_err(|e| e.to_string())? {
         return Err(format!("'{}' appears to be a binary file", file_path));
     }
 
@@ -303,21 +559,135 @@ pub async fn list_files(project_path: String) -> Result<Vec<String>, String> {
         return Err(format!("Project not found: {}", project_path));
     }
 
-    let mut files = Vec::new();
-    collect_files(&root, &root, &mut files).map_err(|e| e.to_string())?;
+    let mut files = tokio::task::spawn_blocking(move || {
+        let mut files = Vec::new();
+        collect_files(&root, &root, &IgnoreRules::root(&root), &mut files).map(|_| files)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
     files.sort();
     Ok(files)
 }
 
 #[tauri::command]
-pub async fn get_file_tree(project_path: String) -> Result<Vec<FileNode>, String> {
+pub async fn get_file_tree(
+    project_path: String,
+    show_ignored: Option<bool>,
+) -> Result<Vec<FileNode>, String> {
     let root = PathBuf::from(&project_path);
 
     if !root.exists() {
         return Err(format!("Project not found: {}", project_path));
     }
 
-    build_tree(&root, &root).map_err(|e| format!("Failed to build tree: {}", e))
+    tokio::task::spawn_blocking(move || {
+        walk_tree(
+            &root,
+            &root,
+            &IgnoreRules::root(&root),
+            show_ignored.unwrap_or(false),
+            false,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("Failed to build tree: {}", e))
+}
+
+/// One level of the tree, for expanding folders on demand. `dir_path` is
+/// project-relative; empty or absent lists the root.
+#[tauri::command]
+pub async fn list_directory(
+    project_path: String,
+    dir_path: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    show_ignored: Option<bool>,
+) -> Result<DirectoryPage, String> {
+    let base = PathBuf::from(&project_path)
+        .canonicalize()
+        .map_err(|_| format!("Project not found: {}", project_path))?;
+    let rel = dir_path.unwrap_or_default();
+    let dir = base
+        .join(&rel)
+        .canonicalize()
+        .map_err(|_| format!("Directory not found: {}", rel))?;
+
+    if !dir.starts_with(&base) {
+        return Err("Path traversal detected — access denied".to_string());
+    }
+    if !dir.is_dir() {
+        return Err(format!("Not a directory: {}", rel));
+    }
+
+    let offset = offset.unwrap_or(0);
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    tokio::task::spawn_blocking(move || {
+        list_page(&base, &dir, offset, limit, show_ignored.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("Failed to list '{}': {}", rel, e))
+}
+
+/// Starts emitting `project_fs_event` batches for changes under the project.
+/// Watching a project that is already watched is a no-op.
+#[tauri::command]
+pub async fn watch_project<R: Runtime>(
+    app: AppHandle<R>,
+    watchers: State<'_, ProjectWatchers>,
+    project_path: String,
+) -> Result<(), String> {
+    let base = PathBuf::from(&project_path)
+        .canonicalize()
+        .map_err(|e| format!("Invalid project path '{}': {}", project_path, e))?;
+
+    let mut watchers = watchers.0.lock().map_err(|e| e.to_string())?;
+    if watchers.contains_key(&base) {
+        return Ok(());
+    }
+
+    let root = base.clone();
+    let mut rules = WatchedRules::new(&base);
+    let mut watcher = new_debouncer(WATCH_DEBOUNCE, None, move |result: DebounceEventResult| {
+        // watcher errors (e.g. a vanished directory) carry nothing the tree can use
+        let Ok(events) = result else {
+            return;
+        };
+        let changes = to_changes(&root, &mut rules, events);
+        if changes.is_empty() {
+            return;
+        }
+        let _ = app.emit(
+            "project_fs_event",
+            FsEvent {
+                project_path: project_path.clone(),
+                changes,
+            },
+        );
+    })
+    .map_err(|e| format!("Failed to start file watcher: {}", e))?;
+
+    watcher
+        .watch(&base, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch '{}': {}", base.display(), e))?;
+
+    watchers.insert(base, watcher);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn unwatch_project(
+    watchers: State<'_, ProjectWatchers>,
+    project_path: String,
+) -> Result<(), String> {
+    let Ok(base) = PathBuf::from(&project_path).canonicalize() else {
+        // a deleted project has nothing left to watch
+        return Ok(());
+    };
+    watchers.0.lock().map_err(|e| e.to_string())?.remove(&base);
+    Ok(())
 }
 
 #[tauri::command]
@@ -451,3 +821,160 @@ pub async fn open_in_finder(path: String) -> Result<(), String> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify_debouncer_full::notify::event::{CreateKind, DataChange, MetadataKind};
+    use notify_debouncer_full::notify::Event;
+    use notify_debouncer_full::DebouncedEvent;
+    use std::time::Instant;
+
+    const RENAME_BOTH: EventKind = EventKind::Modify(ModifyKind::Name(RenameMode::Both));
+    const MODIFIED: EventKind = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+
+    fn project(files: &[&str]) -> PathBuf {
+        let base = std::env::temp_dir()
+            .join("coding-editor-watch")
+            .join(uuid::Uuid::new_v4().to_string());
+        fs::create_dir_all(&base).unwrap();
+        fs::write(base.join(".gitignore"), "*.log\n").unwrap();
+        for path in files {
+            let file = base.join(path);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, "").unwrap();
+        }
+        base
+    }
+
+    fn event(kind: EventKind, paths: &[PathBuf]) -> DebouncedEvent {
+        let event = paths
+            .iter()
+            .fold(Event::new(kind), |e, p| e.add_path(p.clone()));
+        DebouncedEvent::new(event, Instant::now())
+    }
+
+    fn changes(base: &Path, events: Vec<DebouncedEvent>) -> Vec<FsChange> {
+        to_changes(base, &mut WatchedRules::new(base), events)
+    }
+
+    #[test]
+    fn paired_rename_is_one_change() {
+        let base = project(&["app/b.ts"]);
+        let events = vec![event(
+            RENAME_BOTH,
+            &[base.join("app/a.ts"), base.join("app/b.ts")],
+        )];
+        assert_eq!(
+            changes(&base, events),
+            vec![FsChange::Rename {
+                from: "app/a.ts".into(),
+                to: "app/b.ts".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn rename_across_an_ignore_boundary_is_a_delete_or_create() {
+        let base = project(&["node_modules/a.ts", "app/trace.ts"]);
+        let events = vec![
+            event(
+                RENAME_BOTH,
+                &[base.join("app/a.ts"), base.join("node_modules/a.ts")],
+            ),
+            event(
+                RENAME_BOTH,
+                &[base.join("trace.log"), base.join("app/trace.ts")],
+            ),
+            event(
+                RENAME_BOTH,
+                &[base.join("debug.log"), base.join("node_modules/debug.log")],
+            ),
+        ];
+        assert_eq!(
+            changes(&base, events),
+            vec![
+                FsChange::Delete {
+                    path: "app/a.ts".into()
+                },
+                FsChange::Create {
+                    path: "app/trace.ts".into(),
+                    is_dir: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unpaired_rename_halves_read_as_delete_and_create() {
+        let base = project(&["app/new.ts"]);
+        let events = vec![
+            event(
+                EventKind::Modify(ModifyKind::Name(RenameMode::From)),
+                &[base.join("app/old.ts")],
+            ),
+            event(
+                EventKind::Modify(ModifyKind::Name(RenameMode::To)),
+                &[base.join("app/new.ts")],
+            ),
+            // the OS could not tell which half this is, so the disk decides
+            event(
+                EventKind::Modify(ModifyKind::Name(RenameMode::Any)),
+                &[base.join("app/gone.ts")],
+            ),
+        ];
+        assert_eq!(
+            changes(&base, events),
+            vec![
+                FsChange::Delete {
+                    path: "app/old.ts".into()
+                },
+                FsChange::Create {
+                    path: "app/new.ts".into(),
+                    is_dir: false,
+                },
+                FsChange::Delete {
+                    path: "app/gone.ts".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn ignored_paths_produce_nothing() {
+        let base = project(&["debug.log", "node_modules/lib/index.js"]);
+        let events = vec![
+            event(MODIFIED, &[base.join("debug.log")]),
+            event(
+                EventKind::Create(CreateKind::File),
+                &[base.join("node_modules/lib/index.js")],
+            ),
+            event(
+                EventKind::Modify(ModifyKind::Name(RenameMode::From)),
+                &[base.join(".git/index.lock")],
+            ),
+            // the project root itself
+            event(MODIFIED, std::slice::from_ref(&base)),
+        ];
+        assert_eq!(changes(&base, events), Vec::new());
+    }
+
+    #[test]
+    fn repeated_modifies_collapse() {
+        let base = project(&["app/a.ts"]);
+        let events = vec![
+            event(MODIFIED, &[base.join("app/a.ts")]),
+            event(MODIFIED, &[base.join("app/a.ts")]),
+            event(
+                EventKind::Modify(ModifyKind::Metadata(MetadataKind::Permissions)),
+                &[base.join("app/a.ts")],
+            ),
+        ];
+        assert_eq!(
+            changes(&base, events),
+            vec![FsChange::Modify {
+                path: "app/a.ts".into()
+            }]
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! Ignore rules for walking a project: `.gitignore` at every level,
+//! `.git/info/exclude`, and the project's own [`PROJECT_IGNORE_FILE`].
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Entries the project wants kept out of the editor and the agent without
+/// touching `.gitignore`. Same syntax; it wins over `.gitignore` in its directory.
+pub(crate) const PROJECT_IGNORE_FILE: &str = ".editorignore";
+
+const IGNORE_FILES: &[&str] = &[".gitignore", PROJECT_IGNORE_FILE];
+
+/// The rules in effect inside one directory: its own ignore files plus those
+/// of every ancestor up to the project root.
+#[derive(Clone, Default)]
+pub(crate) struct IgnoreRules {
+    // outermost first; the deepest match decides
+    levels: Vec<Arc<Gitignore>>,
+}
+
+impl IgnoreRules {
+    /// Rules at the project root.
+    pub(crate) fn root(base: &Path) -> Self {
+        let mut rules = Self::default();
+        let exclude = base.join(".git").join("info").join("exclude");
+        if exclude.is_file() {
+            rules.push(base, &[exclude]);
+        }
+        rules.enter(base)
+    }
+
+    /// Rules inside `dir`, a directory under `base`, and whether `dir` itself
+    /// (or one of its ancestors) is ignored.
+    pub(crate) fn for_dir(base: &Path, dir: &Path) -> (Self, bool) {
+        let mut rules = Self::root(base);
+        let mut ignored = false;
+        let mut current = base.to_path_buf();
+        for part in dir.strip_prefix(base).unwrap_or(Path::new("")).components() {
+            current.push(part);
+            ignored = ignored || rules.is_ignored(&current, true);
+            rules = rules.enter(&current);
+        }
+        (rules, ignored)
+    }
+
+    /// Rules inside `dir`, a direct child of the directory these rules cover.
+    pub(crate) fn enter(&self, dir: &Path) -> Self {
+        let files: Vec<PathBuf> = IGNORE_FILES
+            .iter()
+            .map(|name| dir.join(name))
+            .filter(|file| file.is_file())
+            .collect();
+
+        let mut rules = self.clone();
+        if !files.is_empty() {
+            rules.push(dir, &files);
+        }
+        rules
+    }
+
+    fn push(&mut self, root: &Path, files: &[PathBuf]) {
+        let mut builder = GitignoreBuilder::new(root);
+        for file in files {
+            // a bad glob only loses its own line
+            let _ = builder.add(file);
+        }
+        if let Ok(gitignore) = builder.build() {
+            self.levels.push(Arc::new(gitignore));
+        }
+    }
+
+    /// Whether `path` is ignored by these rules. A directory's own ignored
+    /// status is not inherited here; callers walking down track that.
+    pub(crate) fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for level in self.levels.iter().rev() {
+            match level.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+}
+
+/// Whether `path` is one of the files the rules are read from.
+fn is_rules_file(base: &Path, path: &Path) -> bool {
+    path == base.join(".git").join("info").join("exclude")
+        || path
+            .file_name()
+            .is_some_and(|name| IGNORE_FILES.iter().any(|f| name == *f))
+}
+
+/// Rules for the file watcher, built per directory as events arrive and
+/// dropped whenever one of the ignore files changes.
+pub(crate) struct WatchedRules {
+    base: PathBuf,
+    // each directory's rules and whether the directory itself is ignored
+    dirs: RefCell<HashMap<PathBuf, (IgnoreRules, bool)>>,
+}
+
+impl WatchedRules {
+    pub(crate) fn new(base: &Path) -> Self {
+        Self {
+            base: base.to_path_buf(),
+            dirs: RefCell::default(),
+        }
+    }
+
+    /// Forgets the cached rules when any of `paths` is an ignore file.
+    pub(crate) fn refresh<'p>(&mut self, paths: impl IntoIterator<Item = &'p PathBuf>) {
+        if paths.into_iter().any(|p| is_rules_file(&self.base, p)) {
+            self.dirs.get_mut().clear();
+        }
+    }
+
+    /// Whether `path`, under the project root, is ignored. A path that no
+    /// longer exists counts as ignored when it would be as a file or a directory.
+    pub(crate) fn is_ignored(&self, path: &Path) -> bool {
+        let Some(dir) = path.parent().filter(|d| d.starts_with(&self.base)) else {
+            return false;
+        };
+        let mut dirs = self.dirs.borrow_mut();
+        let (rules, dir_ignored) = dirs
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| IgnoreRules::for_dir(&self.base, dir));
+        if *dir_ignored {
+            return true;
+        }
+        if path.exists() {
+            rules.is_ignored(path, path.is_dir())
+        } else {
+            rules.is_ignored(path, false) || rules.is_ignored(path, true)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn project(files: &[(&str, &str)]) -> PathBuf {
+        let base = std::env::temp_dir()
+            .join("coding-editor-ignores")
+            .join(uuid::Uuid::new_v4().to_string());
+        for (path, content) in files {
+            let file = base.join(path);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, content).unwrap();
+        }
+        fs::create_dir_all(&base).unwrap();
+        base
+    }
+
+    fn ignored(base: &Path, relative: &str) -> bool {
+        let path = base.join(relative);
+        let (rules, dir_ignored) = IgnoreRules::for_dir(base, path.parent().unwrap());
+        dir_ignored || rules.is_ignored(&path, false)
+    }
+
+    #[test]
+    fn negation_keeps_a_file() {
+        let base = project(&[(".gitignore", "*.log\n!keep.log\n")]);
+        assert!(ignored(&base, "debug.log"));
+        assert!(!ignored(&base, "keep.log"));
+        assert!(!ignored(&base, "main.ts"));
+    }
+
+    #[test]
+    fn nested_gitignore_applies_below_its_directory() {
+        let base = project(&[
+            (".gitignore", "*.log\n"),
+            ("app/.gitignore", "generated/\n!trace.log\n"),
+        ]);
+        assert!(ignored(&base, "app/generated/types.ts"));
+        assert!(!ignored(&base, "generated/types.ts"));
+        // the deeper file re-includes what the root ignores
+        assert!(!ignored(&base, "app/trace.log"));
+        assert!(ignored(&base, "trace.log"));
+    }
+
+    #[test]
+    fn editorignore_wins_over_gitignore() {
+        let base = project(&[
+            (".gitignore", "dist/\n"),
+            (PROJECT_IGNORE_FILE, "!dist/\nfixtures/\n"),
+        ]);
+        assert!(!ignored(&base, "dist/index.js"));
+        assert!(ignored(&base, "fixtures/big.json"));
+    }
+
+    #[test]
+    fn exclude_file_is_read() {
+        let base = project(&[(".git/info/exclude", "scratch.ts\n")]);
+        assert!(ignored(&base, "scratch.ts"));
+    }
+
+    #[test]
+    fn watched_rules_rebuild_when_an_ignore_file_changes() {
+        let base = project(&[(".gitignore", "*.log\n")]);
+        let mut rules = WatchedRules::new(&base);
+        assert!(rules.is_ignored(&base.join("debug.log")));
+
+        fs::write(base.join(".gitignore"), "").unwrap();
+        rules.refresh([&base.join("app").join("index.ts")]);
+        assert!(rules.is_ignored(&base.join("debug.log")));
+        rules.refresh([&base.join(".gitignore")]);
+        assert!(!rules.is_ignored(&base.join("debug.log")));
+    }
+}
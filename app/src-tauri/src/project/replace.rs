@@ -0,0 +1,542 @@
+//! Project-wide find and replace. A preview works out every replacement and
+//! its diff without touching the project; applying writes the selected ones in
+//! a single all-or-nothing pass and keeps the replaced files, so the whole
+//! operation can be undone as one step.
+
+use super::resolve_within;
+use super::search::{build_pattern, read_text, search_files, SearchSpec};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use similar::TextDiff;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, Runtime};
+
+const UNDO_DIR: &str = "project-replace";
+const JOURNAL_FILE: &str = "journal.json";
+const ORIGINALS_DIR: &str = "originals";
+// older operations are dropped once there are more than this
+const MAX_UNDO_RECORDS: usize = 20;
+const MAX_REPLACEMENTS: usize = 20_000;
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Replacement {
+    // position among the file's replacements; what a selection refers to
+    pub index: usize,
+    // 1-based; columns count characters
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub text: String,
+    // the line with only this replacement made
+    pub replaced: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileReplacements {
+    pub path: String,
+    // content hash the preview was made against
+    pub hash: String,
+    pub replacements: Vec<Replacement>,
+    // unified diff with every replacement made
+    pub diff: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplacePreview {
+    pub files: Vec<FileReplacements>,
+    pub replacements: usize,
+    // files past the cap were left out
+    pub truncated: bool,
+}
+
+/// Which replacements of a previewed file to make.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReplaceSelection {
+    pub path: String,
+    pub hash: String,
+    // all of them when absent
+    pub indices: Option<Vec<usize>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplaceResult {
+    pub undo_id: String,
+    pub files: Vec<String>,
+    pub replacements: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReplaceOutcome {
+    Preview(ReplacePreview),
+    Applied(ReplaceResult),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    path: String,
+    // hash of the content the replace wrote; undo refuses if it has changed since
+    hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Journal {
+    project_path: String,
+    created_at: String,
+    entries: Vec<Entry>,
+}
+
+/// One match and what replaces it, as byte offsets into the file's text.
+struct Found {
+    start: usize,
+    end: usize,
+    with: String,
+    line: usize,
+    column: usize,
+    length: usize,
+    text: String,
+    replaced: String,
+}
+
+struct PendingWrite {
+    path: String,
+    target: PathBuf,
+    temp: PathBuf,
+    content: String,
+    original: String,
+}
+
+fn digest(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Every replacement in `text`, matched a line at a time like `search_project`.
+/// Capture groups (`$1`, `${name}`) expand only in regex searches.
+fn find_replacements(text: &str, pattern: &Regex, replacement: &str, expand: bool) -> Vec<Found> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+
+    for (i, segment) in text.split_inclusive('\n').enumerate() {
+        let line = segment.strip_suffix('\n').unwrap_or(segment);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        for caps in pattern.captures_iter(line) {
+            let Some(m) = caps.get(0) else {
+                continue;
+            };
+            if m.as_str().is_empty() {
+                continue;
+            }
+            let with = if expand {
+                let mut with = String::new();
+                caps.expand(replacement, &mut with);
+                with
+            } else {
+                replacement.to_string()
+            };
+            found.push(Found {
+                start: offset + m.start(),
+                end: offset + m.end(),
+                line: i + 1,
+                column: line[..m.start()].chars().count() + 1,
+                length: m.as_str().chars().count(),
+                text: line.to_string(),
+                replaced: format!("{}{}{}", &line[..m.start()], with, &line[m.end()..]),
+                with,
+            });
+        }
+
+        offset += segment.len();
+    }
+
+    found
+}
+
+fn apply_found(text: &str, found: &[Found], selected: impl Fn(usize) -> bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (index, f) in found.iter().enumerate() {
+        if !selected(index) {
+            continue;
+        }
+        out.push_str(&text[last..f.start]);
+        out.push_str(&f.with);
+        last = f.end;
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+fn preview(base: &Path, spec: &SearchSpec, replacement: &str) -> Result<ReplacePreview, String> {
+    let pattern = build_pattern(spec)?;
+    let mut preview = ReplacePreview {
+        files: Vec::new(),
+        replacements: 0,
+        truncated: false,
+    };
+
+    for path in search_files(base, spec)? {
+        let Some(text) = read_text(&base.join(&path)) else {
+            continue;
+        };
+        let found = find_replacements(&text, &pattern, replacement, spec.regex);
+        if found.is_empty() {
+            continue;
+        }
+        if preview.replacements + found.len() > MAX_REPLACEMENTS {
+            preview.truncated = true;
+            break;
+        }
+
+        let updated = apply_found(&text, &found, |_| true);
+        let diff = TextDiff::from_lines(&text, &updated)
+            .unified_diff()
+            .context_radius(CONTEXT_LINES)
+            .header(&format!("a/{}", path), &format!("b/{}", path))
+            .to_string();
+
+        preview.replacements += found.len();
+        preview.files.push(FileReplacements {
+            hash: digest(&text),
+            replacements: found
+                .into_iter()
+                .enumerate()
+                .map(|(index, f)| Replacement {
+                    index,
+                    line: f.line,
+                    column: f.column,
+                    length: f.length,
+                    text: f.text,
+                    replaced: f.replaced,
+                })
+                .collect(),
+            diff,
+            path,
+        });
+    }
+
+    Ok(preview)
+}
+
+fn temp_path(target: &Path, path: &str) -> Result<PathBuf, String> {
+    let name = target
+        .file_name()
+        .ok_or_else(|| format!("Invalid file path: {}", path))?
+        .to_string_lossy()
+        .to_string();
+    Ok(target.with_file_name(format!(".{}.replacing", name)))
+}
+
+fn cleanup_temps(writes: &[PendingWrite]) {
+    for write in writes {
+        fs::remove_file(&write.temp).ok();
+    }
+}
+
+/// Writes every file or none: each new version is staged next to its target
+/// first, then swapped in, putting back what was already swapped on failure.
+fn write_all(writes: &[PendingWrite]) -> Result<(), String> {
+    for write in writes {
+        let staged = fs::write(&write.temp, &write.content).and_then(|_| {
+            // keep the mode, so scripts stay executable
+            let permissions = fs::metadata(&write.target)?.permissions();
+            fs::set_permissions(&write.temp, permissions)
+        });
+        if let Err(e) = staged {
+            cleanup_temps(writes);
+            return Err(format!("Failed to write '{}': {}", write.path, e));
+        }
+    }
+
+    for (i, write) in writes.iter().enumerate() {
+        if let Err(e) = fs::rename(&write.temp, &write.target) {
+            for done in &writes[..i] {
+                fs::write(&done.target, &done.original).ok();
+            }
+            cleanup_temps(&writes[i..]);
+            return Err(format!("Failed to replace in '{}': {}", write.path, e));
+        }
+    }
+
+    Ok(())
+}
+
+fn undo_root<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {}", e))?;
+    Ok(data_dir.join(UNDO_DIR))
+}
+
+fn undo_dir<R: Runtime>(app: &AppHandle<R>, undo_id: &str) -> Result<PathBuf, String> {
+    if undo_id.is_empty()
+        || !undo_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(format!("Invalid undo id: {}", undo_id));
+    }
+    Ok(undo_root(app)?.join(undo_id))
+}
+
+/// Keeps the replaced content of every file the operation is about to write.
+fn record_undo(dir: &Path, project_path: &str, writes: &[PendingWrite]) -> Result<(), String> {
+    fs::create_dir_all(dir.join(ORIGINALS_DIR))
+        .map_err(|e| format!("Failed to create undo record: {}", e))?;
+
+    for (index, write) in writes.iter().enumerate() {
+        fs::write(
+            dir.join(ORIGINALS_DIR).join(index.to_string()),
+            &write.original,
+        )
+        .map_err(|e| format!("Failed to save '{}' for undo: {}", write.path, e))?;
+    }
+
+    let journal = Journal {
+        project_path: project_path.to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        entries: writes
+            .iter()
+            .map(|w| Entry {
+                path: w.path.clone(),
+                hash: digest(&w.content),
+            })
+            .collect(),
+    };
+    let raw = serde_json::to_string_pretty(&journal).map_err(|e| e.to_string())?;
+    fs::write(dir.join(JOURNAL_FILE), raw)
+        .map_err(|e| format!("Failed to write undo record: {}", e))
+}
+
+/// Drops all but the newest `MAX_UNDO_RECORDS` operations.
+fn prune_undo(root: &Path) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+    let mut records: Vec<(String, PathBuf)> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let raw = fs::read_to_string(e.path().join(JOURNAL_FILE)).ok()?;
+            let journal: Journal = serde_json::from_str(&raw).ok()?;
+            Some((journal.created_at, e.path()))
+        })
+        .collect();
+
+    records.sort_by(|a, b| b.0.cmp(&a.0));
+    for (_, dir) in records.into_iter().skip(MAX_UNDO_RECORDS) {
+        fs::remove_dir_all(dir).ok();
+    }
+}
+
+fn apply<R: Runtime>(
+    app: &AppHandle<R>,
+    project_path: &str,
+    spec: &SearchSpec,
+    replacement: &str,
+    selection: Vec<ReplaceSelection>,
+) -> Result<ReplaceResult, String> {
+    let pattern = build_pattern(spec)?;
+
+    let mut writes = Vec::new();
+    let mut replacements = 0;
+    let mut seen = HashSet::new();
+    for selected in selection {
+        let target = resolve_within(project_path, &selected.path)?;
+        // a second pass over the same file would write from stale text
+        if !seen.insert(target.clone()) {
+            return Err(format!("'{}' is selected more than once", selected.path));
+        }
+        let text = read_text(&target)
+            .ok_or_else(|| format!("Cannot replace in '{}': not a text file", selected.path))?;
+        if digest(&text) != selected.hash {
+            return Err(format!(
+                "'{}' changed since the preview — preview again before replacing",
+                selected.path
+            ));
+        }
+
+        let found = find_replacements(&text, &pattern, replacement, spec.regex);
+        if let Some(index) = selected
+            .indices
+            .iter()
+            .flatten()
+            .find(|&&i| i >= found.len())
+        {
+            return Err(format!("No replacement {} in '{}'", index, selected.path));
+        }
+        let is_selected = |i: usize| {
+            selected
+                .indices
+                .as_ref()
+                .is_none_or(|indices| indices.contains(&i))
+        };
+
+        let content = apply_found(&text, &found, is_selected);
+        if content == text {
+            continue;
+        }
+        let count = (0..found.len()).filter(|&i| is_selected(i)).count();
+
+        replacements += count;
+        writes.push(PendingWrite {
+            temp: temp_path(&target, &selected.path)?,
+            path: selected.path,
+            target,
+            content,
+            original: text,
+        });
+    }
+
+    if writes.is_empty() {
+        return Err("Nothing to replace".to_string());
+    }
+
+    let undo_id = uuid::Uuid::new_v4().to_string();
+    let dir = undo_dir(app, &undo_id)?;
+    if let Err(e) = record_undo(&dir, project_path, &writes) {
+        fs::remove_dir_all(&dir).ok();
+        return Err(e);
+    }
+    if let Err(e) = write_all(&writes) {
+        fs::remove_dir_all(&dir).ok();
+        return Err(e);
+    }
+    prune_undo(&undo_root(app)?);
+
+    Ok(ReplaceResult {
+        undo_id,
+        files: writes.into_iter().map(|w| w.path).collect(),
+        replacements,
+    })
+}
+
+fn undo<R: Runtime>(app: &AppHandle<R>, undo_id: &str) -> Result<Vec<String>, String> {
+    let dir = undo_dir(app, undo_id)?;
+    let raw = fs::read_to_string(dir.join(JOURNAL_FILE))
+        .map_err(|_| "Nothing to undo for this replace".to_string())?;
+    let journal: Journal =
+        serde_json::from_str(&raw).map_err(|e| format!("Corrupt undo record: {}", e))?;
+
+    let mut writes = Vec::new();
+    for (index, entry) in journal.entries.into_iter().enumerate() {
+        let target = resolve_within(&journal.project_path, &entry.path)?;
+        let current = fs::read_to_string(&target).unwrap_or_default();
+        // undoing over later edits would silently throw them away
+        if digest(&current) != entry.hash {
+            return Err(format!(
+                "'{}' changed after the replace — cannot undo",
+                entry.path
+            ));
+        }
+        let original = fs::read_to_string(dir.join(ORIGINALS_DIR).join(index.to_string()))
+            .map_err(|e| format!("Failed to read saved '{}': {}", entry.path, e))?;
+
+        writes.push(PendingWrite {
+            temp: temp_path(&target, &entry.path)?,
+            path: entry.path,
+            target,
+            content: original,
+            original: current,
+        });
+    }
+
+    write_all(&writes)?;
+    fs::remove_dir_all(&dir).map_err(|e| format!("Failed to clear undo record: {}", e))?;
+
+    Ok(writes.into_iter().map(|w| w.path).collect())
+}
+
+// Commands
+/// Without a selection, previews every replacement the search would make.
+/// With one, makes the selected replacements across all files at once; the
+/// result carries the id that `undo_replace` takes.
+#[tauri::command]
+pub async fn replace_in_project<R: Runtime>(
+    app: AppHandle<R>,
+    project_path: String,
+    spec: SearchSpec,
+    replacement: String,
+    selection: Option<Vec<ReplaceSelection>>,
+) -> Result<ReplaceOutcome, String> {
+    let base: PathBuf = PathBuf::from(&project_path)
+        .canonicalize()
+        .map_err(|_| format!("Project not found: {}", project_path))?;
+
+    tokio::task::spawn_blocking(move || match selection {
+        None => preview(&base, &spec, &replacement).map(ReplaceOutcome::Preview),
+        Some(selection) => {
+            apply(&app, &project_path, &spec, &replacement, selection).map(ReplaceOutcome::Applied)
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Puts back every file a replace wrote. Returns the restored paths.
+#[tauri::command]
+pub async fn undo_replace<R: Runtime>(
+    app: AppHandle<R>,
+    undo_id: String,
+) -> Result<Vec<String>, String> {
+    tokio::task::spawn_blocking(move || undo(&app, &undo_id))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regex(pattern: &str) -> Regex {
+        Regex::new(pattern).unwrap()
+    }
+
+    #[test]
+    fn regex_replacements_expand_capture_groups() {
+        let text = "let a = 1;\nlet b = 2;\n";
+        let found = find_replacements(text, &regex(r"let (?P<name>\w+)"), "const ${name}", true);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[1].line, 2);
+        assert_eq!(found[1].replaced, "const b = 2;");
+        assert_eq!(
+            apply_found(text, &found, |_| true),
+            "const a = 1;\nconst b = 2;\n"
+        );
+    }
+
+    #[test]
+    fn literal_replacements_keep_dollar_signs() {
+        let text = "price\n";
+        let pattern = regex(&regex::escape("price"));
+        let found = find_replacements(text, &pattern, "$1 $price", false);
+        assert_eq!(apply_found(text, &found, |_| true), "$1 $price\n");
+    }
+
+    #[test]
+    fn only_selected_indices_are_replaced() {
+        let text = "a a a\n";
+        let found = find_replacements(text, &regex("a"), "b", false);
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[2].column, 5);
+        assert_eq!(apply_found(text, &found, |i| i != 1), "b a b\n");
+    }
+
+    #[test]
+    fn crlf_line_endings_survive() {
+        let text = "one\r\ntwo one\r\n";
+        let found = find_replacements(text, &regex("one$"), "1", true);
+        // `$` matches before the `\r`, which stays out of the line text
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[1].text, "two one");
+        assert_eq!(apply_found(text, &found, |_| true), "1\r\ntwo 1\r\n");
+    }
+}
@@ -0,0 +1,386 @@
+//! Project-wide text search. The walk runs on a blocking thread and streams
+//! each file's matches as a `search_event` while it goes.
+
+use super::ignores::IgnoreRules;
+use super::{collect_files, looks_binary};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Runtime, State};
+
+const DEFAULT_MAX_RESULTS: usize = 2000;
+const MAX_RESULTS: usize = 20_000;
+const DEFAULT_CONTEXT_LINES: usize = 2;
+const MAX_CONTEXT_LINES: usize = 10;
+// generated bundles and data dumps, not source
+const MAX_FILE_SIZE: u64 = 4 * 1024 * 1024;
+
+/// What to search for. Globs are matched against project-relative paths; a
+/// glob naming a directory covers everything under it.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchSpec {
+    pub query: String,
+    pub regex: bool,
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub context_lines: Option<usize>,
+    pub max_results: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchMatch {
+    // 1-based; columns count characters
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub text: String,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileMatches {
+    pub path: String,
+    pub matches: Vec<SearchMatch>,
+}
+
+/// Envelope for `search_event`: one file's matches, tagged with the search.
+#[derive(Debug, Clone, Serialize)]
+struct SearchEvent<'a> {
+    search_id: &'a str,
+    #[serde(flatten)]
+    file: &'a FileMatches,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SearchSummary {
+    pub files_searched: usize,
+    pub files_matched: usize,
+    pub matches: usize,
+    // stopped at the result cap
+    pub truncated: bool,
+    pub cancelled: bool,
+}
+
+/// Cancellation flags for the searches in flight.
+#[derive(Default)]
+pub struct Searches {
+    flags: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl Searches {
+    fn register(&self, search_id: &str) -> Result<Arc<AtomicBool>, String> {
+        let mut flags = self.flags.lock().map_err(|e| e.to_string())?;
+        if flags.contains_key(search_id) {
+            return Err(format!("Search already active: {}", search_id));
+        }
+        let flag = Arc::new(AtomicBool::new(false));
+        flags.insert(search_id.to_string(), flag.clone());
+        Ok(flag)
+    }
+
+    fn cancel(&self, search_id: &str) -> Result<(), String> {
+        let flags = self.flags.lock().map_err(|e| e.to_string())?;
+        let flag = flags
+            .get(search_id)
+            .ok_or_else(|| format!("No active search: {}", search_id))?;
+        flag.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn remove(&self, search_id: &str) {
+        if let Ok(mut flags) = self.flags.lock() {
+            flags.remove(search_id);
+        }
+    }
+}
+
+pub(crate) fn build_pattern(spec: &SearchSpec) -> Result<Regex, String> {
+    if spec.query.is_empty() {
+        return Err("Search query is empty".to_string());
+    }
+
+    let mut pattern = if spec.regex {
+        spec.query.clone()
+    } else {
+        regex::escape(&spec.query)
+    };
+    if spec.whole_word {
+        pattern = format!(r"\b(?:{})\b", pattern);
+    }
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!spec.case_sensitive)
+        .build()
+        .map_err(|e| format!("Invalid search pattern: {}", e))
+}
+
+fn glob_set(globs: &[String]) -> Result<Option<GlobSet>, String> {
+    let globs: Vec<&str> = globs
+        .iter()
+        .map(|g| g.trim().trim_start_matches("./").trim_end_matches('/'))
+        .filter(|g| !g.is_empty())
+        .collect();
+    if globs.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(Glob::new(glob).map_err(|e| format!("Invalid glob '{}': {}", glob, e))?);
+    }
+    builder
+        .build()
+        .map(Some)
+        .map_err(|e| format!("Invalid globs: {}", e))
+}
+
+/// Whether `path` or one of its parent directories matches.
+fn glob_matches(set: &GlobSet, path: &str) -> bool {
+    Path::new(path).ancestors().any(|p| set.is_match(p))
+}
+
+/// The files a search covers: the ignore-filtered set `list_files` returns,
+/// narrowed by the spec's globs.
+pub(crate) fn search_files(base: &Path, spec: &SearchSpec) -> Result<Vec<String>, String> {
+    let include = glob_set(&spec.include)?;
+    let exclude = glob_set(&spec.exclude)?;
+
+    let mut files = Vec::new();
+    collect_files(base, base, &IgnoreRules::root(base), &mut files)
+        .map_err(|e| format!("Failed to list files: {}", e))?;
+    files.retain(|path| {
+        include.as_ref().is_none_or(|set| glob_matches(set, path))
+            && !exclude.as_ref().is_some_and(|set| glob_matches(set, path))
+    });
+    files.sort();
+    Ok(files)
+}
+
+/// The file's text, or `None` for binary, oversized or unreadable files.
+pub(crate) fn read_text(full: &Path) -> Option<String> {
+    let mut file = fs::File::open(full).ok()?;
+    if file.metadata().ok()?.len() > MAX_FILE_SIZE || looks_binary(&mut file).ok()? {
+        return None;
+    }
+    fs::read_to_string(full).ok()
+}
+
+fn char_column(line: &str, byte: usize) -> usize {
+    line[..byte].chars().count() + 1
+}
+
+/// Matches in one file's text, at most `limit` of them.
+fn find_matches(
+    text: &str,
+    pattern: &Regex,
+    context_lines: usize,
+    limit: usize,
+) -> Vec<SearchMatch> {
+    let text = text.strip_suffix('\n').unwrap_or(text);
+    let lines: Vec<&str> = text
+        .split('\n')
+        .map(|l| l.strip_suffix('\r').unwrap_or(l))
+        .collect();
+    let mut matches = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        for found in pattern.find_iter(line) {
+            if matches.len() == limit {
+                return matches;
+            }
+            // patterns like `a*` match the empty string everywhere; not results
+            if found.as_str().is_empty() {
+                continue;
+            }
+            matches.push(SearchMatch {
+                line: i + 1,
+                column: char_column(line, found.start()),
+                length: found.as_str().chars().count(),
+                text: line.to_string(),
+                before: lines[i.saturating_sub(context_lines)..i]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect(),
+                after: lines[(i + 1).min(lines.len())..(i + 1 + context_lines).min(lines.len())]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect(),
+            });
+        }
+    }
+
+    matches
+}
+
+fn run_search(
+    base: &Path,
+    spec: &SearchSpec,
+    cancelled: &AtomicBool,
+    mut on_file: impl FnMut(&FileMatches),
+) -> Result<SearchSummary, String> {
+    let pattern = build_pattern(spec)?;
+    let files = search_files(base, spec)?;
+    let context_lines = spec
+        .context_lines
+        .unwrap_or(DEFAULT_CONTEXT_LINES)
+        .min(MAX_CONTEXT_LINES);
+    let max_results = spec
+        .max_results
+        .unwrap_or(DEFAULT_MAX_RESULTS)
+        .clamp(1, MAX_RESULTS);
+
+    let mut summary = SearchSummary::default();
+    for path in files {
+        if cancelled.load(Ordering::Relaxed) {
+            summary.cancelled = true;
+            break;
+        }
+        if summary.matches == max_results {
+            // whatever is left may or may not match; say the cap was hit
+            summary.truncated = true;
+            break;
+        }
+
+        summary.files_searched += 1;
+        let Some(text) = read_text(&base.join(&path)) else {
+            continue;
+        };
+        // asking for one more than is left tells filling the cap from overflowing it
+        let remaining = max_results - summary.matches;
+        let mut matches = find_matches(&text, &pattern, context_lines, remaining + 1);
+        if matches.len() > remaining {
+            matches.truncate(remaining);
+            summary.truncated = true;
+        }
+        if matches.is_empty() {
+            continue;
+        }
+
+        summary.files_matched += 1;
+        summary.matches += matches.len();
+        on_file(&FileMatches { path, matches });
+    }
+
+    Ok(summary)
+}
+
+// Commands
+/// Searches the project, emitting a `search_event` per matching file, and
+/// resolves with the totals once the walk ends, hits the cap or is cancelled.
+#[tauri::command]
+pub async fn search_project<R: Runtime>(
+    app: AppHandle<R>,
+    searches: State<'_, Searches>,
+    project_path: String,
+    search_id: String,
+    spec: SearchSpec,
+) -> Result<SearchSummary, String> {
+    let base: PathBuf = PathBuf::from(&project_path)
+        .canonicalize()
+        .map_err(|_| format!("Project not found: {}", project_path))?;
+
+    let cancelled = searches.register(&search_id)?;
+    let id = search_id.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        run_search(&base, &spec, &cancelled, |file| {
+            let _ = app.emit(
+                "search_event",
+                SearchEvent {
+                    search_id: &id,
+                    file,
+                },
+            );
+        })
+    })
+    .await;
+    searches.remove(&search_id);
+
+    result.map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cancel_search(searches: State<'_, Searches>, search_id: String) -> Result<(), String> {
+    searches.cancel(&search_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(query: &str) -> Regex {
+        build_pattern(&SearchSpec {
+            query: query.into(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn globs_cover_files_under_a_directory() {
+        let set = glob_set(&["./src/".into(), "*.md".into()])
+            .unwrap()
+            .unwrap();
+        assert!(glob_matches(&set, "src/app/index.ts"));
+        assert!(glob_matches(&set, "README.md"));
+        assert!(!glob_matches(&set, "lib/src.ts"));
+        assert!(glob_set(&[" ".into()]).unwrap().is_none());
+    }
+
+    #[test]
+    fn context_is_cut_at_the_file_edges() {
+        let text = "one\ntwo\nfind\nthree\n";
+        let matches = find_matches(text, &pattern("find"), 5, 10);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].line, 3);
+        assert_eq!(matches[0].before, vec!["one", "two"]);
+        assert_eq!(matches[0].after, vec!["three"]);
+
+        let matches = find_matches("find\r\nnext\r\n", &pattern("find"), 1, 10);
+        assert!(matches[0].before.is_empty());
+        assert_eq!(matches[0].after, vec!["next"]);
+    }
+
+    #[test]
+    fn matches_stop_at_the_limit() {
+        let matches = find_matches("x x\nx\n", &pattern("x"), 0, 2);
+        assert_eq!(matches.len(), 2);
+        assert_eq!((matches[1].line, matches[1].column), (1, 3));
+    }
+
+    #[test]
+    fn search_reports_hitting_the_cap() {
+        let base = std::env::temp_dir()
+            .join("coding-editor-search")
+            .join(uuid::Uuid::new_v4().to_string());
+        fs::create_dir_all(&base).unwrap();
+        fs::write(base.join("a.ts"), "x x\n").unwrap();
+        fs::write(base.join("b.ts"), "x\n").unwrap();
+
+        let spec = |max_results| SearchSpec {
+            query: "x".into(),
+            max_results: Some(max_results),
+            ..Default::default()
+        };
+        let mut files = Vec::new();
+        let summary = run_search(&base, &spec(2), &AtomicBool::new(false), |f| {
+            files.push(f.path.clone())
+        })
+        .unwrap();
+        assert_eq!(summary.matches, 2);
+        assert!(summary.truncated);
+        assert_eq!(files, vec!["a.ts"]);
+
+        let summary = run_search(&base, &spec(3), &AtomicBool::new(false), |_| {}).unwrap();
+        assert_eq!(summary.matches, 3);
+        assert!(!summary.truncated);
+    }
+}
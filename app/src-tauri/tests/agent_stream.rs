@@ -0,0 +1,845 @@
+//! Drives `scaffold_project` and `edit_project_file` against a scripted backend.
+
+mod common;
+
+use coding_editor_lib::agent::conversation::{get_conversation, list_conversations};
+use coding_editor_lib::agent::policy::{set_write_policy, WritePolicy};
+use coding_editor_lib::agent::recording::replay_agent_run;
+use coding_editor_lib::agent::staging::{
+    accept_staged_change, apply_staged_changes, list_staged_files,
+};
+use coding_editor_lib::agent::{edit_project_file, scaffold_project, undo_agent_run, RunOptions};
+use common::{frame, frame_with_id, raw, temp_project, MockBackend, Reply, Step, TestApp};
+use serde_json::json;
+use std::path::Path;
+use tauri::Manager;
+
+fn options(run_id: &str) -> Option<RunOptions> {
+    Some(RunOptions {
+        run_id: Some(run_id.to_string()),
+        ..Default::default()
+    })
+}
+
+async fn scaffold(app: &TestApp, project: &Path, run_id: &str) -> Result<(), String> {
+    scaffold_with(app, project, options(run_id)).await
+}
+
+async fn scaffold_with(
+    app: &TestApp,
+    project: &Path,
+    options: Option<RunOptions>,
+) -> Result<(), String> {
+    let handle = app.handle();
+    scaffold_project(
+        handle.clone(),
+        handle.state(),
+        handle.state(),
+        handle.state(),
+        options,
+        project.to_string_lossy().to_string(),
+        "Build a todo app".into(),
+        "Todo".into(),
+        "#336699".into(),
+        Vec::new(),
+    )
+    .await
+}
+
+fn write(path: &str, content: &str) -> serde_json::Value {
+    json!({ "type": "file_write", "path": path, "content": content })
+}
+
+fn done(summary: &str, files: &[&str]) -> serde_json::Value {
+    json!({ "type": "done", "summary": summary, "files": files })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn scaffold_writes_streamed_files() {
+    let backend = MockBackend::start(vec![Reply::stream(vec![
+        frame("status", json!({ "type": "status", "message": "Planning" })),
+        frame("file_write", write("app/index.tsx", "export default 1;\n")),
+        frame("done", done("Built it", &["app/index.tsx"])),
+    ])])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+
+    scaffold(&app, &project, "scaffold-writes").await.unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(project.join("app/index.tsx")).unwrap(),
+        "export default 1;\n"
+    );
+    let last = app.last_event();
+    assert_eq!(last["type"], "done");
+    assert_eq!(last["summary"], "Built it");
+    assert_eq!(last["run_id"], "scaffold-writes");
+
+    let requests = backend.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].path, "/generate");
+    assert_eq!(requests[0].header("x-agent-protocol"), Some("1"));
+    let body = requests[0].json();
+    assert_eq!(body["user_prompt"], "Build a todo app");
+    assert_eq!(body["app_name"], "Todo");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn malformed_frames_are_reported_and_skipped() {
+    let backend = MockBackend::start(vec![Reply::stream(vec![
+        raw("event: file_write\ndata: {not json\n\n"),
+        frame("teleport", json!({ "type": "teleport", "to": "mars" })),
+        frame("file_write", write("app/ok.tsx", "ok\n")),
+        frame("done", done("Done", &["app/ok.tsx"])),
+    ])])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+
+    scaffold(&app, &project, "malformed-frames").await.unwrap();
+
+    let malformed: Vec<_> = app
+        .events_of("diagnostic")
+        .into_iter()
+        .filter(|e| e["message"].as_str().unwrap().starts_with("Malformed"))
+        .collect();
+    assert_eq!(malformed.len(), 2);
+    assert!(project.join("app/ok.tsx").is_file());
+    assert_eq!(app.last_event()["type"], "done");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn traversal_attempts_never_leave_the_project() {
+    let project = temp_project();
+    let outside = project.parent().unwrap();
+    // absolute, and in a directory of this test's own
+    let absolute = temp_project().join("escape-absolute.txt");
+    let backend = MockBackend::start(vec![Reply::stream(vec![
+        frame("file_write", write("../escape.txt", "out")),
+        frame("file_write", write("app/../../escape-nested.txt", "out")),
+        frame("file_write", write(&absolute.to_string_lossy(), "out")),
+        frame("file_write", write("app/ok.tsx", "ok\n")),
+        frame("done", done("Done", &["app/ok.tsx"])),
+    ])])
+    .await;
+    let app = TestApp::new(&backend).await;
+
+    scaffold(&app, &project, "traversal").await.unwrap();
+
+    assert!(!outside.join("escape.txt").exists());
+    assert!(!outside.join("escape-nested.txt").exists());
+    assert!(!absolute.exists());
+    let errors = app.events_of("error");
+    assert_eq!(errors.len(), 3);
+    assert!(errors
+        .iter()
+        .all(|e| e["message"].as_str().unwrap().contains("traversal")));
+    assert!(project.join("app/ok.tsx").is_file());
+    assert_eq!(app.last_event()["type"], "done");
+
+    // the rejected paths must not have poisoned the undo journal
+    let handle = app.handle();
+    let restored = undo_agent_run(handle.clone(), handle.state(), "traversal".into())
+        .await
+        .unwrap();
+    assert_eq!(restored, vec!["app/ok.tsx".to_string()]);
+    assert!(!project.join("app/ok.tsx").exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn oversized_frame_fails_the_run() {
+    let huge = format!("event: file_write\ndata: {}", "x".repeat(11 * 1024 * 1024));
+    let backend = MockBackend::start(vec![Reply::stream(vec![
+        frame("file_write", write("app/first.tsx", "first\n")),
+        Step::Send(huge.into_bytes()),
+        frame("done", done("Done", &[])),
+    ])])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+
+    let err = scaffold(&app, &project, "oversized").await.unwrap_err();
+
+    assert!(err.contains("safety limit"), "{}", err);
+    assert!(project.join("app/first.tsx").is_file());
+    assert!(app.events_of("done").is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn disconnect_without_event_ids_is_resumable_failure() {
+    let backend = MockBackend::start(vec![Reply::stream(vec![
+        frame("file_write", write("app/a.tsx", "a\n")),
+        Step::Disconnect,
+    ])])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+
+    scaffold(&app, &project, "disconnect").await.unwrap();
+
+    let last = app.last_event();
+    assert_eq!(last["type"], "resumable_failure");
+    assert_eq!(last["applied_files"], json!(["app/a.tsx"]));
+    assert_eq!(last["attempts"], 0);
+    assert!(project.join("app/a.tsx").is_file());
+    // nothing to resume from, so no reconnect
+    assert_eq!(backend.requests().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reconnects_from_the_last_event_id() {
+    let backend = MockBackend::start(vec![
+        Reply::stream(vec![
+            raw("retry: 10\n\n"),
+            frame_with_id("1", "file_write", write("app/a.tsx", "a\n")),
+            frame_with_id("2", "file_write", write("app/b.tsx", "b\n")),
+            Step::Disconnect,
+        ]),
+        Reply::stream(vec![
+            // the server replays the last frame it is unsure about
+            frame_with_id("2", "file_write", write("app/b.tsx", "b\n")),
+            frame_with_id("3", "done", done("Done", &["app/a.tsx", "app/b.tsx"])),
+        ]),
+    ])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+
+    scaffold(&app, &project, "reconnect").await.unwrap();
+
+    let requests = backend.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].header("last-event-id"), None);
+    assert_eq!(requests[1].header("last-event-id"), Some("2"));
+    assert_eq!(app.events_of("file_write").len(), 2);
+    assert!(app
+        .events_of("status")
+        .iter()
+        .any(|e| e["message"].as_str().unwrap().contains("reconnecting 1/")));
+    assert_eq!(app.last_event()["type"], "done");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reconnect_after_a_partial_frame_resends_it() {
+    let backend = MockBackend::start(vec![
+        Reply::stream(vec![
+            raw("retry: 10\n\n"),
+            frame_with_id("1", "file_write", write("app/a.tsx", "a\n")),
+            // the blank line that would end event 2 never arrives
+            raw(&format!(
+                "id: 2\nevent: file_write\ndata: {}\n",
+                write("app/b.tsx", "b\n")
+            )),
+            Step::Disconnect,
+        ]),
+        Reply::stream(vec![
+            frame_with_id("2", "file_write", write("app/b.tsx", "b\n")),
+            frame_with_id("3", "done", done("Done", &["app/a.tsx", "app/b.tsx"])),
+        ]),
+    ])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+
+    scaffold(&app, &project, "reconnect-partial").await.unwrap();
+
+    let requests = backend.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].header("last-event-id"), Some("1"));
+    assert!(project.join("app/b.tsx").is_file());
+    assert_eq!(app.last_event()["type"], "done");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn edit_applies_a_streamed_patch() {
+    let diff = "--- a/app/index.tsx\n+++ b/app/index.tsx\n@@ -1,2 +1,2 @@\n line one\n-line two\n+line 2\n";
+    let backend = MockBackend::start(vec![Reply::stream(vec![
+        frame(
+            "file_patch",
+            json!({ "type": "file_patch", "path": "app/index.tsx", "diff": diff }),
+        ),
+        frame("done", done("Renamed a line", &["app/index.tsx"])),
+    ])])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+    std::fs::create_dir_all(project.join("app")).unwrap();
+    let original = "line one\nline two\n";
+    std::fs::write(project.join("app/index.tsx"), original).unwrap();
+
+    let handle = app.handle();
+    edit_project_file(
+        handle.clone(),
+        handle.state(),
+        handle.state(),
+        handle.state(),
+        options("edit-patch"),
+        project.to_string_lossy().to_string(),
+        "app/index.tsx".into(),
+        original.into(),
+        "Use a digit".into(),
+        None,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(project.join("app/index.tsx")).unwrap(),
+        "line one\nline 2\n"
+    );
+    assert_eq!(app.last_event()["type"], "done");
+
+    let requests = backend.requests();
+    assert_eq!(requests[0].path, "/edit");
+    let body = requests[0].json();
+    assert_eq!(body["relative_path"], "app/index.tsx");
+    assert_eq!(body["content"], original);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rejected_credentials_are_reported() {
+    let backend = MockBackend::start(vec![Reply::status(401)]).await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+
+    scaffold(&app, &project, "unauthorized").await.unwrap();
+
+    let last = app.last_event();
+    assert_eq!(last["type"], "unauthorized");
+    assert_eq!(last["status"], 401);
+    assert_eq!(backend.requests().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reused_run_id_keeps_the_staged_run() {
+    let backend = MockBackend::start(vec![Reply::stream(vec![
+        frame("file_write", write("app/staged.tsx", "staged\n")),
+        frame("done", done("Staged", &["app/staged.tsx"])),
+    ])])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+    let staged = || {
+        Some(RunOptions {
+            run_id: Some("staged-reuse".into()),
+            staged: true,
+            ..Default::default()
+        })
+    };
+
+    scaffold_with(&app, &project, staged()).await.unwrap();
+    let err = scaffold_with(&app, &project, staged()).await.unwrap_err();
+
+    assert!(err.contains("staged-reuse"), "{}", err);
+    let files = list_staged_files(app.handle().clone(), "staged-reuse".into())
+        .await
+        .unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, "app/staged.tsx");
+    // the duplicate never reached the backend
+    assert_eq!(backend.requests().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reused_run_id_keeps_the_undo_journal() {
+    let backend = MockBackend::start(vec![Reply::stream(vec![
+        frame("file_write", write("app/first.tsx", "first\n")),
+        frame("done", done("Done", &["app/first.tsx"])),
+    ])])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+
+    scaffold(&app, &project, "undo-reuse").await.unwrap();
+    let err = scaffold(&app, &project, "undo-reuse").await.unwrap_err();
+    assert!(err.contains("undo-reuse"), "{}", err);
+
+    let handle = app.handle();
+    let restored = undo_agent_run(handle.clone(), handle.state(), "undo-reuse".into())
+        .await
+        .unwrap();
+    assert_eq!(restored, vec!["app/first.tsx".to_string()]);
+    assert!(!project.join("app/first.tsx").exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rejected_requests_leave_the_run_id_free() {
+    let backend = MockBackend::start(vec![Reply::stream(vec![frame(
+        "done",
+        done("Nothing to do", &[]),
+    )])])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+    std::fs::create_dir_all(project.join("app")).unwrap();
+    std::fs::write(project.join("app/index.tsx"), "one\n").unwrap();
+
+    let edit = |selection: Option<serde_json::Value>| {
+        let handle = app.handle();
+        edit_project_file(
+            handle.clone(),
+            handle.state(),
+            handle.state(),
+            handle.state(),
+            options("reject-early"),
+            project.to_string_lossy().to_string(),
+            "app/index.tsx".into(),
+            "one\n".into(),
+            "Change it".into(),
+            selection.map(|s| serde_json::from_value(s).unwrap()),
+        )
+    };
+
+    let outside = json!({ "startLine": 9, "startColumn": 1, "endLine": 9, "endColumn": 2 });
+    assert!(edit(Some(outside)).await.is_err());
+    let unknown_thread = scaffold_with(
+        &app,
+        &project,
+        Some(RunOptions {
+            run_id: Some("reject-early".into()),
+            thread_id: Some("no-such-thread".into()),
+            ..Default::default()
+        }),
+    )
+    .await;
+    assert!(unknown_thread.is_err());
+    assert!(backend.requests().is_empty());
+
+    edit(None).await.unwrap();
+    assert_eq!(app.last_event()["type"], "done");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn applying_staged_changes_skips_unaccepted_files() {
+    let backend = MockBackend::start(vec![Reply::stream(vec![
+        frame("file_write", write("app/keep.tsx", "keep\n")),
+        frame("file_write", write("nested/deep/skip.tsx", "skip\n")),
+        frame(
+            "done",
+            done("Staged", &["app/keep.tsx", "nested/deep/skip.tsx"]),
+        ),
+    ])])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+    let options = Some(RunOptions {
+        run_id: Some("staged-apply".into()),
+        staged: true,
+        ..Default::default()
+    });
+
+    scaffold_with(&app, &project, options).await.unwrap();
+    let handle = app.handle();
+    accept_staged_change(
+        handle.clone(),
+        "staged-apply".into(),
+        "app/keep.tsx".into(),
+        None,
+    )
+    .await
+    .unwrap();
+    let applied = apply_staged_changes(handle.clone(), handle.state(), "staged-apply".into())
+        .await
+        .unwrap();
+
+    assert_eq!(applied, vec!["app/keep.tsx".to_string()]);
+    assert!(project.join("app/keep.tsx").is_file());
+    // a skipped file must not leave its directories behind
+    assert!(!project.join("nested").exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn overlapping_runs_on_a_thread_keep_both_turns() {
+    let slow = |name: &str| {
+        Reply::stream(vec![
+            Step::Pause(std::time::Duration::from_millis(200)),
+            frame("done", done(name, &[])),
+        ])
+    };
+    let backend = MockBackend::start(vec![
+        Reply::stream(vec![frame("done", done("First", &[]))]),
+        slow("Second"),
+        slow("Third"),
+    ])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+    let project_path = project.to_string_lossy().to_string();
+
+    scaffold(&app, &project, "thread-first").await.unwrap();
+    let handle = app.handle();
+    let thread = list_conversations(handle.clone(), project_path.clone())
+        .await
+        .unwrap()
+        .remove(0);
+    let on_thread = |run_id: &str| {
+        Some(RunOptions {
+            run_id: Some(run_id.into()),
+            thread_id: Some(thread.id.clone()),
+            ..Default::default()
+        })
+    };
+
+    let (second, third) = tokio::join!(
+        scaffold_with(&app, &project, on_thread("thread-second")),
+        scaffold_with(&app, &project, on_thread("thread-third")),
+    );
+    second.unwrap();
+    third.unwrap();
+
+    let thread = get_conversation(handle.clone(), project_path, thread.id)
+        .await
+        .unwrap();
+    let mut runs: Vec<_> = thread.turns.iter().map(|t| t.run_id.as_str()).collect();
+    runs.sort();
+    assert_eq!(runs, vec!["thread-first", "thread-second", "thread-third"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn edit_context_follows_unsaved_imports() {
+    let backend = MockBackend::start(vec![Reply::stream(vec![frame(
+        "done",
+        done("Nothing to do", &[]),
+    )])])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+    std::fs::create_dir_all(project.join("app")).unwrap();
+    std::fs::write(project.join("app/index.tsx"), "export default 1;\n").unwrap();
+    std::fs::write(project.join("app/util.ts"), "export const x = 1;\n").unwrap();
+
+    let handle = app.handle();
+    edit_project_file(
+        handle.clone(),
+        handle.state(),
+        handle.state(),
+        handle.state(),
+        options("edit-unsaved"),
+        project.to_string_lossy().to_string(),
+        "app/index.tsx".into(),
+        // the import exists only in the editor buffer
+        "import { x } from './util';\nexport default x;\n".into(),
+        "Use x".into(),
+        None,
+    )
+    .await
+    .unwrap();
+
+    let body = backend.requests()[0].json();
+    let files: Vec<&str> = body["context"]["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(files, vec!["app/util.ts"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn failed_operations_do_not_count_towards_max_files() {
+    let backend = MockBackend::start(vec![Reply::stream(vec![
+        // there is nothing to patch, so this fails
+        frame(
+            "file_patch",
+            json!({ "type": "file_patch", "path": "app/missing.tsx", "diff": "@@ -1 +1 @@\n-a\n+b\n" }),
+        ),
+        frame("file_write", write("app/a.tsx", "a\n")),
+        frame("file_write", write("app/b.tsx", "b\n")),
+        frame("done", done("Done", &["app/a.tsx"])),
+    ])])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+    set_write_policy(
+        app.handle().clone(),
+        project.to_string_lossy().to_string(),
+        WritePolicy {
+            max_files: 1,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    scaffold(&app, &project, "policy-max-files").await.unwrap();
+
+    assert!(project.join("app/a.tsx").is_file());
+    assert!(!project.join("app/b.tsx").exists());
+    let violations = app.events_of("policy_violation");
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0]["path"], "app/b.tsx");
+    assert_eq!(violations[0]["rule"], "max_files");
+}
+
+// fails while the file still says "bad", in `tsc --pretty false` form
+#[cfg(unix)]
+const CHECK_SCRIPT: &str = "if grep -q bad app/index.tsx; then\n  echo \"app/index.tsx(2,1): error TS2304: Cannot find name 'bad'.\"\n  exit 1\nfi\n";
+
+#[cfg(unix)]
+async fn edit_selection_with_check(app: &TestApp, project: &Path, run_id: &str) {
+    std::fs::create_dir_all(project.join("app")).unwrap();
+    let original = "line one\nline two\nline three\n";
+    std::fs::write(project.join("app/index.tsx"), original).unwrap();
+    std::fs::write(project.join("check.sh"), CHECK_SCRIPT).unwrap();
+
+    let handle = app.handle();
+    edit_project_file(
+        handle.clone(),
+        handle.state(),
+        handle.state(),
+        handle.state(),
+        Some(RunOptions {
+            run_id: Some(run_id.into()),
+            validate: true,
+            check_command: Some("sh check.sh".into()),
+            ..Default::default()
+        }),
+        project.to_string_lossy().to_string(),
+        "app/index.tsx".into(),
+        original.into(),
+        "Rename the second line".into(),
+        Some(
+            serde_json::from_value(json!({
+                "startLine": 2, "startColumn": 1, "endLine": 2, "endColumn": 9
+            }))
+            .unwrap(),
+        ),
+    )
+    .await
+    .unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn repairs_after_a_selection_edit_may_rewrite_the_file() {
+    let backend = MockBackend::start(vec![
+        Reply::stream(vec![
+            frame(
+                "selection_edit",
+                json!({ "type": "selection_edit", "path": "app/index.tsx", "content": "bad two" }),
+            ),
+            frame("done", done("Renamed", &["app/index.tsx"])),
+        ]),
+        // the repair rewrites the whole file, outside the selection too
+        Reply::stream(vec![
+            frame(
+                "file_write",
+                write("app/index.tsx", "line 1\nline 2\nline three\n"),
+            ),
+            frame("done", done("Fixed", &["app/index.tsx"])),
+        ]),
+    ])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+
+    edit_selection_with_check(&app, &project, "selection-repair").await;
+
+    assert_eq!(
+        std::fs::read_to_string(project.join("app/index.tsx")).unwrap(),
+        "line 1\nline 2\nline three\n"
+    );
+    assert!(app.events_of("conflict").is_empty());
+    // the repair's own `done` must not read as the end of the run
+    assert_eq!(app.events_of("done").len(), 1);
+    assert_eq!(app.events_of("repair_finished").len(), 1);
+    assert_eq!(app.last_event()["type"], "validation_finished");
+    let finished = app.events_of("validation_finished");
+    assert_eq!(finished.len(), 1);
+    assert_eq!(finished[0]["passed"], true);
+    assert_eq!(finished[0]["rounds"], 1);
+
+    let handle = app.handle();
+    let project_path = project.to_string_lossy().to_string();
+    let thread = list_conversations(handle.clone(), project_path.clone())
+        .await
+        .unwrap()
+        .remove(0);
+    let thread = get_conversation(handle.clone(), project_path, thread.id)
+        .await
+        .unwrap();
+    assert_eq!(thread.turns[0].outcome, "done");
+    assert_eq!(thread.turns[0].summary.as_deref(), Some("Renamed"));
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn failed_repair_keeps_its_outcome() {
+    let backend = MockBackend::start(vec![
+        Reply::stream(vec![
+            frame(
+                "selection_edit",
+                json!({ "type": "selection_edit", "path": "app/index.tsx", "content": "bad two" }),
+            ),
+            frame("done", done("Renamed", &["app/index.tsx"])),
+        ]),
+        Reply::stream(vec![frame(
+            "error",
+            json!({ "type": "error", "message": "Model overloaded" }),
+        )]),
+    ])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+
+    edit_selection_with_check(&app, &project, "selection-repair-fails").await;
+
+    let finished = app.events_of("validation_finished");
+    assert_eq!(finished[0]["passed"], false);
+    assert_eq!(
+        finished[0]["reason"],
+        "Repair of app/index.tsx did not finish"
+    );
+
+    let handle = app.handle();
+    let project_path = project.to_string_lossy().to_string();
+    let thread = list_conversations(handle.clone(), project_path.clone())
+        .await
+        .unwrap()
+        .remove(0);
+    let thread = get_conversation(handle.clone(), project_path, thread.id)
+        .await
+        .unwrap();
+    assert_eq!(thread.turns[0].outcome, "error");
+    assert_eq!(thread.turns[0].summary.as_deref(), Some("Model overloaded"));
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn unreachable_repair_keeps_the_turn() {
+    let backend = MockBackend::start(vec![
+        Reply::stream(vec![
+            frame(
+                "selection_edit",
+                json!({ "type": "selection_edit", "path": "app/index.tsx", "content": "bad two" }),
+            ),
+            frame("done", done("Renamed", &["app/index.tsx"])),
+        ]),
+        Reply::status(500),
+    ])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+
+    edit_selection_with_check(&app, &project, "selection-repair-500").await;
+
+    let finished = app.events_of("validation_finished");
+    assert_eq!(finished[0]["passed"], false);
+    assert!(finished[0]["reason"]
+        .as_str()
+        .unwrap()
+        .starts_with("Repair of app/index.tsx failed"));
+
+    let handle = app.handle();
+    let project_path = project.to_string_lossy().to_string();
+    let thread = list_conversations(handle.clone(), project_path.clone())
+        .await
+        .unwrap()
+        .remove(0);
+    let thread = get_conversation(handle.clone(), project_path, thread.id)
+        .await
+        .unwrap();
+    assert_eq!(thread.turns[0].outcome, "error");
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn replay_runs_every_recorded_request() {
+    let backend = MockBackend::start(vec![
+        Reply::stream(vec![
+            frame("file_write", write("app/index.tsx", "line one\nbad two\n")),
+            frame("done", done("Created", &["app/index.tsx"])),
+        ]),
+        Reply::stream(vec![
+            frame("file_write", write("app/index.tsx", "line one\nline two\n")),
+            frame("done", done("Fixed", &["app/index.tsx"])),
+        ]),
+    ])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+    std::fs::write(project.join("check.sh"), CHECK_SCRIPT).unwrap();
+
+    scaffold_with(
+        &app,
+        &project,
+        Some(RunOptions {
+            run_id: Some("replay-repair".into()),
+            validate: true,
+            check_command: Some("sh check.sh".into()),
+            ..Default::default()
+        }),
+    )
+    .await
+    .unwrap();
+
+    let scratch = temp_project().join("scratch");
+    let handle = app.handle();
+    let report = replay_agent_run(
+        handle.clone(),
+        project.to_string_lossy().to_string(),
+        "replay-repair".into(),
+        Some(scratch.to_string_lossy().to_string()),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(report.requests, 2);
+    assert_eq!(report.frames, 4);
+    assert_eq!(
+        std::fs::read_to_string(scratch.join("app/index.tsx")).unwrap(),
+        "line one\nline two\n"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn replay_feeds_the_recorded_bytes() {
+    let backend = MockBackend::start(vec![Reply::stream(vec![
+        // a field the decoder reports and a frame split across chunks
+        raw(": proxy keep-alive\ndat: typo\n\nevent: file_write\nda"),
+        raw(&format!("ta: {}\n\n", write("app/a.tsx", "a\n"))),
+        frame("done", done("Done", &["app/a.tsx"])),
+    ])])
+    .await;
+    let app = TestApp::new(&backend).await;
+    let project = temp_project();
+
+    scaffold(&app, &project, "replay-bytes").await.unwrap();
+    let unknown_field = |app: &TestApp| {
+        app.events_of("diagnostic")
+            .iter()
+            .filter(|e| {
+                e["message"]
+                    .as_str()
+                    .unwrap()
+                    .contains("unknown event stream field")
+            })
+            .count()
+    };
+    assert_eq!(unknown_field(&app), 1);
+
+    let scratch = temp_project().join("scratch");
+    let handle = app.handle();
+    let project_path = project.to_string_lossy().to_string();
+    let report = replay_agent_run(
+        handle.clone(),
+        project_path.clone(),
+        "replay-bytes".into(),
+        Some(scratch.to_string_lossy().to_string()),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(unknown_field(&app), 2);
+    assert_eq!(report.frames, 2);
+    assert!(scratch.join("app/a.tsx").is_file());
+
+    // the log belongs to the first run; a second one may not append to it
+    undo_agent_run(handle.clone(), handle.state(), "replay-bytes".into())
+        .await
+        .unwrap();
+    let reused = scaffold(&app, &project, "replay-bytes").await;
+    assert!(reused.unwrap_err().contains("run log"));
+}
@@ -0,0 +1,316 @@
+//! Test harness for the agent commands: an in-process HTTP server standing in
+//! for the agent backend, serving scripted SSE replies, and a mock-runtime app
+//! wired up the way `run()` wires the real one.
+
+#![allow(dead_code)]
+
+use coding_editor_lib::agent::handshake::BackendCache;
+use coding_editor_lib::agent::AgentRuns;
+use coding_editor_lib::config::{save_settings, AppSettings};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::test::{mock_builder, mock_context, noop_assets, MockRuntime};
+use tauri::utils::config::AppDirectoriesOverride;
+use tauri::{App, AppHandle, Listener};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// One step of a scripted reply body.
+pub enum Step {
+    // written as one chunk of the response body
+    Send(Vec<u8>),
+    Pause(Duration),
+    // drop the connection mid-body, without the closing chunk
+    Disconnect,
+}
+
+/// An SSE frame carrying `data` as JSON.
+pub fn frame(event: &str, data: Value) -> Step {
+    Step::Send(format!("event: {}\ndata: {}\n\n", event, data).into_bytes())
+}
+
+/// An SSE frame with an `id:`, so the client can resume after it.
+pub fn frame_with_id(id: &str, event: &str, data: Value) -> Step {
+    Step::Send(format!("id: {}\nevent: {}\ndata: {}\n\n", id, event, data).into_bytes())
+}
+
+/// Bytes sent exactly as given.
+pub fn raw(text: &str) -> Step {
+    Step::Send(text.as_bytes().to_vec())
+}
+
+/// What the server sends back for one agent request.
+pub struct Reply {
+    status: u16,
+    steps: Vec<Step>,
+}
+
+impl Reply {
+    pub fn stream(steps: Vec<Step>) -> Self {
+        Self { status: 200, steps }
+    }
+
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            steps: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    // lower-cased names
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_lowercase()).map(String::as_str)
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).expect("request body is JSON")
+    }
+}
+
+struct Shared {
+    replies: Mutex<VecDeque<Reply>>,
+    requests: Mutex<Vec<RecordedRequest>>,
+}
+
+/// Serves `GET /capabilities` as a legacy backend (404) and answers every
+/// other request with the next scripted reply, one reply per connection.
+pub struct MockBackend {
+    url: String,
+    shared: Arc<Shared>,
+}
+
+impl MockBackend {
+    pub async fn start(replies: Vec<Reply>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock backend");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let shared = Arc::new(Shared {
+            replies: Mutex::new(replies.into()),
+            requests: Mutex::new(Vec::new()),
+        });
+
+        let state = shared.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(serve(socket, state.clone()));
+            }
+        });
+
+        Self { url, shared }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Agent requests received so far, without the capability probes.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.shared
+            .requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| r.path != "/capabilities")
+            .cloned()
+            .collect()
+    }
+}
+
+async fn read_request(socket: &mut TcpStream) -> Option<RecordedRequest> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+
+    let head_end = loop {
+        let n = socket.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let length: usize = headers
+        .get("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    let mut body = buf[head_end..].to_vec();
+    while body.len() < length {
+        let n = socket.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+
+    Some(RecordedRequest {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+async fn serve(mut socket: TcpStream, shared: Arc<Shared>) {
+    let Some(request) = read_request(&mut socket).await else {
+        return;
+    };
+    let is_probe = request.path == "/capabilities";
+    shared.requests.lock().unwrap().push(request);
+
+    let reply = if is_probe {
+        Reply::status(404)
+    } else {
+        shared
+            .replies
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or_else(|| Reply::status(500))
+    };
+
+    if reply.status != 200 {
+        let head = format!(
+            "HTTP/1.1 {} Mock\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+            reply.status
+        );
+        let _ = socket.write_all(head.as_bytes()).await;
+        return;
+    }
+
+    let head = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\
+                transfer-encoding: chunked\r\nconnection: close\r\n\r\n";
+    if socket.write_all(head.as_bytes()).await.is_err() {
+        return;
+    }
+
+    for step in reply.steps {
+        match step {
+            Step::Send(bytes) => {
+                let mut out = format!("{:x}\r\n", bytes.len()).into_bytes();
+                out.extend_from_slice(&bytes);
+                out.extend_from_slice(b"\r\n");
+                if socket.write_all(&out).await.is_err() {
+                    return;
+                }
+                let _ = socket.flush().await;
+            }
+            Step::Pause(duration) => tokio::time::sleep(duration).await,
+            Step::Disconnect => return,
+        }
+    }
+
+    let _ = socket.write_all(b"0\r\n\r\n").await;
+}
+
+/// An empty project directory.
+pub fn temp_project() -> PathBuf {
+    let dir = std::env::temp_dir()
+        .join("coding-editor-test-projects")
+        .join(uuid::Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.canonicalize().unwrap()
+}
+
+/// A mock-runtime app pointed at `backend`, collecting every `agent_event`.
+pub struct TestApp {
+    app: App<MockRuntime>,
+    events: Arc<Mutex<Vec<Value>>>,
+    data_dir: PathBuf,
+}
+
+impl TestApp {
+    pub async fn new(backend: &MockBackend) -> Self {
+        // app data (conversations, run logs, snapshots) goes to a directory of
+        // its own, out of the real profile and apart from concurrent tests
+        let data_dir = std::env::temp_dir()
+            .join("coding-editor-test-data")
+            .join(uuid::Uuid::new_v4().to_string());
+        let mut context = mock_context(noop_assets());
+        context.config_mut().app.app_directories_override =
+            Some(AppDirectoriesOverride::Root(data_dir.clone()));
+
+        let app = mock_builder()
+            .plugin(tauri_plugin_store::Builder::new().build())
+            .manage(reqwest::Client::new())
+            .manage(AgentRuns::default())
+            .manage(BackendCache::default())
+            .build(context)
+            .expect("build mock app");
+
+        save_settings(
+            app.handle().clone(),
+            AppSettings {
+                username: "tester".into(),
+                api_url: backend.url().to_string(),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("save settings");
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        app.handle().listen_any("agent_event", move |event| {
+            let value: Value = serde_json::from_str(event.payload()).unwrap();
+            sink.lock().unwrap().push(value);
+        });
+
+        Self {
+            app,
+            events,
+            data_dir,
+        }
+    }
+
+    pub fn handle(&self) -> &AppHandle<MockRuntime> {
+        self.app.handle()
+    }
+
+    pub fn events(&self) -> Vec<Value> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// Events of one type, in order.
+    pub fn events_of(&self, kind: &str) -> Vec<Value> {
+        self.events()
+            .into_iter()
+            .filter(|e| e["type"] == kind)
+            .collect()
+    }
+
+    pub fn last_event(&self) -> Value {
+        self.events().pop().expect("no events emitted")
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.data_dir);
+    }
+}
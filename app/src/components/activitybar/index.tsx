@@ -1,4 +1,4 @@
-import { ArrowRightSquareIcon, BubblesIcon, File, Settings } from "lucide-react";
+import { ArrowRightSquareIcon, BubblesIcon, File, Search, Settings } from "lucide-react";
 import { SettingsModal } from "../settings";
 import { useState } from "react";
 
@@ -11,6 +11,7 @@ interface ActivityBarProps {
 
 const navItems = [
   { icon: <File size={18} />, label: "Explorer", name: "explorer" },
+  { icon: <Search size={18} />, label: "Search", name: "search" },
   { icon: <BubblesIcon size={18} />, label: "Collab", name: "collab" },
 ];
 
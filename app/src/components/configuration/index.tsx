@@ -151,6 +151,16 @@ export const ConfigurationComponent = () => {
 				aws_secret_access_key: values.aws_secret_access_key ?? '',
 				aws_region: values.aws_region ?? '',
 				aws_bucket: values.aws_bucket ?? '',
+				// set later from Settings when the backend needs one
+				api_token: '',
+				api_auth_header: '',
+				agent_provider: 'backend',
+				openai_base_url: '',
+				openai_model: '',
+				openai_api_key: '',
+				model: '',
+				temperature: null,
+				max_tokens: null,
 			});
 
 			navigate('/');
@@ -13,6 +13,8 @@ import { useShallow } from 'zustand/react/shallow';
 import { useCollab } from '@/store/collab';
 import { getYdoc } from '@/store/collab/helpers/session';
 import { observeFile } from '@/store/collab/helpers/observe';
+import { isTauri } from '@/store';
+import { watchProject } from '@/service';
 
 export const EditorComponent = () => {
 	const navigate = useNavigate();
@@ -26,18 +28,21 @@ export const EditorComponent = () => {
 
 	// App store — NO object selectors, only primitives
 	const hasProject = useAppStore((s) => !!s.currentProject);
+	const projectPath = useAppStore((s) => s.currentProject?.path);
 	const activeFilePath = useAppStore((s) => s.activeFile?.path);
 	const activeFileContent = useAppStore((s) => s.activeFile?.content);
 	const activeFileLanguage = useAppStore((s) =>
 		s.activeFile ? inferLanguage(s.activeFile.path) : undefined,
 	);
 	const openTabs = useAppStore(useShallow((s) => s.openTabs));
+	const stalePaths = useAppStore((s) => s.stalePaths);
 
 	// Actions — stable references, safe to select directly
 	const openFile = useAppStore((s) => s.openFile);
 	const closeTab = useAppStore((s) => s.closeTab);
 	const updateFileContent = useAppStore((s) => s.updateFileContent);
 	const loadFileTree = useAppStore((s) => s.loadFileTree);
+	const applyFsChanges = useAppStore((s) => s.applyFsChanges);
 
 	const isCollabActive = status === 'active';
 	const collabYdoc = isCollabActive ? getYdoc() : undefined;
@@ -55,6 +60,25 @@ export const EditorComponent = () => {
 		}
 	}, [hasProject]);
 
+	// Pick up changes made outside the editor
+	useEffect(() => {
+		if (!projectPath || !isTauri()) return;
+
+		let stop: (() => void) | undefined;
+		let cancelled = false;
+		watchProject(projectPath, applyFsChanges)
+			.then((unwatch) => {
+				if (cancelled) unwatch();
+				else stop = unwatch;
+			})
+			.catch(console.error);
+
+		return () => {
+			cancelled = true;
+			stop?.();
+		};
+	}, [projectPath]);
+
 	// Keep awareness in sync when active file changes during collab
 	useEffect(() => {
 		if (!isCollabActive) return;
@@ -93,6 +117,7 @@ export const EditorComponent = () => {
 							<TabBar
 								tabs={openTabs}
 								activeTab={activeFilePath || ''}
+								staleTabs={stalePaths}
 								onSelectTab={(path) => openFile(path)}
 								onCloseTab={closeTab}
 							/>
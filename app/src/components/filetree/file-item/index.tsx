@@ -50,7 +50,8 @@ export const FileItem = ({
 			role="button"
 			onClick={() => onSelect(item.path)}
 			className={`w-full cursor-pointer flex items-center gap-2 py-0.75 px-2 rounded-md transition-all duration-100 group
-        ${isActive ? 'bg-white/[0.07] text-neutral-100' : 'text-neutral-500 hover:text-neutral-300 hover:bg-white/3'}`}
+        ${isActive ? 'bg-white/[0.07] text-neutral-100' : 'text-neutral-500 hover:text-neutral-300 hover:bg-white/3'}
+        ${item.ignored ? 'opacity-45' : ''}`}
 			style={{ paddingLeft: `${depth * 14 + 22}px` }}
 		>
 			<FileIcn size={14} className="shrink-0" style={{ color }} />
@@ -1,13 +1,14 @@
 import { ChevronRight, FilePlus, Folder, 
   FolderOpen, FolderPlus, 
   MoreHorizontal, Pencil, Trash2 } from "lucide-react";
-import { useState } from "react";
+import { useEffect, useState } from "react";
 import { RenameInput } from "../rename-input";
 import { DropdownMenu, DropdownMenuContent, DropdownMenuItem, 
   DropdownMenuSeparator, DropdownMenuTrigger } from "@/components/ui/dropdown-menu";
 import { InlineInput } from "../inline-input";
 import { FileTreeItem } from "../file-tree-item";
 import { FileTreeItemProps } from "..";
+import { useAppStore } from "@/store";
 
 export const FolderItem = ({
   item,
@@ -21,6 +22,16 @@ export const FolderItem = ({
   const [open, setOpen] = useState(item.open ?? false);
   const [pending, setPending] = useState<"file" | "folder" | null>(null);
   const [renaming, setRenaming] = useState(false);
+  const loadDirectory = useAppStore((s) => s.loadDirectory);
+
+  // children are listed the first time the folder opens
+  useEffect(() => {
+    if (open && !item.children) loadDirectory(item.path);
+  }, [open]);
+
+  const remaining = item.children && item.child_count !== undefined
+    ? item.child_count - item.children.length
+    : 0;
 
   const startCreate = (type: "file" | "folder") => {
     setOpen(true);
@@ -60,7 +71,7 @@ export const FolderItem = ({
         <div
           role="button"
           onClick={() => setOpen((o) => !o)}
-          className="w-full cursor-pointer flex items-center gap-1.5 py-0.75 px-2 hover:bg-white/4 transition-colors rounded-md group"
+          className={`w-full cursor-pointer flex items-center gap-1.5 py-0.75 px-2 hover:bg-white/4 transition-colors rounded-md group ${item.ignored ? "opacity-45" : ""}`}
           style={{ paddingLeft: `${depth * 14 + 8}px` }}
         >
           <ChevronRight
@@ -133,9 +144,9 @@ export const FolderItem = ({
               onDone={handleDone}
             />
           )}
-          {item.children?.map((child, i) => (
+          {item.children?.map((child) => (
             <FileTreeItem
-              key={i}
+              key={child.path}
               item={child}
               depth={depth + 1}
               activeFile={activeFile}
@@ -145,6 +156,15 @@ export const FolderItem = ({
               onRefresh={onRefresh}
             />
           ))}
+          {remaining > 0 && (
+            <button
+              onClick={() => loadDirectory(item.path, true)}
+              className="w-full cursor-pointer text-left py-0.75 px-2 rounded-md text-[11.5px] text-neutral-600 hover:text-neutral-400 hover:bg-white/3 transition-colors"
+              style={{ paddingLeft: `${(depth + 1) * 14 + 22}px` }}
+            >
+              Show more ({remaining} remaining)
+            </button>
+          )}
         </>
       )}
     </div>
@@ -59,8 +59,8 @@ interface FileTreeProps {
 
 export const FileTree = (props: FileTreeProps) => (
   <div className="flex-1 overflow-y-auto py-2 px-1.5">
-    {props.tree.map((item, i) => (
-      <FileTreeItem key={i} item={item} {...props} />
+    {props.tree.map((item) => (
+      <FileTreeItem key={item.path} item={item} {...props} />
     ))}
   </div>
 );
\ No newline at end of file
@@ -21,8 +21,15 @@ import {
 	Check,
 	AlertCircle,
 	Settings,
+	RefreshCw,
 } from 'lucide-react';
-import { useSettings } from '@/store/settings';
+import {
+	BackendStatus,
+	checkBackend,
+	listModels,
+	ModelInfo,
+	useSettings,
+} from '@/store/settings';
 
 const schema = z
 	.object({
@@ -43,8 +50,53 @@ const schema = z
 		aws_secret_access_key: z.string().optional().or(z.literal('')),
 		aws_region: z.string().optional().or(z.literal('')),
 		aws_bucket: z.string().optional().or(z.literal('')),
+		api_token: z.string().optional().or(z.literal('')),
+		api_auth_header: z
+			.string()
+			.regex(/^[A-Za-z0-9-]*$/, 'Letters, numbers and - only')
+			.optional()
+			.or(z.literal('')),
+		agent_provider: z.enum(['backend', 'openai']),
+		openai_base_url: z
+			.string()
+			.refine((v) => v === '' || z.string().url().safeParse(v).success, {
+				message: 'Must be a valid URL if provided',
+			})
+			.optional()
+			.or(z.literal('')),
+		openai_model: z.string().optional().or(z.literal('')),
+		openai_api_key: z.string().optional().or(z.literal('')),
+		model: z.string().optional().or(z.literal('')),
+		temperature: z
+			.string()
+			.refine(
+				(v) => v === '' || (Number(v) >= 0 && Number(v) <= 2),
+				{ message: 'Between 0 and 2' },
+			)
+			.optional()
+			.or(z.literal('')),
+		max_tokens: z
+			.string()
+			.regex(/^([1-9][0-9]*)?$/, 'A positive whole number')
+			.optional()
+			.or(z.literal('')),
 	})
 	.superRefine((data, ctx) => {
+		if (data.agent_provider === 'openai') {
+			if (!data.openai_base_url?.trim())
+				ctx.addIssue({
+					path: ['openai_base_url'],
+					code: 'custom',
+					message: 'Required for the OpenAI-compatible provider',
+				});
+			if (!data.openai_model?.trim())
+				ctx.addIssue({
+					path: ['openai_model'],
+					code: 'custom',
+					message: 'Required for the OpenAI-compatible provider',
+				});
+		}
+
 		const aws = [
 			data.aws_access_key_id,
 			data.aws_secret_access_key,
@@ -161,6 +213,13 @@ export const SettingsModal = ({
 	const { settings, save, saving, load, loading } = useSettings();
 	const [tab, setTab] = useState<Tab>(initialTab);
 	const [saved, setSaved] = useState(false);
+	const [testing, setTesting] = useState(false);
+	const [backendStatus, setBackendStatus] = useState<BackendStatus | null>(
+		null,
+	);
+	const [models, setModels] = useState<ModelInfo[]>([]);
+	const [listingModels, setListingModels] = useState(false);
+	const [modelsError, setModelsError] = useState('');
 
 	const {
 		register,
@@ -179,6 +238,15 @@ export const SettingsModal = ({
 			aws_secret_access_key: '',
 			aws_region: '',
 			aws_bucket: '',
+			api_token: '',
+			api_auth_header: '',
+			agent_provider: 'backend',
+			openai_base_url: '',
+			openai_model: '',
+			openai_api_key: '',
+			model: '',
+			temperature: '',
+			max_tokens: '',
 		},
 	});
 
@@ -196,21 +264,73 @@ export const SettingsModal = ({
 						aws_secret_access_key: settings.aws_secret_access_key,
 						aws_region: settings.aws_region,
 						aws_bucket: settings.aws_bucket,
+						api_token: settings.api_token,
+						api_auth_header: settings.api_auth_header,
+						agent_provider: settings.agent_provider || 'backend',
+						openai_base_url: settings.openai_base_url,
+						openai_model: settings.openai_model,
+						openai_api_key: settings.openai_api_key,
+						model: settings.model ?? '',
+						temperature: settings.temperature?.toString() ?? '',
+						max_tokens: settings.max_tokens?.toString() ?? '',
 					});
 			});
 		}
 	}, [open]);
 
+	const toSettings = (values: FormValues) => ({
+		username: values.username,
+		api_url: values.api_url,
+		ws_url: values.ws_url ?? '',
+		aws_access_key_id: values.aws_access_key_id ?? '',
+		aws_secret_access_key: values.aws_secret_access_key ?? '',
+		aws_region: values.aws_region ?? '',
+		aws_bucket: values.aws_bucket ?? '',
+		api_token: values.api_token ?? '',
+		api_auth_header: values.api_auth_header ?? '',
+		agent_provider: values.agent_provider,
+		openai_base_url: values.openai_base_url ?? '',
+		openai_model: values.openai_model ?? '',
+		openai_api_key: values.openai_api_key ?? '',
+		model: values.model?.trim() ?? '',
+		temperature: values.temperature ? Number(values.temperature) : null,
+		max_tokens: values.max_tokens ? Number(values.max_tokens) : null,
+	});
+
+	const onListModels = async (values: FormValues) => {
+		setListingModels(true);
+		setModelsError('');
+		try {
+			setModels(await listModels(toSettings(values)));
+		} catch (e: any) {
+			setModels([]);
+			setModelsError(e?.toString() ?? 'Failed to list models');
+		} finally {
+			setListingModels(false);
+		}
+	};
+
+	const onTest = async (values: FormValues) => {
+		setTesting(true);
+		setBackendStatus(null);
+		try {
+			setBackendStatus(await checkBackend(toSettings(values)));
+		} catch (e: any) {
+			setBackendStatus({
+				reachable: false,
+				latency_ms: null,
+				protocol_version: null,
+				server_version: null,
+				compatible: false,
+				message: e?.toString() ?? 'Check failed',
+			});
+		} finally {
+			setTesting(false);
+		}
+	};
+
 	const onSubmit = async (values: FormValues) => {
-		await save({
-			username: values.username,
-			api_url: values.api_url,
-			ws_url: values.ws_url ?? '',
-			aws_access_key_id: values.aws_access_key_id ?? '',
-			aws_secret_access_key: values.aws_secret_access_key ?? '',
-			aws_region: values.aws_region ?? '',
-			aws_bucket: values.aws_bucket ?? '',
-		});
+		await save(toSettings(values));
 		setSaved(true);
 		setTimeout(() => {
 			setSaved(false);
@@ -338,6 +458,199 @@ export const SettingsModal = ({
 										message={errors.ws_url?.message}
 									/>
 								</div>
+								<div>
+									<Label>
+										API Token{' '}
+										<span className="text-neutral-700 normal-case tracking-normal font-normal">
+											optional · encrypted
+										</span>
+									</Label>
+									<SecretInput
+										value={watch('api_token') ?? ''}
+										onChange={(v) => setValue('api_token', v)}
+										placeholder="Sent with every agent request"
+										hasError={!!errors.api_token}
+									/>
+									<FieldError
+										message={errors.api_token?.message}
+									/>
+								</div>
+								<div>
+									<Label>
+										Auth Header{' '}
+										<span className="text-neutral-700 normal-case tracking-normal font-normal">
+											optional
+										</span>
+									</Label>
+									<div className="relative">
+										<KeyRound
+											size={13}
+											className="absolute left-3 top-1/2 -translate-y-1/2 text-neutral-600 pointer-events-none"
+										/>
+										<input
+											{...register('api_auth_header')}
+											autoCapitalize="none"
+											autoCorrect="off"
+											autoComplete="off"
+											spellCheck={false}
+											placeholder="Authorization: Bearer"
+											className={`${inputCls(!!errors.api_auth_header)} pl-8 font-mono`}
+										/>
+									</div>
+									<FieldError
+										message={errors.api_auth_header?.message}
+									/>
+								</div>
+								<div>
+									<Label>Agent Provider</Label>
+									<select
+										{...register('agent_provider')}
+										className={`${inputCls(false)} cursor-pointer`}
+									>
+										<option value="backend">
+											Agent backend (API server)
+										</option>
+										<option value="openai">
+											OpenAI-compatible endpoint
+										</option>
+									</select>
+								</div>
+								{watch('agent_provider') === 'openai' && (
+									<>
+										<div>
+											<Label>Endpoint Base URL</Label>
+											<input
+												{...register('openai_base_url')}
+												autoCapitalize="none"
+												autoCorrect="off"
+												autoComplete="off"
+												spellCheck={false}
+												placeholder="http://localhost:11434/v1"
+												className={`${inputCls(!!errors.openai_base_url)} font-mono`}
+											/>
+											<FieldError
+												message={errors.openai_base_url?.message}
+											/>
+										</div>
+										<div className="grid grid-cols-2 gap-3">
+											<div>
+												<Label>Model</Label>
+												<input
+													{...register('openai_model')}
+													autoCapitalize="none"
+													autoCorrect="off"
+													autoComplete="off"
+													spellCheck={false}
+													placeholder="qwen2.5-coder:14b"
+													className={`${inputCls(!!errors.openai_model)} font-mono`}
+												/>
+												<FieldError
+													message={errors.openai_model?.message}
+												/>
+											</div>
+											<div>
+												<Label>API Key</Label>
+												<SecretInput
+													value={watch('openai_api_key') ?? ''}
+													onChange={(v) =>
+														setValue('openai_api_key', v)
+													}
+													placeholder="optional"
+													hasError={!!errors.openai_api_key}
+												/>
+											</div>
+										</div>
+									</>
+								)}
+								<div>
+									<Label>Default Model</Label>
+									<div className="flex gap-2">
+										<input
+											{...register('model')}
+											list="settings-models"
+											autoCapitalize="none"
+											autoCorrect="off"
+											autoComplete="off"
+											spellCheck={false}
+											placeholder="provider default"
+											className={`${inputCls(!!errors.model)} font-mono`}
+										/>
+										<datalist id="settings-models">
+											{models.map((m) => (
+												<option key={m.id} value={m.id}>
+													{m.name ?? m.id}
+												</option>
+											))}
+										</datalist>
+										<button
+											type="button"
+											title="Fetch available models"
+											onClick={handleSubmit(onListModels)}
+											disabled={listingModels}
+											className="flex shrink-0 cursor-pointer items-center px-2.5 rounded-lg text-neutral-400 border border-white/[0.07] hover:text-neutral-200 hover:border-white/20 disabled:opacity-40 transition-colors"
+										>
+											<RefreshCw
+												size={12}
+												className={listingModels ? 'animate-spin' : ''}
+											/>
+										</button>
+									</div>
+									<FieldError message={errors.model?.message || modelsError} />
+								</div>
+								<div className="grid grid-cols-2 gap-3">
+									<div>
+										<Label>Temperature</Label>
+										<input
+											{...register('temperature')}
+											inputMode="decimal"
+											placeholder="default"
+											className={`${inputCls(!!errors.temperature)} font-mono`}
+										/>
+										<FieldError message={errors.temperature?.message} />
+									</div>
+									<div>
+										<Label>Max Tokens</Label>
+										<input
+											{...register('max_tokens')}
+											inputMode="numeric"
+											placeholder="default"
+											className={`${inputCls(!!errors.max_tokens)} font-mono`}
+										/>
+										<FieldError message={errors.max_tokens?.message} />
+									</div>
+								</div>
+								<div className="flex items-start gap-3">
+									<button
+										type="button"
+										onClick={handleSubmit(onTest)}
+										disabled={testing}
+										className="flex shrink-0 cursor-pointer items-center gap-1.5 px-3 py-1.5 rounded-lg text-[11.5px] text-neutral-400 border border-white/[0.07] hover:text-neutral-200 hover:border-white/20 disabled:opacity-40 transition-colors"
+									>
+										{testing ? (
+											<Loader2 size={12} className="animate-spin" />
+										) : (
+											<Server size={12} />
+										)}
+										Test connection
+									</button>
+									{backendStatus && (
+										<p
+											className={`text-[10.5px] leading-relaxed mt-1 ${
+												backendStatus.compatible
+													? 'text-emerald-400/90'
+													: 'text-red-400/90'
+											}`}
+										>
+											{backendStatus.message}
+											{backendStatus.latency_ms !== null &&
+												` · ${backendStatus.latency_ms} ms`}
+											{backendStatus.server_version &&
+												` · v${backendStatus.server_version}`}
+											{backendStatus.protocol_version !== null &&
+												` · protocol ${backendStatus.protocol_version}`}
+										</p>
+									)}
+								</div>
 							</>
 						)}
 
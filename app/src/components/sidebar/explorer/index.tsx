@@ -8,6 +8,8 @@ import {
 	MoreHorizontal,
 	Terminal,
 	FolderOpen,
+	Eye,
+	EyeOff,
 } from 'lucide-react';
 import {
 	DropdownMenu,
@@ -21,8 +23,14 @@ import { ConfirmDialog, DialogState } from './confirm-dialog';
 import { InputState, RootInlineInput } from './inline-input';
 
 export const Explorer = () => {
-	const { currentProject, openFile, activeFile, loadFileTree } =
-		useAppStore();
+	const {
+		currentProject,
+		openFile,
+		activeFile,
+		loadFileTree,
+		showIgnored,
+		setShowIgnored,
+	} = useAppStore();
 	const { settings } = useSettings();
 
 	const [dialog, setDialog] = useState<DialogState>({
@@ -186,6 +194,26 @@ export const Explorer = () => {
 									/>
 									Open Project Folder
 								</DropdownMenuItem>
+								<DropdownMenuSeparator className="bg-white/6" />
+								<DropdownMenuItem
+									onClick={() => setShowIgnored(!showIgnored)}
+									className="text-[11.5px] gap-2.5 cursor-pointer focus:bg-white/6 focus:text-neutral-100"
+								>
+									{showIgnored ? (
+										<EyeOff
+											size={12}
+											className="text-neutral-400"
+										/>
+									) : (
+										<Eye
+											size={12}
+											className="text-neutral-400"
+										/>
+									)}
+									{showIgnored
+										? 'Hide Ignored Files'
+										: 'Show Ignored Files'}
+								</DropdownMenuItem>
 							</DropdownMenuContent>
 						</DropdownMenu>
 					</div>
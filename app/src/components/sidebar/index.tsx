@@ -1,6 +1,7 @@
 import { useSettings } from "@/store/settings";
 import { Collab } from "./collab";
 import { Explorer } from "./explorer";
+import { Search } from "./search";
 import { useAppStore } from "@/store";
 
 type SidebarProps = {
@@ -13,6 +14,7 @@ export const Sidebar = ({ activityTabItem }: SidebarProps) => {
   return (
     <div className="w-full h-full flex flex-col bg-[#141416] border-r border-white/6 shrink-0">
       {activityTabItem === "explorer" && <Explorer />}
+      {activityTabItem === "search" && <Search />}
       {activityTabItem === "collab" && <Collab 
         workspaceCode={currentProject!.id.toUpperCase()}
         username={settings!.username}
@@ -0,0 +1,442 @@
+import { useRef, useState } from 'react';
+import {
+	CaseSensitive,
+	ChevronRight,
+	Loader2,
+	Regex,
+	ReplaceAll,
+	Square,
+	WholeWord,
+} from 'lucide-react';
+import { toast } from 'sonner';
+import { useAppStore } from '@/store';
+import { applyReplace, previewReplace, searchProject, undoReplace } from '@/service';
+import { RunningSearch } from '@/service/search-service';
+import {
+	FileMatches,
+	FileReplacements,
+	ReplacePreview,
+	SearchMatch,
+	SearchSpec,
+	SearchSummary,
+} from '@/models';
+import { getFileIcon } from '@/components/filetree';
+import { ReplacePreviewList } from './replace-preview';
+
+const splitGlobs = (value: string) =>
+	value
+		.split(',')
+		.map((g) => g.trim())
+		.filter(Boolean);
+
+const ToggleButton = ({
+	active,
+	title,
+	onClick,
+	children,
+}: {
+	active: boolean;
+	title: string;
+	onClick: () => void;
+	children: React.ReactNode;
+}) => (
+	<button
+		onClick={onClick}
+		title={title}
+		className={`w-5 h-5 cursor-pointer flex items-center justify-center rounded transition-colors shrink-0
+			${active ? 'text-amber-400 bg-amber-400/10' : 'text-neutral-600 hover:text-neutral-400 hover:bg-white/6'}`}
+	>
+		{children}
+	</button>
+);
+
+const MatchLine = ({ match }: { match: SearchMatch }) => {
+	// columns are 1-based character offsets
+	const chars = Array.from(match.text);
+	const start = match.column - 1;
+	const end = start + match.length;
+	// keep the match in view on long lines
+	const lead = Math.max(0, start - 24);
+
+	return (
+		<span className="truncate">
+			{lead > 0 && '…'}
+			{chars.slice(lead, start).join('')}
+			<span className="bg-amber-400/25 text-amber-200 rounded-sm">
+				{chars.slice(start, end).join('')}
+			</span>
+			{chars.slice(end).join('')}
+		</span>
+	);
+};
+
+export const Search = () => {
+	const { currentProject, openFile } = useAppStore();
+
+	const [query, setQuery] = useState('');
+	const [caseSensitive, setCaseSensitive] = useState(false);
+	const [wholeWord, setWholeWord] = useState(false);
+	const [regex, setRegex] = useState(false);
+	const [include, setInclude] = useState('');
+	const [exclude, setExclude] = useState('');
+	const [showReplace, setShowReplace] = useState(false);
+	const [replacement, setReplacement] = useState('');
+
+	const [results, setResults] = useState<FileMatches[]>([]);
+	const [collapsed, setCollapsed] = useState<Set<string>>(new Set());
+	const [summary, setSummary] = useState<SearchSummary | null>(null);
+	const [error, setError] = useState('');
+	const [running, setRunning] = useState(false);
+	const active = useRef<RunningSearch | null>(null);
+	// results from a superseded search are dropped
+	const generation = useRef(0);
+
+	// the spec a preview was made with, so applying replaces the same matches
+	const [preview, setPreview] = useState<{ spec: SearchSpec; replacement: string; result: ReplacePreview } | null>(null);
+	const [selected, setSelected] = useState<Record<string, Set<number>>>({});
+	const [applying, setApplying] = useState(false);
+
+	const currentSpec = (): SearchSpec => ({
+		query,
+		regex,
+		caseSensitive,
+		wholeWord,
+		include: splitGlobs(include),
+		exclude: splitGlobs(exclude),
+	});
+
+	const runSearch = async () => {
+		if (!currentProject || !query) return;
+
+		active.current?.cancel();
+		active.current = null;
+		const current = ++generation.current;
+		setResults([]);
+		setCollapsed(new Set());
+		setSummary(null);
+		setPreview(null);
+		setError('');
+		setRunning(true);
+
+		try {
+			const search = await searchProject(
+				currentProject.path,
+				currentSpec(),
+				(file) => {
+					if (generation.current !== current) return;
+					setResults((r) => [...r, file]);
+				},
+			);
+			active.current = search;
+
+			const result = await search.done;
+			if (generation.current !== current) return;
+			setSummary(result);
+			setRunning(false);
+		} catch (err: any) {
+			if (generation.current !== current) return;
+			setError(err?.toString() ?? 'Search failed');
+			setRunning(false);
+		}
+	};
+
+	const runPreview = async () => {
+		if (!currentProject || !query) return;
+
+		active.current?.cancel();
+		active.current = null;
+		const current = ++generation.current;
+		setCollapsed(new Set());
+		setError('');
+		setRunning(true);
+
+		try {
+			const spec = currentSpec();
+			const result = await previewReplace(currentProject.path, spec, replacement);
+			if (generation.current !== current) return;
+			setPreview({ spec, replacement, result });
+			setSelected(
+				Object.fromEntries(
+					result.files.map((f) => [f.path, new Set(f.replacements.map((r) => r.index))]),
+				),
+			);
+		} catch (err: any) {
+			if (generation.current !== current) return;
+			setError(err?.toString() ?? 'Preview failed');
+		} finally {
+			if (generation.current === current) setRunning(false);
+		}
+	};
+
+	const runReplace = async () => {
+		if (!currentProject || !preview) return;
+
+		const selection = preview.result.files
+			.filter((f) => selected[f.path]?.size)
+			.map((f) => ({
+				path: f.path,
+				hash: f.hash,
+				indices:
+					selected[f.path].size === f.replacements.length
+						? undefined
+						: [...selected[f.path]],
+			}));
+		if (selection.length === 0) return;
+
+		setApplying(true);
+		try {
+			const result = await applyReplace(
+				currentProject.path,
+				preview.spec,
+				preview.replacement,
+				selection,
+			);
+			setPreview(null);
+			toast.success(
+				`Replaced ${result.replacements} occurrence${result.replacements === 1 ? '' : 's'} in ${result.files.length} file${result.files.length === 1 ? '' : 's'}`,
+				{
+					position: 'top-center',
+					action: {
+						label: 'Undo',
+						onClick: () => {
+							undoReplace(result.undo_id).catch((err) =>
+								toast.error(err, { position: 'top-center' }),
+							);
+						},
+					},
+				},
+			);
+			runSearch();
+		} catch (err: any) {
+			toast.error(err, { position: 'top-center' });
+		} finally {
+			setApplying(false);
+		}
+	};
+
+	const toggleReplaceFile = (file: FileReplacements) =>
+		setSelected((s) => ({
+			...s,
+			[file.path]:
+				s[file.path]?.size === file.replacements.length
+					? new Set()
+					: new Set(file.replacements.map((r) => r.index)),
+		}));
+
+	const toggleReplacement = (path: string, index: number) =>
+		setSelected((s) => {
+			const next = new Set(s[path]);
+			if (next.has(index)) next.delete(index);
+			else next.add(index);
+			return { ...s, [path]: next };
+		});
+
+	const selectedCount = Object.values(selected).reduce((n, s) => n + s.size, 0);
+
+	const toggleFile = (path: string) =>
+		setCollapsed((c) => {
+			const next = new Set(c);
+			if (next.has(path)) next.delete(path);
+			else next.add(path);
+			return next;
+		});
+
+	const inputClass =
+		'w-full bg-white/3 border border-white/6 focus:border-amber-400/30 rounded-md px-2 py-1 text-[11.5px] text-neutral-200 placeholder-neutral-600 outline-none';
+
+	return (
+		<div className="w-full h-full flex flex-col bg-[#141416] border-r border-white/6 shrink-0 select-none">
+			{/* ── Header ── */}
+			<div className="h-9.5 flex items-center justify-between px-3 border-b border-white/4">
+				<span className="text-[10.5px] font-semibold tracking-[0.12em] uppercase text-neutral-500">
+					Search
+				</span>
+				<div className="flex items-center gap-0.5">
+					{running && (
+						<button
+							onClick={() => active.current?.cancel()}
+							title="Stop search"
+							className="w-5 h-5 cursor-pointer flex items-center justify-center rounded text-neutral-600 hover:text-neutral-400 hover:bg-white/6 transition-colors"
+						>
+							<Square size={11} />
+						</button>
+					)}
+					<ToggleButton
+						active={showReplace}
+						title="Toggle Replace"
+						onClick={() => {
+							setShowReplace((v) => !v);
+							setPreview(null);
+						}}
+					>
+						<ReplaceAll size={13} />
+					</ToggleButton>
+				</div>
+			</div>
+
+			{/* ── Query ── */}
+			<div className="px-2 pt-2 pb-1.5 flex flex-col gap-1.5 border-b border-white/4">
+				<div className="flex items-center gap-0.5 bg-white/3 border border-white/6 focus-within:border-amber-400/30 rounded-md pl-2 pr-1 py-0.5">
+					<input
+						autoFocus
+						value={query}
+						onChange={(e) => setQuery(e.target.value)}
+						onKeyDown={(e) => e.key === 'Enter' && runSearch()}
+						placeholder="Search"
+						className="flex-1 bg-transparent text-[11.5px] text-neutral-200 placeholder-neutral-600 outline-none min-w-0"
+					/>
+					<ToggleButton
+						active={caseSensitive}
+						title="Match Case"
+						onClick={() => setCaseSensitive((v) => !v)}
+					>
+						<CaseSensitive size={13} />
+					</ToggleButton>
+					<ToggleButton
+						active={wholeWord}
+						title="Match Whole Word"
+						onClick={() => setWholeWord((v) => !v)}
+					>
+						<WholeWord size={13} />
+					</ToggleButton>
+					<ToggleButton
+						active={regex}
+						title="Use Regular Expression"
+						onClick={() => setRegex((v) => !v)}
+					>
+						<Regex size={13} />
+					</ToggleButton>
+				</div>
+				{showReplace && (
+					<div className="flex items-center gap-0.5 bg-white/3 border border-white/6 focus-within:border-amber-400/30 rounded-md pl-2 pr-1 py-0.5">
+						<input
+							value={replacement}
+							onChange={(e) => setReplacement(e.target.value)}
+							onKeyDown={(e) => e.key === 'Enter' && runPreview()}
+							placeholder={regex ? 'Replace ($1 for groups)' : 'Replace'}
+							className="flex-1 bg-transparent text-[11.5px] text-neutral-200 placeholder-neutral-600 outline-none min-w-0"
+						/>
+						<button
+							onClick={runPreview}
+							disabled={!query || running}
+							title="Preview Replace"
+							className="cursor-pointer text-[10.5px] px-1.5 py-0.5 rounded text-neutral-400 hover:text-neutral-200 hover:bg-white/6 disabled:opacity-40 disabled:cursor-default transition-colors shrink-0"
+						>
+							Preview
+						</button>
+					</div>
+				)}
+				<input
+					value={include}
+					onChange={(e) => setInclude(e.target.value)}
+					onKeyDown={(e) => e.key === 'Enter' && runSearch()}
+					placeholder="files to include (e.g. src, *.tsx)"
+					className={inputClass}
+				/>
+				<input
+					value={exclude}
+					onChange={(e) => setExclude(e.target.value)}
+					onKeyDown={(e) => e.key === 'Enter' && runSearch()}
+					placeholder="files to exclude"
+					className={inputClass}
+				/>
+			</div>
+
+			{/* ── Status ── */}
+			<div className="px-3 py-1.5 text-[11px] text-neutral-500 flex items-center gap-1.5">
+				{running && <Loader2 size={11} className="animate-spin" />}
+				{error ? (
+					<span className="text-red-400/80">{error}</span>
+				) : preview ? (
+					<>
+						<span className="flex-1">
+							{selectedCount} of {preview.result.replacements} selected
+							{preview.result.truncated && ' (limit reached)'}
+						</span>
+						<button
+							onClick={() => setPreview(null)}
+							className="cursor-pointer px-1.5 py-0.5 rounded text-neutral-500 hover:text-neutral-300 hover:bg-white/6 transition-colors"
+						>
+							Discard
+						</button>
+						<button
+							onClick={runReplace}
+							disabled={selectedCount === 0 || applying}
+							className="cursor-pointer px-1.5 py-0.5 rounded text-amber-400 bg-amber-400/10 hover:bg-amber-400/20 disabled:opacity-40 disabled:cursor-default transition-colors"
+						>
+							{applying ? 'Replacing…' : 'Replace'}
+						</button>
+					</>
+				) : summary ? (
+					<span>
+						{summary.matches} result{summary.matches === 1 ? '' : 's'} in{' '}
+						{summary.files_matched} file
+						{summary.files_matched === 1 ? '' : 's'}
+						{summary.truncated && ' (limit reached)'}
+						{summary.cancelled && ' (stopped)'}
+					</span>
+				) : running ? (
+					<span>Searching…</span>
+				) : null}
+			</div>
+
+			{/* ── Results ── */}
+			<div className="flex-1 overflow-y-auto pb-2 px-1.5">
+				{preview && (
+					<ReplacePreviewList
+						files={preview.result.files}
+						selected={selected}
+						collapsed={collapsed}
+						onToggleCollapsed={toggleFile}
+						onToggleFile={toggleReplaceFile}
+						onToggleReplacement={toggleReplacement}
+						onOpen={openFile}
+					/>
+				)}
+				{!preview && results.map((file) => {
+					const { icon: FileIcn, color } = getFileIcon(
+						file.path.split('/').pop() ?? file.path,
+					);
+					const open = !collapsed.has(file.path);
+					return (
+						<div key={file.path}>
+							<div
+								role="button"
+								onClick={() => toggleFile(file.path)}
+								title={file.path}
+								className="w-full cursor-pointer flex items-center gap-1.5 py-0.75 px-2 hover:bg-white/4 transition-colors rounded-md"
+							>
+								<ChevronRight
+									size={12}
+									className={`transition-transform duration-150 text-neutral-500 shrink-0 ${open ? 'rotate-90' : ''}`}
+								/>
+								<FileIcn size={14} className="shrink-0" style={{ color }} />
+								<span className="flex-1 text-[12px] text-neutral-400 tracking-wide truncate">
+									{file.path}
+								</span>
+								<span className="text-[10px] text-neutral-500 bg-white/6 rounded-full px-1.5 shrink-0">
+									{file.matches.length}
+								</span>
+							</div>
+							{open &&
+								file.matches.map((match) => (
+									<div
+										key={`${match.line}:${match.column}`}
+										role="button"
+										onClick={() => openFile(file.path)}
+										title={`${file.path}:${match.line}:${match.column}`}
+										className="w-full cursor-pointer flex items-center gap-2 py-0.5 pl-9 pr-2 rounded-md text-[11.5px] text-neutral-500 hover:text-neutral-300 hover:bg-white/3 font-mono"
+									>
+										<span className="text-neutral-700 shrink-0 w-7 text-right">
+											{match.line}
+										</span>
+										<MatchLine match={match} />
+									</div>
+								))}
+						</div>
+					);
+				})}
+			</div>
+		</div>
+	);
+};
@@ -0,0 +1,106 @@
+import { ChevronRight } from 'lucide-react';
+import { FileReplacements, Replacement } from '@/models';
+import { getFileIcon } from '@/components/filetree';
+
+const ReplacementLine = ({ replacement }: { replacement: Replacement }) => {
+	// columns are 1-based character offsets
+	const chars = Array.from(replacement.text);
+	const start = replacement.column - 1;
+	const end = start + replacement.length;
+	const after = Array.from(replacement.replaced);
+	const inserted = after.slice(start, after.length - (chars.length - end)).join('');
+	// keep the change in view on long lines
+	const lead = Math.max(0, start - 24);
+
+	return (
+		<span className="truncate">
+			{lead > 0 && '…'}
+			{chars.slice(lead, start).join('')}
+			<span className="bg-red-400/20 text-red-300 line-through rounded-sm">
+				{chars.slice(start, end).join('')}
+			</span>
+			<span className="bg-emerald-400/20 text-emerald-300 rounded-sm">{inserted}</span>
+			{chars.slice(end).join('')}
+		</span>
+	);
+};
+
+export const ReplacePreviewList = ({
+	files,
+	selected,
+	collapsed,
+	onToggleCollapsed,
+	onToggleFile,
+	onToggleReplacement,
+	onOpen,
+}: {
+	files: FileReplacements[];
+	selected: Record<string, Set<number>>;
+	collapsed: Set<string>;
+	onToggleCollapsed: (path: string) => void;
+	onToggleFile: (file: FileReplacements) => void;
+	onToggleReplacement: (path: string, index: number) => void;
+	onOpen: (path: string) => void;
+}) => (
+	<>
+		{files.map((file) => {
+			const { icon: FileIcn, color } = getFileIcon(file.path.split('/').pop() ?? file.path);
+			const open = !collapsed.has(file.path);
+			const picked = selected[file.path]?.size ?? 0;
+			return (
+				<div key={file.path}>
+					<div
+						role="button"
+						onClick={() => onToggleCollapsed(file.path)}
+						title={file.path}
+						className="w-full cursor-pointer flex items-center gap-1.5 py-0.75 px-2 hover:bg-white/4 transition-colors rounded-md"
+					>
+						<ChevronRight
+							size={12}
+							className={`transition-transform duration-150 text-neutral-500 shrink-0 ${open ? 'rotate-90' : ''}`}
+						/>
+						<input
+							type="checkbox"
+							checked={picked === file.replacements.length}
+							ref={(el) => {
+								if (el) el.indeterminate = picked > 0 && picked < file.replacements.length;
+							}}
+							onClick={(e) => e.stopPropagation()}
+							onChange={() => onToggleFile(file)}
+							className="accent-amber-400 shrink-0"
+						/>
+						<FileIcn size={14} className="shrink-0" style={{ color }} />
+						<span className="flex-1 text-[12px] text-neutral-400 tracking-wide truncate">
+							{file.path}
+						</span>
+						<span className="text-[10px] text-neutral-500 bg-white/6 rounded-full px-1.5 shrink-0">
+							{picked}/{file.replacements.length}
+						</span>
+					</div>
+					{open &&
+						file.replacements.map((replacement) => (
+							<div
+								key={replacement.index}
+								role="button"
+								onClick={() => onOpen(file.path)}
+								title={`${file.path}:${replacement.line}:${replacement.column}`}
+								className="w-full cursor-pointer flex items-center gap-2 py-0.5 pl-7 pr-2 rounded-md text-[11.5px] text-neutral-500 hover:text-neutral-300 hover:bg-white/3 font-mono"
+							>
+								<input
+									type="checkbox"
+									checked={selected[file.path]?.has(replacement.index) ?? false}
+									onClick={(e) => e.stopPropagation()}
+									onChange={() => onToggleReplacement(file.path, replacement.index)}
+									className="accent-amber-400 shrink-0"
+								/>
+								<span className="text-neutral-700 shrink-0 w-7 text-right">
+									{replacement.line}
+								</span>
+								<ReplacementLine replacement={replacement} />
+							</div>
+						))}
+				</div>
+			);
+		})}
+	</>
+);
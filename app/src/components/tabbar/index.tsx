@@ -3,6 +3,7 @@ import { X } from "lucide-react";
 interface TabBarProps {
   tabs: string[];
   activeTab: string;
+  staleTabs?: Set<string>;   // changed on disk behind the open buffer
   onSelectTab: (tab: string) => void;
   onCloseTab: (tab: string) => void;
 }
@@ -14,12 +15,14 @@ const getFileName = (path: string) => {
 export const TabBar = ({
   tabs,
   activeTab,
+  staleTabs,
   onSelectTab,
   onCloseTab,
 }: TabBarProps) => (
   <div className="h-9.5 flex items-end bg-[#111113] border-b border-white/6 pl-1 shrink-0 overflow-x-auto">
     {tabs.map((tab) => {
       const isActive = tab === activeTab;
+      const isStale = !!staleTabs?.has(tab);
 
       return (
         <div
@@ -31,7 +34,11 @@ export const TabBar = ({
                 : "text-neutral-600 hover:text-neutral-400"
             }`}
         >
-          <span onClick={() => onSelectTab(tab)} className="truncate max-w-35">
+          <span
+            onClick={() => onSelectTab(tab)}
+            title={isStale ? "Changed on disk" : undefined}
+            className={`truncate max-w-35 ${isStale ? "italic text-amber-400/80" : ""}`}
+          >
             {getFileName(tab)}
           </span>
 
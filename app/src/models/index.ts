@@ -9,7 +9,9 @@ export interface FileNode {
   type: "file" | "folder";
   lang?: string;
   open?: boolean;
-  children?: FileNode[];
+  children?: FileNode[];    // undefined until the folder is listed
+  child_count?: number;     // entries in the folder, loaded or not
+  ignored?: boolean;   // matched by .gitignore/.editorignore; only sent when shown
 }
 
 export interface Project {
@@ -31,7 +33,7 @@ export interface Project {
 export type ProjectLogs = {
   runId:  string,
   action: "scaffold" | "edit" | "preview",
-  type: "file_write" | "status" | "done" | "error",
+  type: "file_write" | "status" | "done" | "error" | "cancelled",
   message: string,
   timestamp?: string
 }
@@ -42,10 +44,143 @@ export interface AgentFileEvent {
   content: string;
 }
 
+export interface AgentFileStagedEvent {
+  type: "file_staged";
+  path: string;
+}
+
+export interface AgentFileDeleteEvent {
+  type: "file_delete";
+  path: string;
+}
+
+export interface AgentFileRenameEvent {
+  type: "file_rename";
+  from: string;
+  to: string;
+}
+
+export interface AgentFilePatchEvent {
+  type: "file_patch";
+  path: string;
+  diff: string;     // unified diff
+}
+
+// 1-based, end-exclusive range in the file being edited
+export interface EditorSelection {
+  startLine:   number;
+  startColumn: number;
+  endLine:     number;
+  endColumn:   number;
+}
+
+export interface AgentConflictEvent {
+  type: "conflict";
+  path: string;
+  message: string;
+}
+
+export interface AgentPolicyViolationEvent {
+  type: "policy_violation";
+  path: string;
+  rule: "deny" | "allow" | "max_file_size" | "max_files";
+  message: string;
+}
+
+// per-project limits on what the agent may write
+export interface WritePolicy {
+  allow:         string[];
+  deny:          string[];
+  max_file_size: number;
+  max_files:     number;
+}
+
+export interface AgentUsage {
+  prompt_tokens:     number;
+  completion_tokens: number;
+  model:             string | null;
+  duration_ms:       number;
+  cost:              number | null;  // USD, when the provider reports it
+}
+
 export interface AgentDoneEvent {
   type: "done";
   summary: string;
   files: string[];    // list of paths written
+  usage?: AgentUsage; // totals for the whole run
+}
+
+export interface UsageEntry {
+  run_id:            string;
+  project_path:      string;
+  action:            string;
+  outcome:           string;
+  model:             string | null;
+  prompt_tokens:     number;
+  completion_tokens: number;
+  duration_ms:       number;
+  cost:              number | null;
+  timestamp:         string;
+}
+
+export interface UsageTotals {
+  runs:              number;
+  prompt_tokens:     number;
+  completion_tokens: number;
+  duration_ms:       number;
+  cost:              number;
+  unpriced_runs:     number;  // runs with unknown cost, left out of `cost`
+}
+
+export interface UsageSummary {
+  total:      UsageTotals;
+  by_project: Record<string, UsageTotals>;
+  by_day:     Record<string, UsageTotals>;  // YYYY-MM-DD, UTC
+  by_model:   Record<string, UsageTotals>;
+}
+
+export interface UsageBudget {
+  period:      "day" | "month";
+  soft_tokens: number | null;
+  hard_tokens: number | null;
+  soft_cost:   number | null;  // USD
+  hard_cost:   number | null;
+}
+
+export interface RecordedRun {
+  run_id:      string;
+  action:      string;
+  provider:    string;
+  staged:      boolean;
+  started_at:  string;
+  finished_at: string | null;
+  outcome:     string | null;  // null while running, or if the app quit mid-run
+  requests:    number;
+  frames:      number;
+  events:      number;
+}
+
+export type RunRecord =
+  | { kind: "start"; run_id: string; project_path: string; action: string; provider: string; staged: boolean; timestamp: string }
+  | { kind: "request"; endpoint: string; body: unknown; timestamp: string }
+  | { kind: "stream"; timestamp: string }  // a connection's chunks and frames follow
+  | { kind: "chunk"; data: string }       // response bytes as read, base64
+  | { kind: "frame"; event: string; id: string | null; data: string }
+  | { kind: "event"; event: AgentEvent }
+  | { kind: "outcome"; outcome: AgentEvent | null; timestamp: string };
+
+export interface ReplayReport {
+  scratch_path: string;
+  requests:     number;
+  frames:       number;
+  seeded:       boolean;  // false when replay started from an empty directory
+  outcome:      AgentEvent | null;
+}
+
+// USD per million tokens
+export interface ModelPrice {
+  input:  number;
+  output: number;
 }
 
 export interface AgentErrorEvent {
@@ -58,7 +193,202 @@ export interface AgentStatusEvent {
   message: string;
 }
 
-export type AgentEvent = AgentFileEvent | AgentDoneEvent | AgentStatusEvent | AgentErrorEvent;
+export interface AgentTextDeltaEvent {
+  type: "text_delta";
+  delta: string;
+}
+
+export interface AgentToolCallStartEvent {
+  type: "tool_call_start";
+  call_id: string;
+  tool: string;
+  path: string | null;
+}
+
+export interface AgentToolCallFinishEvent {
+  type: "tool_call_finish";
+  call_id: string;
+  tool: string;
+  path: string | null;
+  error: string | null;
+}
+
+export interface AgentProgressEvent {
+  type: "progress";
+  current: number;
+  total: number;
+  label: string | null;
+}
+
+export interface AgentDiagnosticEvent {
+  type: "diagnostic";
+  message: string;
+}
+
+// the backend rejected the API token (401/403)
+export interface AgentUnauthorizedEvent {
+  type: "unauthorized";
+  status: number;
+  message: string;
+}
+
+export interface CheckDiagnostic {
+  path: string | null;
+  line: number | null;
+  column: number | null;
+  code: string | null;
+  message: string;
+}
+
+export interface AgentCheckStartedEvent {
+  type: "check_started";
+  round: number;
+  command: string;
+}
+
+export interface AgentCheckFinishedEvent {
+  type: "check_finished";
+  round: number;
+  diagnostics: CheckDiagnostic[];
+}
+
+export interface AgentRepairStartedEvent {
+  type: "repair_started";
+  round: number;
+  max_rounds: number;
+  files: string[];
+}
+
+// a repair request finished; `done` is emitted once per run
+export interface AgentRepairFinishedEvent {
+  type: "repair_finished";
+  summary: string;
+  files: string[];
+}
+
+export interface AgentValidationFinishedEvent {
+  type: "validation_finished";
+  passed: boolean;
+  rounds: number;
+  remaining: number;      // diagnostics left when the loop stopped
+  reason: string | null;  // why validation was skipped or stopped early
+}
+
+export interface AgentResumableFailureEvent {
+  type: "resumable_failure";
+  message: string;
+  last_event_id: string | null;
+  attempts: number;
+  applied_files: string[];
+}
+
+export interface AgentCancelledEvent {
+  type: "cancelled";
+}
+
+export type AgentEvent = AgentFileEvent | AgentFileStagedEvent | AgentFileDeleteEvent | AgentFileRenameEvent
+  | AgentFilePatchEvent | AgentConflictEvent | AgentPolicyViolationEvent | AgentDoneEvent | AgentStatusEvent | AgentErrorEvent | AgentTextDeltaEvent
+  | AgentToolCallStartEvent | AgentToolCallFinishEvent | AgentProgressEvent | AgentDiagnosticEvent | AgentResumableFailureEvent | AgentCancelledEvent | AgentUnauthorizedEvent
+  | AgentCheckStartedEvent | AgentCheckFinishedEvent | AgentRepairStartedEvent | AgentRepairFinishedEvent
+  | AgentValidationFinishedEvent;
+
+// every event emitted by the backend is tagged with the run it belongs to
+export type AgentRunEvent = AgentEvent & {
+  run_id: string;
+  project_path: string;
+};
+
+// one page of `list_directory`
+export interface DirectoryPage {
+  entries: FileNode[];
+  total: number;
+  next_offset: number | null;
+}
+
+// search_project; globs are project-relative, a directory glob covers its contents
+export interface SearchSpec {
+  query: string;
+  regex?: boolean;
+  caseSensitive?: boolean;
+  wholeWord?: boolean;
+  include?: string[];
+  exclude?: string[];
+  contextLines?: number;
+  maxResults?: number;
+}
+
+export interface SearchMatch {
+  line: number;     // 1-based
+  column: number;   // 1-based, in characters
+  length: number;
+  text: string;
+  before: string[];
+  after: string[];
+}
+
+export interface FileMatches {
+  path: string;
+  matches: SearchMatch[];
+}
+
+export interface SearchSummary {
+  files_searched: number;
+  files_matched: number;
+  matches: number;
+  truncated: boolean;
+  cancelled: boolean;
+}
+
+// replace_in_project; a preview is made against the file content with `hash`
+export interface Replacement {
+  index: number;    // what a selection refers to
+  line: number;     // 1-based
+  column: number;   // 1-based, in characters
+  length: number;
+  text: string;
+  replaced: string; // the line with only this replacement made
+}
+
+export interface FileReplacements {
+  path: string;
+  hash: string;
+  replacements: Replacement[];
+  diff: string;
+}
+
+export interface ReplacePreview {
+  files: FileReplacements[];
+  replacements: number;
+  truncated: boolean;
+}
+
+export interface ReplaceSelection {
+  path: string;
+  hash: string;
+  indices?: number[]; // all of the file's replacements when absent
+}
+
+export interface ReplaceResult {
+  undo_id: string;
+  files: string[];
+  replacements: number;
+}
+
+export type ReplaceOutcome =
+  | ({ type: "preview" } & ReplacePreview)
+  | ({ type: "applied" } & ReplaceResult);
+
+// a change on disk reported by the project watcher; paths are project-relative
+export type FsChange =
+  | { type: "create"; path: string; is_dir: boolean }
+  | { type: "modify"; path: string }
+  | { type: "delete"; path: string }
+  | { type: "rename"; from: string; to: string };
+
+export interface FsEvent {
+  project_path: string;
+  changes: FsChange[];
+}
 
 export interface IAppState {
   freshRead: boolean,
@@ -69,4 +399,6 @@ export interface IAppState {
   agentRunning: boolean;
   expoRunning: boolean;
   unsavedPaths: Set<string>;
+  stalePaths: Set<string>;   // open tabs changed or removed on disk behind an unsaved buffer
+  showIgnored: boolean;      // list ignored entries in the tree, greyed out
 }
\ No newline at end of file
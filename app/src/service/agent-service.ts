@@ -1,4 +1,7 @@
-import { AgentEvent } from "@/models";
+import {
+  AgentRunEvent, EditorSelection, ModelPrice, RecordedRun, ReplayReport, RunRecord, UsageBudget,
+  UsageEntry, UsageSummary, WritePolicy,
+} from "@/models";
 import { useAppStore } from "@/store";
 import { invoke } from "@tauri-apps/api/core";
 import { listen, UnlistenFn } from "@tauri-apps/api/event";
@@ -9,6 +12,15 @@ export interface ScaffoldAgentParams {
   appName:     string;
   brandColor:  string;
   imageUrls:   string[];
+  staged?:     boolean;
+  threadId?:   string;
+  validate?:   boolean;
+  checkCommand?:    string;
+  maxRepairRounds?: number;
+  model?:       string;
+  temperature?: number;
+  maxTokens?:   number;
+  overSoftBudget?: boolean;  // start even though the soft budget is spent
 }
 
 export interface EditorAgentParams {
@@ -16,6 +28,18 @@ export interface EditorAgentParams {
   prompt:       string;
   relativePath: string;
   content:      string;
+  staged?:      boolean;
+  threadId?:    string;
+  pinnedFiles?: string[];
+  contextBudget?: number;
+  selection?:   EditorSelection;
+  validate?:    boolean;
+  checkCommand?:    string;
+  maxRepairRounds?: number;
+  model?:       string;
+  temperature?: number;
+  maxTokens?:   number;
+  overSoftBudget?: boolean;  // start even though the soft budget is spent
 }
 
 const runAgentOperation = async (
@@ -24,7 +48,7 @@ const runAgentOperation = async (
   params:     Record<string, unknown>,
 ): Promise<void> => {
   const runId = crypto.randomUUID();
-  const { setAgentRunning, onAgentFileWrite, loadFileTree, addProjectLog } =
+  const { setAgentRunning, onAgentFileWrite, loadFileTree, addProjectLog, closeTab } =
     useAppStore.getState();
 
   setAgentRunning(true);
@@ -32,36 +56,211 @@ const runAgentOperation = async (
 
   let unlistenFn: UnlistenFn | null = null;
 
+  // assistant text arrives token by token; log it a line at a time
+  let pendingText = "";
+  const flushText = (force = false) => {
+    const lines = pendingText.split("\n");
+    pendingText = force ? "" : lines.pop() ?? "";
+    for (const line of lines) {
+      if (line.trim()) addProjectLog({ runId, action, type: "status", message: line });
+    }
+  };
+
+  // with validation on, the run only ends once the repair loop reports back
+  const validate = !!params.validate;
+
   const cleanup = () => {
     unlistenFn?.();
     setAgentRunning(false);
   };
 
-  unlistenFn = await listen<AgentEvent>("agent_event", ({ payload }) => {
+  unlistenFn = await listen<AgentRunEvent>("agent_event", ({ payload }) => {
+    // other runs (another project, a parallel edit) share this channel
+    if (payload.run_id !== runId) return;
+
     switch (payload.type) {
       case "file_write":
         addProjectLog({ runId, action, type: "file_write", message: `Wrote ${payload.path}` });
         onAgentFileWrite(payload.path, payload.content);
         break;
 
+      case "file_delete":
+        addProjectLog({ runId, action, type: "file_write", message: `Deleted ${payload.path}` });
+        closeTab(payload.path);
+        break;
+
+      case "file_rename":
+        addProjectLog({ runId, action, type: "file_write", message: `Renamed ${payload.from} to ${payload.to}` });
+        closeTab(payload.from);
+        break;
+
+      case "file_patch":
+        addProjectLog({ runId, action, type: "file_write", message: `Patched ${payload.path}` });
+        invoke<string>("read_file", { projectPath: payload.project_path, filePath: payload.path })
+          .then((content) => onAgentFileWrite(payload.path, content))
+          .catch(console.error);
+        break;
+
+      case "conflict":
+        addProjectLog({ runId, action, type: "status", message: `Conflict in ${payload.path}: ${payload.message}` });
+        break;
+
+      case "policy_violation":
+        addProjectLog({ runId, action, type: "error", message: `Blocked write to ${payload.path}: ${payload.message}` });
+        break;
+
+      case "file_staged":
+        addProjectLog({ runId, action, type: "file_write", message: `Staged ${payload.path} for review` });
+        break;
+
       case "status":
         addProjectLog({ runId, action, type: "status", message: payload.message });
         break;
 
+      case "text_delta":
+        pendingText += payload.delta;
+        flushText();
+        break;
+
+      case "tool_call_start":
+        flushText(true);
+        addProjectLog({
+          runId,
+          action,
+          type: "status",
+          message: payload.path ? `${payload.tool}: ${payload.path}` : `${payload.tool}...`,
+        });
+        break;
+
+      case "tool_call_finish":
+        if (payload.error) {
+          addProjectLog({ runId, action, type: "status", message: `${payload.tool} failed: ${payload.error}` });
+        }
+        break;
+
+      case "progress":
+        addProjectLog({
+          runId,
+          action,
+          type: "status",
+          message: `${payload.label ?? "Step"} ${payload.current} of ${payload.total}`,
+        });
+        break;
+
+      case "diagnostic":
+        console.warn(`[agent ${runId}] ${payload.message}`);
+        addProjectLog({ runId, action, type: "status", message: `Stream warning: ${payload.message}` });
+        break;
+
       case "done":
+        flushText(true);
         addProjectLog({ runId, action, type: "done", message: payload.summary });
+        if (payload.usage) {
+          const { prompt_tokens, completion_tokens, model, duration_ms, cost } = payload.usage;
+          addProjectLog({
+            runId, action, type: "status",
+            message: `${prompt_tokens + completion_tokens} tokens`
+              + (model ? ` · ${model}` : "")
+              + ` · ${(duration_ms / 1000).toFixed(1)}s`
+              + (cost !== null ? ` · $${cost.toFixed(4)}` : ""),
+          });
+        }
         loadFileTree();
-        cleanup();
+        if (!validate) cleanup();
         break;
 
       case "error":
         addProjectLog({ runId, action, type: "error", message: payload.message });
+        if (!validate) cleanup();
+        break;
+
+      case "resumable_failure":
+        addProjectLog({
+          runId,
+          action,
+          type: "error",
+          message: `Connection lost after ${payload.attempts} retries (${payload.message}). ${payload.applied_files.length} file(s) were written.`,
+        });
+        loadFileTree();
+        if (!validate) cleanup();
+        break;
+
+      case "unauthorized":
+        addProjectLog({ runId, action, type: "error", message: payload.message });
+        if (!validate) cleanup();
+        break;
+
+      case "check_started":
+        addProjectLog({ runId, action, type: "status", message: `Type-checking (${payload.command})...` });
+        break;
+
+      case "check_finished":
+        addProjectLog({
+          runId,
+          action,
+          type: "status",
+          message: payload.diagnostics.length
+            ? `Type check found ${payload.diagnostics.length} error(s)`
+            : "Type check passed",
+        });
+        break;
+
+      case "repair_started":
+        addProjectLog({
+          runId,
+          action,
+          type: "status",
+          message: `Repair round ${payload.round} of ${payload.max_rounds}: ${payload.files.join(", ")}`,
+        });
+        break;
+
+      case "repair_finished":
+        flushText(true);
+        addProjectLog({ runId, action, type: "status", message: `Repair: ${payload.summary}` });
+        break;
+
+      case "validation_finished":
+        addProjectLog({
+          runId,
+          action,
+          type: payload.passed ? "done" : "error",
+          message: payload.passed
+            ? `Type check passed after ${payload.rounds} repair round(s)`
+            : payload.reason ?? `${payload.remaining} type error(s) left after ${payload.rounds} repair round(s)`,
+        });
+        loadFileTree();
+        cleanup();
+        break;
+
+      case "cancelled":
+        addProjectLog({ runId, action, type: "cancelled", message: "Agent run cancelled" });
+        loadFileTree();
         cleanup();
         break;
     }
   });
 
-  await invoke(command, params).catch((e) => {
+  const {
+    staged, threadId, pinnedFiles, contextBudget, checkCommand, maxRepairRounds,
+    model, temperature, maxTokens, overSoftBudget, ...args
+  } = params;
+  delete args.validate;
+  const options = {
+    runId,
+    staged: !!staged,
+    threadId,
+    pinnedFiles: pinnedFiles ?? [],
+    contextBudget,
+    validate,
+    checkCommand,
+    maxRepairRounds,
+    model,
+    temperature,
+    maxTokens,
+    overSoftBudget: !!overSoftBudget,
+  };
+
+  await invoke(command, { ...args, options }).catch((e) => {
     addProjectLog({ runId, action, type: "error", message: `Failed to start agent: ${e}` });
     cleanup();
   });
@@ -71,4 +270,71 @@ export const scaffoldAgentOperation = (params: ScaffoldAgentParams) =>
   runAgentOperation("scaffold", "scaffold_project", { ...params });
 
 export const editorAgentOperation = (params: EditorAgentParams) =>
-  runAgentOperation("edit", "edit_project_file", { ...params });
\ No newline at end of file
+  runAgentOperation("edit", "edit_project_file", { ...params });
+
+export interface ConversationSummary {
+  id:          string;
+  title:       string;
+  turns:       number;
+  created_at:  string;
+  updated_at:  string;
+  forked_from: string | null;
+}
+
+export const listConversations = (projectPath: string) =>
+  invoke<ConversationSummary[]>("list_conversations", { projectPath });
+
+export const continueConversation = (projectPath: string, threadId: string) =>
+  invoke("continue_conversation", { projectPath, threadId });
+
+export const forkConversation = (projectPath: string, threadId: string, turns?: number) =>
+  invoke<{ id: string }>("fork_conversation", { projectPath, threadId, turns });
+
+export const clearConversation = (projectPath: string, threadId?: string) =>
+  invoke("clear_conversation", { projectPath, threadId });
+
+export const getWritePolicy = (projectPath: string) =>
+  invoke<WritePolicy>("get_write_policy", { projectPath });
+
+export const setWritePolicy = (projectPath: string, policy: WritePolicy) =>
+  invoke("set_write_policy", { projectPath, policy });
+
+export const resetWritePolicy = (projectPath: string) =>
+  invoke("reset_write_policy", { projectPath });
+
+export const usageSummary = (projectPath?: string, since?: string, until?: string) =>
+  invoke<UsageSummary>("usage_summary", { projectPath, since, until });
+
+export const listUsage = (projectPath: string) =>
+  invoke<UsageEntry[]>("list_usage", { projectPath });
+
+export const getUsageBudget = (projectPath: string) =>
+  invoke<UsageBudget | null>("get_usage_budget", { projectPath });
+
+export const setUsageBudget = (projectPath: string, budget: UsageBudget | null) =>
+  invoke("set_usage_budget", { projectPath, budget });
+
+export const getModelPrices = () =>
+  invoke<Record<string, ModelPrice>>("get_model_prices");
+
+export const setModelPrices = (prices: Record<string, ModelPrice>) =>
+  invoke("set_model_prices", { prices });
+
+export const listAgentRuns = (projectPath: string) =>
+  invoke<RecordedRun[]>("list_agent_runs", { projectPath });
+
+export const getAgentRunLog = (projectPath: string, runId: string) =>
+  invoke<RunRecord[]>("get_agent_run_log", { projectPath, runId });
+
+// replays a recorded backend stream into an empty scratch directory, offline
+export const replayAgentRun = (projectPath: string, runId: string, scratchPath?: string) =>
+  invoke<ReplayReport>("replay_agent_run", { projectPath, runId, scratchPath });
+
+export const cancelAgentRun = (runId: string) =>
+  invoke("cancel_agent_run", { runId });
+
+export const undoAgentRun = async (runId: string) => {
+  const restored = await invoke<string[]>("undo_agent_run", { runId });
+  useAppStore.getState().loadFileTree();
+  return restored;
+};
\ No newline at end of file
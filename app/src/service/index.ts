@@ -1,2 +1,4 @@
 export { scaffoldAgentOperation } from "./agent-service";
-export { publishToSnack } from "./snack-service";
\ No newline at end of file
+export { publishToSnack } from "./snack-service";
+export { watchProject } from "./project-service";
+export { searchProject, previewReplace, applyReplace, undoReplace } from "./search-service";
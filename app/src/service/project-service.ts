@@ -0,0 +1,27 @@
+import { FsChange, FsEvent } from "@/models";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+// Watches a project for changes made outside the editor (npm install, git,
+// another editor). Resolves to a function that stops watching.
+export const watchProject = async (
+  projectPath: string,
+  onChanges: (changes: FsChange[]) => void,
+): Promise<() => void> => {
+  const unlisten = await listen<FsEvent>("project_fs_event", ({ payload }) => {
+    if (payload.project_path !== projectPath) return;
+    onChanges(payload.changes);
+  });
+
+  try {
+    await invoke("watch_project", { projectPath });
+  } catch (err) {
+    unlisten();
+    throw err;
+  }
+
+  return () => {
+    unlisten();
+    invoke("unwatch_project", { projectPath }).catch(console.error);
+  };
+};
@@ -0,0 +1,69 @@
+import {
+  FileMatches,
+  ReplaceOutcome,
+  ReplacePreview,
+  ReplaceResult,
+  ReplaceSelection,
+  SearchSpec,
+  SearchSummary,
+} from "@/models";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+export interface RunningSearch {
+  searchId: string;
+  // resolves once the search ends, hits the result cap or is cancelled
+  done:   Promise<SearchSummary>;
+  cancel: () => void;
+}
+
+// Matches arrive a file at a time through onFile while the search runs.
+export const searchProject = async (
+  projectPath: string,
+  spec:        SearchSpec,
+  onFile:      (file: FileMatches) => void,
+): Promise<RunningSearch> => {
+  const searchId = crypto.randomUUID();
+
+  const unlisten = await listen<FileMatches & { search_id: string }>("search_event", ({ payload }) => {
+    if (payload.search_id !== searchId) return;
+    onFile({ path: payload.path, matches: payload.matches });
+  });
+
+  const done = invoke<SearchSummary>("search_project", { projectPath, searchId, spec })
+    .finally(unlisten);
+
+  return {
+    searchId,
+    done,
+    cancel: () => {
+      invoke("cancel_search", { searchId }).catch(() => {}); // already finished
+    },
+  };
+};
+
+// $1 and ${name} in the replacement expand only for regex searches
+export const previewReplace = async (
+  projectPath: string,
+  spec:        SearchSpec,
+  replacement: string,
+): Promise<ReplacePreview> => {
+  const outcome = await invoke<ReplaceOutcome>("replace_in_project", { projectPath, spec, replacement });
+  if (outcome.type !== "preview") throw new Error("Unexpected replace outcome");
+  return outcome;
+};
+
+// fails without writing anything if a selected file changed since the preview
+export const applyReplace = async (
+  projectPath: string,
+  spec:        SearchSpec,
+  replacement: string,
+  selection:   ReplaceSelection[],
+): Promise<ReplaceResult> => {
+  const outcome = await invoke<ReplaceOutcome>("replace_in_project", { projectPath, spec, replacement, selection });
+  if (outcome.type !== "applied") throw new Error("Unexpected replace outcome");
+  return outcome;
+};
+
+export const undoReplace = (undoId: string) =>
+  invoke<string[]>("undo_replace", { undoId });
@@ -1,6 +1,14 @@
 import { create } from 'zustand';
 import { persist } from 'zustand/middleware';
-import { CodeFile, FileNode, IAppState, Project, ProjectLogs } from '../models';
+import {
+	CodeFile,
+	DirectoryPage,
+	FileNode,
+	FsChange,
+	IAppState,
+	Project,
+	ProjectLogs,
+} from '../models';
 import { tauriStorage } from '@/lib/persistence';
 import { toast } from 'sonner';
 import { invoke } from '@tauri-apps/api/core';
@@ -13,11 +21,99 @@ export const tauriInvoke = async <T>(cmd: string, args?: any): Promise<T> => {
 	return invoke<T>(cmd, args);
 };
 
+// Content last written by the editor itself, so the watcher event our own
+// save triggers isn't mistaken for an outside change.
+const savedContent = new Map<string, string>();
+
+// Folders are listed a page at a time; the root is always listed in full.
+const DIRECTORY_PAGE = 200;
+
+const listDirectory = (
+	projectPath: string,
+	dirPath: string,
+	showIgnored: boolean,
+	offset: number,
+	limit: number,
+) =>
+	tauriInvoke<DirectoryPage>('list_directory', {
+		projectPath,
+		dirPath,
+		offset,
+		limit,
+		showIgnored,
+	});
+
+// Lists `dirPath` again, at least as far as `previous` had got, and
+// re-lists every folder under it that had been loaded.
+const relistDirectory = async (
+	projectPath: string,
+	dirPath: string,
+	showIgnored: boolean,
+	previous: FileNode[] | undefined,
+	full = false,
+): Promise<FileNode[]> => {
+	const want = Math.max(DIRECTORY_PAGE, previous?.length ?? 0);
+	const entries: FileNode[] = [];
+	let offset: number | null = 0;
+	while (offset !== null && (full || entries.length < want)) {
+		const page: DirectoryPage = await listDirectory(
+			projectPath,
+			dirPath,
+			showIgnored,
+			offset,
+			full ? DIRECTORY_PAGE : want - entries.length,
+		);
+		entries.push(...page.entries);
+		offset = page.next_offset;
+	}
+
+	return Promise.all(
+		entries.map(async (entry) => {
+			const loaded = previous?.find((p) => p.path === entry.path)?.children;
+			if (entry.type !== 'folder' || !loaded) return entry;
+			return {
+				...entry,
+				children: await relistDirectory(
+					projectPath,
+					entry.path,
+					showIgnored,
+					loaded,
+				),
+			};
+		}),
+	);
+};
+
+const updateNode = (
+	nodes: FileNode[],
+	path: string,
+	update: (node: FileNode) => FileNode,
+): FileNode[] =>
+	nodes.map((node) => {
+		if (node.path === path) return update(node);
+		if (node.children && path.startsWith(`${node.path}/`)) {
+			return { ...node, children: updateNode(node.children, path, update) };
+		}
+		return node;
+	});
+
+const findNode = (nodes: FileNode[], path: string): FileNode | undefined => {
+	for (const node of nodes) {
+		if (node.path === path) return node;
+		if (node.children && path.startsWith(`${node.path}/`)) {
+			return findNode(node.children, path);
+		}
+	}
+	return undefined;
+};
+
 type AppActions = {
 	addProject: (meta: Project) => void;
 	removeProject: (path: string, deleteFromDisk?: boolean) => Promise<void>;
 	setCurrentProject: (project: Project | null) => void;
 	loadFileTree: () => Promise<void>;
+	loadDirectory: (dirPath: string, more?: boolean) => Promise<void>;
+	setShowIgnored: (show: boolean) => Promise<void>;
 	loadFiles: () => Promise<void>;
 	closeProject: () => void;
 	openFile: (path: string) => Promise<void>;
@@ -26,6 +122,7 @@ type AppActions = {
 	closeTab: (path: string) => void;
 	saveActiveFile: () => Promise<void>;
 	onAgentFileWrite: (path: string, content: string) => void;
+	applyFsChanges: (changes: FsChange[]) => Promise<void>;
 	setAgentRunning: (running: boolean) => void;
 	setExpoRunning: (running: boolean) => void;
 	addProjectLog: (entry: ProjectLogs) => void;
@@ -41,6 +138,8 @@ const initialState: IAppState = {
 	agentRunning: false,
 	expoRunning: false,
 	unsavedPaths: new Set<string>(),
+	stalePaths: new Set<string>(),
+	showIgnored: false,
 	freshRead: false,
 };
 
@@ -140,28 +239,21 @@ export const useAppStore = create<IAppState & AppActions>()(
 					return IMAGE_EXTENSIONS.has(ext);
 				};
 
-				const collectFiles = (nodes: FileNode[]): string[] => {
-					const paths: string[] = [];
-					for (const node of nodes) {
-						if (node.children && node.children.length > 0) {
-							paths.push(...collectFiles(node.children));
-						} else if (!isBinaryFile(node.path)) {
-							paths.push(node.path);
-						}
-					}
-					return paths;
-				};
-
-				// Refresh tree first so we walk the latest file structure
+				// Refresh tree first so it matches the files we read
 				await get().loadFileTree();
 
 				// Re-read state after tree update
 				const { currentProject: refreshed } = get();
 				if (!refreshed) return;
 
-				const paths = collectFiles(refreshed.tree ?? []);
-
 				try {
+					// the tree is loaded lazily, so ask for the full list
+					const paths = (
+						await tauriInvoke<string[]>('list_files', {
+							projectPath: refreshed.path,
+						})
+					).filter((path) => !isBinaryFile(path));
+
 					const files: CodeFile[] = await Promise.all(
 						paths.map(async (filePath) => {
 							const content = await tauriInvoke<string>(
@@ -184,25 +276,66 @@ export const useAppStore = create<IAppState & AppActions>()(
 			},
 
 			loadFileTree: async () => {
-				const { currentProject } = get();
+				const { currentProject, showIgnored } = get();
 				if (!currentProject) return;
 
 				try {
 					// In browser, just keep whatever tree we already have
 					if (!isTauri()) return;
 
-					const tree = await tauriInvoke<FileNode[]>(
-						'get_file_tree',
-						{
-							projectPath: currentProject.path,
-						},
+					const tree = await relistDirectory(
+						currentProject.path,
+						'',
+						showIgnored,
+						currentProject.tree,
+						true,
 					);
-					set({ currentProject: { ...currentProject, tree } });
+					const { currentProject: latest } = get();
+					if (latest?.path !== currentProject.path) return;
+					set({ currentProject: { ...latest, tree } });
 				} catch (err: any) {
 					toast.error(err, { position: 'top-center' });
 				}
 			},
 
+			loadDirectory: async (dirPath, more = false) => {
+				const { currentProject, showIgnored } = get();
+				if (!currentProject || !isTauri()) return;
+
+				const loaded = more
+					? (findNode(currentProject.tree, dirPath)?.children ?? [])
+					: [];
+
+				try {
+					const page = await listDirectory(
+						currentProject.path,
+						dirPath,
+						showIgnored,
+						loaded.length,
+						DIRECTORY_PAGE,
+					);
+					const { currentProject: latest } = get();
+					if (latest?.path !== currentProject.path) return;
+					set({
+						currentProject: {
+							...latest,
+							tree: updateNode(latest.tree, dirPath, (node) => ({
+								...node,
+								children: [...loaded, ...page.entries],
+								child_count: page.total,
+							})),
+						},
+					});
+				} catch (err: any) {
+					toast.error(err, { position: 'top-center' });
+				}
+			},
+
+			setShowIgnored: async (show) => {
+				set({ showIgnored: show });
+				await get().loadFileTree();
+			},
+
 			closeProject: () => {
 				set({
 					currentProject: null,
@@ -211,6 +344,7 @@ export const useAppStore = create<IAppState & AppActions>()(
 					agentRunning: false,
 					expoRunning: false,
 					unsavedPaths: new Set(),
+					stalePaths: new Set(),
 				});
 			},
 
@@ -299,15 +433,19 @@ export const useAppStore = create<IAppState & AppActions>()(
 			},
 
 			closeTab: (path) => {
-				const { openTabs, activeFile, unsavedPaths } = get();
+				const { openTabs, activeFile, unsavedPaths, stalePaths } =
+					get();
 				const next = openTabs.filter((t) => t !== path);
 
 				const nextUnsaved = new Set(unsavedPaths);
 				nextUnsaved.delete(path);
+				const nextStale = new Set(stalePaths);
+				nextStale.delete(path);
 
 				set({
 					openTabs: next,
 					unsavedPaths: nextUnsaved,
+					stalePaths: nextStale,
 					activeFile:
 						activeFile?.path === path
 							? next.length > 0
@@ -327,6 +465,7 @@ export const useAppStore = create<IAppState & AppActions>()(
 
 				try {
 					if (isTauri()) {
+						savedContent.set(activeFile.path, activeFile.content);
 						await tauriInvoke('save_file', {
 							projectPath: currentProject.path,
 							filePath: activeFile.path,
@@ -335,7 +474,10 @@ export const useAppStore = create<IAppState & AppActions>()(
 					}
 					const next = new Set(unsavedPaths);
 					next.delete(activeFile.path);
-					set({ unsavedPaths: next });
+					// saving over an outside change is the user's call
+					const nextStale = new Set(get().stalePaths);
+					nextStale.delete(activeFile.path);
+					set({ unsavedPaths: next, stalePaths: nextStale });
 				} catch (err: any) {
 					toast.error(err, { position: 'top-center' });
 				}
@@ -370,6 +512,99 @@ export const useAppStore = create<IAppState & AppActions>()(
 				});
 			},
 
+			applyFsChanges: async (changes) => {
+				const { currentProject } = get();
+				if (!currentProject) return;
+
+				// only content changes leave the tree as it is
+				if (changes.some((c) => c.type !== 'modify')) {
+					get().loadFileTree();
+				}
+
+				const markStale = (path: string, message: string) => {
+					const { stalePaths } = get();
+					if (stalePaths.has(path)) return;
+					set({ stalePaths: new Set([...stalePaths, path]) });
+					toast.warning(message, { position: 'top-center' });
+				};
+
+				const refresh = async (path: string) => {
+					let content: string;
+					try {
+						content = await tauriInvoke<string>('read_file', {
+							projectPath: currentProject.path,
+							filePath: path,
+						});
+					} catch {
+						return; // gone again, or binary
+					}
+
+					const { currentProject: project, activeFile, unsavedPaths } =
+						get();
+					if (!project) return;
+					const buffer = project.files.find((f) => f.path === path);
+					if (buffer?.content === content) return;
+					if (savedContent.get(path) === content) return;
+
+					if (unsavedPaths.has(path)) {
+						markStale(
+							path,
+							`${path} changed on disk; saving will overwrite it`,
+						);
+						return;
+					}
+
+					const file: CodeFile = { path, content };
+					const nextStale = new Set(get().stalePaths);
+					nextStale.delete(path);
+					set({
+						currentProject: {
+							...project,
+							files: buffer
+								? project.files.map((f) =>
+										f.path === path ? file : f,
+									)
+								: [...project.files, file],
+						},
+						activeFile:
+							activeFile?.path === path ? file : activeFile,
+						stalePaths: nextStale,
+					});
+				};
+
+				for (const change of changes) {
+					const { openTabs } = get();
+					switch (change.type) {
+						case 'create':
+						case 'modify':
+							if (openTabs.includes(change.path)) {
+								await refresh(change.path);
+							}
+							break;
+						case 'delete':
+							if (openTabs.includes(change.path)) {
+								markStale(
+									change.path,
+									`${change.path} was deleted on disk`,
+								);
+							}
+							break;
+						case 'rename':
+							if (openTabs.includes(change.from)) {
+								markStale(
+									change.from,
+									`${change.from} was moved to ${change.to}`,
+								);
+							}
+							// editors and git often save by renaming over the file
+							if (openTabs.includes(change.to)) {
+								await refresh(change.to);
+							}
+							break;
+					}
+				}
+			},
+
 			setAgentRunning: (running) => set({ agentRunning: running }),
 			setExpoRunning: (running) => set({ expoRunning: running }),
 
@@ -424,7 +659,10 @@ export const useAppStore = create<IAppState & AppActions>()(
 		{
 			name: 'app-store',
 			storage: tauriStorage,
-			partialize: (state) => ({ projects: state.projects }),
+			partialize: (state) => ({
+				projects: state.projects,
+				showIgnored: state.showIgnored,
+			}),
 		},
 	),
 );
@@ -9,6 +9,15 @@ export interface AppSettings {
 	aws_secret_access_key: string;
 	aws_region: string;
 	aws_bucket: string;
+	api_token: string;
+	api_auth_header: string; // empty sends `Authorization: Bearer <token>`
+	agent_provider: 'backend' | 'openai';
+	openai_base_url: string; // e.g. http://localhost:11434/v1
+	openai_model: string;
+	openai_api_key: string;
+	model: string; // empty lets the provider choose
+	temperature: number | null;
+	max_tokens: number | null;
 }
 
 export const InitialSettings: AppSettings = {
@@ -19,8 +28,40 @@ export const InitialSettings: AppSettings = {
 	aws_secret_access_key: '',
 	aws_region: '',
 	aws_bucket: '',
+	api_token: '',
+	api_auth_header: '',
+	agent_provider: 'backend',
+	openai_base_url: '',
+	openai_model: '',
+	openai_api_key: '',
+	model: '',
+	temperature: null,
+	max_tokens: null,
 };
 
+export interface BackendStatus {
+	reachable: boolean;
+	latency_ms: number | null;
+	protocol_version: number | null;
+	server_version: string | null;
+	compatible: boolean;
+	message: string;
+}
+
+// probes the agent backend; pass unsaved settings to test them before saving
+export const checkBackend = (settings?: AppSettings) =>
+	tauriInvoke<BackendStatus>('check_backend', { settings });
+
+export interface ModelInfo {
+	id: string;
+	name: string | null;
+	description: string | null;
+	context_length: number | null;
+}
+
+// models the provider offers; pass unsaved settings to ask with them
+export const listModels = (settings?: AppSettings) =>
+	tauriInvoke<ModelInfo[]>('list_models', { settings });
 
 const STORAGE_KEY = 'app_settings';
 